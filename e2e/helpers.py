@@ -1,4 +1,6 @@
 import os
+import re
+import unicodedata
 import pexpect
 import tempfile
 import time
@@ -10,6 +12,106 @@ DEFAULT_DELAY = 0.0  # unified zero delay for deterministic tests
 DEFAULT_POLL_TIMEOUT = 0.5
 SHORT_SLEEP = 0.02
 
+class VirtualTerminal:
+    """Keeps the screen evi draws, by interpreting the escape sequences it writes.
+
+    evi only writes the cells that changed, so the output of one key press
+    does not contain the whole screen. Attached as ``logfile_read`` of a
+    pexpect child, this sees everything the child writes.
+    """
+
+    def __init__(self, rows: int, cols: int):
+        self.rows = rows
+        self.cols = cols
+        self.grid = [[" "] * cols for _ in range(rows)]
+        self.row = 0
+        self.col = 0
+        self.top = 0
+        self.bottom = rows
+        self.pending = ""
+
+    def write(self, data: str) -> None:
+        data = self.pending + data
+        self.pending = ""
+        i = 0
+        while i < len(data):
+            ch = data[i]
+            if ch == "\x1b":
+                m = CSI.match(data, i)
+                if m is None:
+                    if len(data) - i < 16 and not ESC_OTHER.match(data, i):
+                        self.pending = data[i:]
+                        return
+                    other = ESC_OTHER.match(data, i)
+                    i = other.end() if other else i + 1
+                    continue
+                self._csi(m.group(1), m.group(2))
+                i = m.end()
+                continue
+            if ch == "\r":
+                self.col = 0
+            elif ch == "\n":
+                self.row = min(self.row + 1, self.rows - 1)
+            elif ch >= " ":
+                width = 2 if unicodedata.east_asian_width(ch) in ("W", "F") else 1
+                if self.row < self.rows and self.col + width <= self.cols:
+                    self.grid[self.row][self.col] = ch
+                    if width == 2:
+                        self.grid[self.row][self.col + 1] = ""
+                self.col += width
+            i += 1
+
+    def flush(self) -> None:
+        pass
+
+    def _csi(self, params: str, final: str) -> None:
+        if params.startswith("?"):
+            return
+        args = [int(p) if p else 0 for p in params.split(";")] if params else []
+        first = args[0] if args else 0
+        if final in "Hf":
+            self.row = min(max(first, 1), self.rows) - 1
+            self.col = min(max(args[1] if len(args) > 1 else 1, 1), self.cols) - 1
+        elif final == "J" and first == 2:
+            self.grid = [[" "] * self.cols for _ in range(self.rows)]
+        elif final == "K":
+            for col in range(self.col, self.cols):
+                self.grid[self.row][col] = " "
+        elif final == "r":
+            self.top = (args[0] - 1) if len(args) > 0 and args[0] else 0
+            self.bottom = args[1] if len(args) > 1 and args[1] else self.rows
+            self.row = self.col = 0
+        elif final in "ST":
+            count = max(first, 1)
+            region = self.grid[self.top:self.bottom]
+            blank = [[" "] * self.cols for _ in range(min(count, len(region)))]
+            if final == "S":
+                region = region[count:] + blank
+            else:
+                region = blank + region[:len(region) - count]
+            self.grid[self.top:self.bottom] = region
+
+    def lines(self) -> list[str]:
+        return ["".join(row).rstrip() for row in self.grid]
+
+    def dump(self) -> str:
+        """The screen as if it was drawn from scratch: each row after a cursor move."""
+        rows = "".join(f"\x1b[{n};1H{line}" for n, line in enumerate(self.lines(), start=1))
+        return f"\x1b[2J{rows}\x1b[{self.row + 1};{self.col + 1}H"
+
+
+CSI = re.compile(r"\x1b\[([0-9;?]*)([@-~])")
+ESC_OTHER = re.compile(r"\x1b[^\[]")
+
+
+def terminal_of(child: pexpect.spawn) -> VirtualTerminal:
+    """Returns the virtual terminal of ``child``, attaching one on first use."""
+    if not isinstance(child.logfile_read, VirtualTerminal):
+        rows, cols = child.getwinsize()
+        child.logfile_read = VirtualTerminal(rows, cols)
+    return child.logfile_read
+
+
 def spawn_evi(path: str, rows: int = 24, cols: int = 80):
     """Spawn evi with consistent env/window setup."""
     env = os.environ.copy()
@@ -22,6 +124,7 @@ def spawn_evi(path: str, rows: int = 24, cols: int = 80):
     )
     child.delaybeforesend = float(os.getenv("EVI_DELAY_BEFORE_SEND", str(DEFAULT_DELAY)))
     child.setwinsize(rows, cols)
+    terminal_of(child)
     return child
 
 def run_commands(commands, initial_content: str = "", exit_cmd: str = ":wq\r"):
@@ -66,6 +169,7 @@ def expect_cursor(child: pexpect.spawn, expected_line: Optional[int] = None, tim
 
     Returns (line, col).
     """
+    terminal_of(child)
     deadline = time.time() + timeout
     last = (1, 1)
     while True:
@@ -86,6 +190,7 @@ def wait_until_top_line(child: pexpect.spawn, predicate: Callable[[str], bool],
 
     Returns final captured screen.
     """
+    terminal_of(child)
     end = time.time() + timeout
     last_screen = ""
     while time.time() < end:
@@ -94,13 +199,12 @@ def wait_until_top_line(child: pexpect.spawn, predicate: Callable[[str], bool],
             child.expect(r"line (\d+) of \d+ --\d+%-- col (\d+)", timeout=0.2)
         except pexpect.exceptions.TIMEOUT:
             pass
-        # mypy: child.before/after are str when match succeeds; ignore union noise
-        last_screen = (child.before or "") + (child.after or "")  # type: ignore[operator]
         # Try to read any remaining buffer (non-fatal)
         try:
-            last_screen += child.read_nonblocking(size=4096, timeout=0.05)
+            child.read_nonblocking(size=4096, timeout=0.05)
         except Exception:
             pass
+        last_screen = terminal_of(child).dump()
         if predicate(last_screen):
             return last_screen
         time.sleep(SHORT_SLEEP)
@@ -6,10 +6,9 @@ from .conftest import EVI_BIN
 from .test_motion_commands import get_screen_and_cursor, get_cursor_position
 
 
-# :w command is not implemented yet
-# def test_write_command():
-#     result = run_commands(['i', 'written', '\x1b', ':w\r', ':q\r'], initial_content='')
-#     assert result.strip() == 'written'
+def test_write_command():
+    result = run_commands(['i', 'written', '\x1b', ':w\r', ':q\r'], initial_content='')
+    assert result.strip() == 'written'
 
 
 # :x command is not implemented yet
@@ -37,10 +36,9 @@ def test_substitute_range():
 #     assert result.splitlines() == ['cde', 'def', 'cde', 'abc']
 
 
-# Line delete and repeat functionality may not be fully implemented
-# def test_delete_line():
-#     result = run_commands([':1d\r'], initial_content='a\nb\n')
-#     assert result.splitlines() == ['b']
+def test_delete_line():
+    result = run_commands([':1d\r'], initial_content='a\nb\n')
+    assert result.splitlines() == ['b']
 
 
 def test_delete_line_undo():
@@ -48,10 +46,10 @@ def test_delete_line_undo():
     assert result.splitlines() == ['a', 'b']
 
 
-# def test_delete_line_repeat():
-#     result = run_commands([':1d\r', '.'], initial_content='a\nb\n')
-#     # Ex commands cannot be repeated with '.'
-#     assert result.splitlines() == ['b']
+def test_delete_line_repeat():
+    result = run_commands([':1d\r', '.'], initial_content='a\nb\n')
+    # Ex commands cannot be repeated with '.'
+    assert result.splitlines() == ['b']
 
 
 # :m (move) command is not implemented yet
@@ -1,14 +1,15 @@
-# :e! and :r commands are not implemented yet
-# import os
-# import tempfile
-# from .helpers import run_commands
+from .helpers import run_commands
 
 
-# def test_reload_file():
-#     result = run_commands(['Achanged', '\x1b', ':e!\r'], initial_content='orig\n')
-#     assert result.splitlines() == ['orig']
+def test_reload_file():
+    result = run_commands(['Achanged', '\x1b', ':e!\r'], initial_content='orig\n')
+    assert result.splitlines() == ['orig']
 
 
+# :r command is not implemented yet
+# import os
+# import tempfile
+#
 # def test_read_file():
 #     fd, path = tempfile.mkstemp()
 #     try:
@@ -19,3 +20,9 @@
 #         os.unlink(path)
 #     assert result.splitlines() == ['orig', 'a', 'b']
 
+
+
+def test_split_window_shares_buffer():
+    commands = [':sp\r', '\x17j', 'iabc', '\x1b', '\x17k', ':q\r']
+    result = run_commands(commands, initial_content='orig\n')
+    assert result.splitlines() == ['abcorig']
@@ -6,6 +6,7 @@ import time
 from typing import Tuple
 
 from .conftest import EVI_BIN
+from .helpers import terminal_of
 
 
 def get_cursor_position(child: pexpect.spawn) -> Tuple[int, int]:
@@ -29,27 +30,24 @@ def goto(child: pexpect.spawn, row: int, col: int) -> None:
 
 
 def get_screen_and_cursor(child: pexpect.spawn, rows: int = 24) -> Tuple[str, Tuple[int, int]]:
-    """Return screen contents and final cursor position using `Ctrl-G`."""
+    """Return screen contents and final cursor position using `Ctrl-G`.
+
+    The screen is returned as if it was drawn from scratch, one cursor move
+    and the text for each row, see `VirtualTerminal.dump`.
+    """
+    terminal = terminal_of(child)
     child.send("\x07")
     child.expect(r"line (\d+) of \d+ --\d+%-- col (\d+)")
-    screen = child.before + child.after
     # Allow the timeout when reading screen updates to be configured via the
     # ``EVI_PEXPECT_TIMEOUT`` environment variable.  The helper functions use a
     # default of ``0.2`` seconds which keeps the tests fast, but this can be
     # increased in slower CI containers to avoid ``pexpect.TIMEOUT`` errors.
     timeout = float(os.getenv("EVI_PEXPECT_TIMEOUT", "0.2"))
     try:
-        screen += child.read_nonblocking(size=4096, timeout=timeout)
+        child.read_nonblocking(size=4096, timeout=timeout)
     except (pexpect.exceptions.TIMEOUT, pexpect.exceptions.EOF):
         pass
-    matches = list(re.finditer(r"\x1b\[(\d+);(\d+)H", screen))
-    for m in reversed(matches):
-        if int(m.group(1)) < rows:
-            return screen, (int(m.group(1)), int(m.group(2)))
-    if matches:
-        m = matches[-1]
-        return screen, (int(m.group(1)), int(m.group(2)))
-    return screen, (1, 1)
+    return terminal.dump(), (terminal.row + 1, terminal.col + 1)
 
 
 def run_motion_test(
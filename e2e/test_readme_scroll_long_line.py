@@ -1,19 +1,19 @@
 import os, re, pexpect
 from .conftest import EVI_BIN
-from e2e.helpers import expect_cursor
+from e2e.helpers import expect_cursor, terminal_of
 
 CUR_MOVE = re.compile(r"\x1b\[(\d+);(\d+)H")
 ANSI_CSI = re.compile(r"\x1b\[[0-9;]*[A-Za-z]")
 
 def _capture(child):
+    terminal = terminal_of(child)
     child.send('\x07')
     child.expect(r'line (\d+) of')
-    data = child.before + child.after
     try:
-        data += child.read_nonblocking(size=4096, timeout=0.2)
+        child.read_nonblocking(size=4096, timeout=0.2)
     except Exception:
         pass
-    return data
+    return terminal.dump()
 
 def test_readme_32j_long_line_fully_visible():
     readme_path = os.path.abspath('README.md')
@@ -1,7 +1,31 @@
-# Registers are not implemented yet
-# from .helpers import run_commands
+from .helpers import run_commands
 
 
-# def test_named_register_yank_paste():
-#     result = run_commands(['"a', 'yy', 'j', '"ap'], initial_content='one\ntwo\n')
-#     assert result.splitlines() == ['one', 'two', 'one', '']
+def test_named_register_yank_paste():
+    result = run_commands(['"a', 'yy', 'j', '"ap'], initial_content='one\ntwo\n')
+    assert result.splitlines() == ['one', 'two', 'one']
+
+
+def test_put_before_with_count():
+    result = run_commands(['yw', '2P'], initial_content='ab cd\n')
+    assert result.splitlines() == ['ab ab ab cd']
+
+
+def test_delete_and_put_undo():
+    result = run_commands(['dd', 'p', 'u'], initial_content='one\ntwo\n')
+    assert result.splitlines() == ['two']
+
+
+def test_record_and_execute_macro():
+    result = run_commands(['qa', 'x', 'j', 'q', '2@a'], initial_content='ab\ncd\nef\n')
+    assert result.splitlines() == ['b', 'd', 'f']
+
+
+def test_put_recorded_macro():
+    result = run_commands(['qa', 'dw', 'q', '"ap'], initial_content='one two\n')
+    assert result.splitlines() == ['tdwwo']
+
+
+def test_repeat_ex_command():
+    result = run_commands([':1d\r', '@:'], initial_content='a\nb\nc\n')
+    assert result.splitlines() == ['c']
@@ -81,6 +81,21 @@ def test_delete_word_undo():
     assert result.strip() == 'foo bar'
 
 
+def test_change_word():
+    result = run_commands(['c', 'w', 'baz', '\x1b'], initial_content='foo bar\n')
+    assert result.strip() == 'baz bar'
+
+
+def test_change_word_undo():
+    result = run_commands(['c', 'w', 'baz', '\x1b', 'u'], initial_content='foo bar\n')
+    assert result.strip() == 'foo bar'
+
+
+def test_change_line():
+    result = run_commands(['j', 'S', 'x', '\x1b'], initial_content='a\nb\nc\n')
+    assert result.splitlines() == ['a', 'x', 'c']
+
+
 # Search commands are not implemented yet
 # def test_search_forward_delete_line():
 #     result = run_commands(['/bar\r', 'dd'], initial_content='foo\nbar\nbaz\n')
@@ -118,30 +133,29 @@ def test_undo():
     assert result.strip() == 'hello'
 
 
-# Command repeat functionality tests - may have implementation issues
-# def test_repeat_command():
-#     result = run_commands(['x', '.', '.'], initial_content='abc\n')
-#     assert result.strip() == ''
+def test_repeat_command():
+    result = run_commands(['x', '.', '.'], initial_content='abc\n')
+    assert result.strip() == ''
 
 
-# def test_undo_then_repeat():
-#     result = run_commands(['x', 'u', '.'], initial_content='abc\n')
-#     assert result.strip() == 'bc'
+def test_undo_then_repeat():
+    result = run_commands(['x', 'u', '.'], initial_content='abc\n')
+    assert result.strip() == 'bc'
 
 
-# def test_repeat_delete_char_with_count():
-#     result = run_commands(['3', 'x', '.'], initial_content='abcdef\n')
-#     assert result.strip() == ''
+def test_repeat_delete_char_with_count():
+    result = run_commands(['3', 'x', '.'], initial_content='abcdef\n')
+    assert result.strip() == ''
 
 
-# def test_repeat_insert():
-#     result = run_commands(['i', 'abc', '\x1b', '.'], initial_content='')
-#     assert result.strip() == 'abcabc'
+def test_repeat_insert():
+    result = run_commands(['i', 'abc', '\x1b', '.'], initial_content='')
+    assert result.strip() == 'abcabc'
 
 
-# def test_repeat_delete_word():
-#     result = run_commands(['d', 'w', '.'], initial_content='one two three\n')
-#     assert result.strip() == 'three'
+def test_repeat_delete_word():
+    result = run_commands(['d', 'w', '.'], initial_content='one two three\n')
+    assert result.strip() == 'three'
 
 
 def test_write_quit_ZZ():
@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::{
+    cursor,
+    event::{self, DisableFocusChange, EnableFocusChange, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{self, Attribute},
+    terminal, QueueableCommand,
+};
+
+use crate::{
+    generic_error::GenericResult,
+    screen::{Cell, Screen, Style},
+    theme::ColorDepth,
+};
+
+// What the editor needs from a terminal: drawing cells, moving the cursor,
+// reading events and knowing the size.
+pub trait Backend {
+    /// Returns the size of the terminal, (width, height).
+    fn size(&self) -> GenericResult<(u16, u16)>;
+
+    fn color_depth(&self) -> ColorDepth;
+
+    /// Blanks the whole terminal.
+    fn clear(&mut self) -> GenericResult<()>;
+
+    /// Draws `cells` from `row`, `col`. Continuation cells are skipped, the
+    /// double width character before them covers them.
+    fn draw(&mut self, row: u16, col: u16, cells: &[Cell]) -> GenericResult<()>;
+
+    /// Moves the rows `top..bottom` up by `count` rows (down if negative).
+    fn scroll(&mut self, top: u16, bottom: u16, count: i32) -> GenericResult<()>;
+
+    fn move_cursor(&mut self, col: u16, row: u16) -> GenericResult<()>;
+
+    fn bell(&mut self) -> GenericResult<()>;
+
+    /// Shows what was drawn.
+    fn flush(&mut self) -> GenericResult<()>;
+
+    /// Waits up to `timeout` for an event and returns whether one came.
+    fn poll_event(&mut self, timeout: Duration) -> GenericResult<bool>;
+
+    /// Returns the next event, or `None` when the input has ended.
+    fn read_event(&mut self) -> GenericResult<Option<Event>>;
+}
+
+// A real terminal, through crossterm.
+pub struct CrosstermBackend<W: Write> {
+    out: W,
+    // The style and the position the terminal writes the next character with,
+    // to leave out escape sequences that change nothing.
+    style: Option<Style>,
+    position: Option<(u16, u16)>,
+    cursor_hidden: bool,
+    entered: bool,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(out: W) -> CrosstermBackend<W> {
+        CrosstermBackend {
+            out,
+            style: None,
+            position: None,
+            cursor_hidden: false,
+            entered: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Puts the terminal in raw mode; it is restored when the backend is dropped.
+    pub fn enter(&mut self) -> GenericResult<()> {
+        terminal::enable_raw_mode()?;
+        self.out.queue(EnableFocusChange)?;
+        self.out.flush()?;
+        self.entered = true;
+        Ok(())
+    }
+
+    fn leave(&mut self) -> GenericResult<()> {
+        self.out.queue(DisableFocusChange)?;
+        terminal::disable_raw_mode()?;
+        self.out.queue(style::SetAttribute(Attribute::Reset))?;
+        self.out.queue(terminal::Clear(terminal::ClearType::All))?;
+        self.out.queue(terminal::LeaveAlternateScreen)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    // The cursor is hidden while drawing so that it does not flicker around.
+    fn hide_cursor(&mut self) -> GenericResult<()> {
+        if !self.cursor_hidden {
+            self.out.queue(cursor::Hide)?;
+            self.cursor_hidden = true;
+        }
+        Ok(())
+    }
+
+    fn reset_style(&mut self) -> GenericResult<()> {
+        if self.style.take().is_some() {
+            self.out.queue(style::SetAttribute(Attribute::Reset))?;
+        }
+        Ok(())
+    }
+
+    fn set_style(&mut self, style: Style) -> GenericResult<()> {
+        if self.style == Some(style) {
+            return Ok(());
+        }
+        self.out.queue(style::SetAttribute(Attribute::Reset))?;
+        if let Some(color) = style.fg {
+            self.out.queue(style::SetForegroundColor(color))?;
+        }
+        if let Some(color) = style.bg {
+            self.out.queue(style::SetBackgroundColor(color))?;
+        }
+        if style.bold {
+            self.out.queue(style::SetAttribute(Attribute::Bold))?;
+        }
+        if style.reverse {
+            self.out.queue(style::SetAttribute(Attribute::Reverse))?;
+        }
+        if style.underline {
+            self.out.queue(style::SetAttribute(Attribute::Underlined))?;
+        }
+        self.style = Some(style);
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for CrosstermBackend<W> {
+    fn drop(&mut self) {
+        if self.entered {
+            let _ = self.leave();
+        }
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn size(&self) -> GenericResult<(u16, u16)> {
+        Ok(terminal::size()?)
+    }
+
+    fn color_depth(&self) -> ColorDepth {
+        ColorDepth::detect()
+    }
+
+    fn clear(&mut self) -> GenericResult<()> {
+        self.hide_cursor()?;
+        self.reset_style()?;
+        // The terminal may have been left with any attributes.
+        self.out.queue(style::SetAttribute(Attribute::Reset))?;
+        self.out.queue(terminal::Clear(terminal::ClearType::All))?;
+        self.position = None;
+        Ok(())
+    }
+
+    fn draw(&mut self, row: u16, col: u16, cells: &[Cell]) -> GenericResult<()> {
+        self.hide_cursor()?;
+        if self.position != Some((col, row)) {
+            self.out.queue(cursor::MoveTo(col, row))?;
+        }
+        for cell in cells.iter().filter(|cell| !cell.is_continuation()) {
+            self.set_style(cell.style)?;
+            self.out.queue(style::Print(cell.ch))?;
+        }
+        self.position = Some((col + cells.len() as u16, row));
+        Ok(())
+    }
+
+    fn scroll(&mut self, top: u16, bottom: u16, count: i32) -> GenericResult<()> {
+        self.hide_cursor()?;
+        // Rows scrolled in take the current colors.
+        self.reset_style()?;
+        // Set the scroll region (DECSTBM); the terminal leaves the other rows alone.
+        self.out
+            .queue(style::Print(format!("\x1b[{};{}r", top + 1, bottom)))?;
+        if count > 0 {
+            self.out.queue(terminal::ScrollUp(count as u16))?;
+        } else {
+            self.out.queue(terminal::ScrollDown((-count) as u16))?;
+        }
+        // Resetting the region moves the cursor home.
+        self.out.queue(style::Print("\x1b[r"))?;
+        self.position = None;
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, col: u16, row: u16) -> GenericResult<()> {
+        self.out.queue(cursor::MoveTo(col, row))?;
+        self.position = Some((col, row));
+        Ok(())
+    }
+
+    fn bell(&mut self) -> GenericResult<()> {
+        self.out.write_all(b"\x07")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> GenericResult<()> {
+        self.reset_style()?;
+        if self.cursor_hidden {
+            self.out.queue(cursor::Show)?;
+            self.cursor_hidden = false;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> GenericResult<bool> {
+        Ok(event::poll(timeout)?)
+    }
+
+    fn read_event(&mut self) -> GenericResult<Option<Event>> {
+        Ok(Some(event::read()?))
+    }
+}
+
+// A terminal in memory, for tests: events are queued beforehand, and what is
+// drawn ends up in a screen that can be inspected.
+pub struct MemoryBackend {
+    screen: Screen,
+    cursor: (u16, u16),
+    events: VecDeque<Event>,
+    bells: usize,
+    color_depth: ColorDepth,
+}
+
+impl MemoryBackend {
+    pub fn new(width: u16, height: u16) -> MemoryBackend {
+        MemoryBackend {
+            screen: Screen::new(width, height),
+            cursor: (0, 0),
+            events: VecDeque::new(),
+            bells: 0,
+            color_depth: ColorDepth::TrueColor,
+        }
+    }
+
+    pub fn set_color_depth(&mut self, color_depth: ColorDepth) {
+        self.color_depth = color_depth;
+    }
+
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Queues a key for every character of `keys`. Like in the terminal,
+    /// "\r" is Enter, "\x1b" is Esc and the other control characters are
+    /// Ctrl with a letter.
+    pub fn push_keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            let (code, modifiers) = match c {
+                '\r' | '\n' => (KeyCode::Enter, KeyModifiers::NONE),
+                '\x1b' => (KeyCode::Esc, KeyModifiers::NONE),
+                '\x7f' => (KeyCode::Backspace, KeyModifiers::NONE),
+                '\x01'..='\x1a' => (
+                    KeyCode::Char((c as u8 - 1 + b'a') as char),
+                    KeyModifiers::CONTROL,
+                ),
+                '\x1e' => (KeyCode::Char('^'), KeyModifiers::CONTROL),
+                _ => (KeyCode::Char(c), KeyModifiers::NONE),
+            };
+            self.push_event(Event::Key(KeyEvent::new(code, modifiers)));
+        }
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Returns the rows of the screen as text, without trailing spaces.
+    pub fn lines(&self) -> Vec<String> {
+        (0..self.screen.height())
+            .map(|row| self.screen.row_text(row).trim_end().to_string())
+            .collect()
+    }
+
+    /// Returns the position of the cursor, (column, row).
+    pub fn cursor(&self) -> (u16, u16) {
+        self.cursor
+    }
+
+    /// Returns how many times the bell rang.
+    pub fn bells(&self) -> usize {
+        self.bells
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn size(&self) -> GenericResult<(u16, u16)> {
+        Ok((self.screen.width(), self.screen.height()))
+    }
+
+    fn color_depth(&self) -> ColorDepth {
+        self.color_depth
+    }
+
+    fn clear(&mut self) -> GenericResult<()> {
+        self.screen = Screen::new(self.screen.width(), self.screen.height());
+        Ok(())
+    }
+
+    fn draw(&mut self, row: u16, col: u16, cells: &[Cell]) -> GenericResult<()> {
+        for (offset, cell) in cells.iter().enumerate() {
+            if !cell.is_continuation() {
+                self.screen
+                    .put_char(row, col + offset as u16, cell.ch, cell.style);
+            }
+        }
+        Ok(())
+    }
+
+    fn scroll(&mut self, top: u16, bottom: u16, count: i32) -> GenericResult<()> {
+        self.screen.scroll(top, bottom, count);
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, col: u16, row: u16) -> GenericResult<()> {
+        self.cursor = (col, row);
+        Ok(())
+    }
+
+    fn bell(&mut self) -> GenericResult<()> {
+        self.bells += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> GenericResult<()> {
+        Ok(())
+    }
+
+    fn poll_event(&mut self, _timeout: Duration) -> GenericResult<bool> {
+        // An empty queue is the end of the input, not something to wait for.
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> GenericResult<Option<Event>> {
+        let event = self.events.pop_front();
+        // The terminal changes its size before it reports it.
+        if let Some(Event::Resize(width, height)) = event {
+            self.screen = Screen::new(width, height);
+        }
+        Ok(event)
+    }
+}
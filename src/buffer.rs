@@ -1,167 +1,307 @@
-use std::{fs, io::Write, path::PathBuf};
+use std::path::Path;
 
-use tempfile::NamedTempFile;
+use ropey::{iter::Lines, Rope, RopeSlice};
+use crate::file_stamp::FileStamp;
+use crate::{file_format::FileFormat, file_write::write_file, generic_error::GenericResult};
+use crate::syntax::{Span, Syntax};
 
-use crate::{generic_error::GenericResult, util::split_line};
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CursorPositionInBuffer {
     pub row: usize,
     pub col: usize,
 }
 
 impl CursorPositionInBuffer {
-    pub fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.row < other.row {
-            std::cmp::Ordering::Less
-        } else if self.row > other.row {
-            std::cmp::Ordering::Greater
-        } else {
-            self.col.cmp(&other.col)
+    /// Returns the position just after `text` when it is inserted at this position.
+    pub fn advanced_by(&self, text: &str) -> CursorPositionInBuffer {
+        match text.rfind('\n') {
+            Some(idx) => CursorPositionInBuffer {
+                row: self.row + text.matches('\n').count(),
+                col: text[idx + 1..].chars().count(),
+            },
+            None => CursorPositionInBuffer {
+                row: self.row,
+                col: self.col + text.chars().count(),
+            },
         }
     }
 }
 
-impl PartialOrd for CursorPositionInBuffer {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
+// The text is kept in a rope in which every line, including the last one,
+// is terminated by '\n'. An empty rope therefore means "no lines", and
+// line `n` of the buffer is line `n` of the rope without its terminator.
+#[derive(Clone, Debug, Default)]
 pub struct Buffer {
-    pub lines: Vec<String>,
+    text: Rope,
+    pub file_format: FileFormat,
+    pub read_only: bool,
+    // The state of the file when it was last read or written.
+    pub file_stamp: Option<FileStamp>,
+    pub syntax: Option<Syntax>,
 }
 
 impl Buffer {
     pub fn new() -> Buffer {
-        Buffer { lines: Vec::new() }
+        Buffer {
+            text: Rope::new(),
+            file_format: FileFormat::default(),
+            read_only: false,
+            file_stamp: None,
+            syntax: None,
+        }
+    }
+
+    pub fn from_lines<S: AsRef<str>>(lines: &[S]) -> Buffer {
+        let mut buffer = Buffer::new();
+        for line in lines {
+            buffer.push_line(line.as_ref());
+        }
+        buffer
     }
 
-    pub fn from_file(file_path: &PathBuf) -> Buffer {
-        let lines = std::fs::read_to_string(file_path)
-            .expect("Failed to read file")
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
-        Buffer { lines }
+    pub fn from_file(file_path: &Path) -> GenericResult<Buffer> {
+        // Taken before reading, so a change made while reading is noticed later.
+        let file_stamp = Some(FileStamp::of(file_path));
+        let bytes = std::fs::read(file_path)?;
+        let (file_format, content) = FileFormat::decode(&bytes)?;
+        Ok(Buffer {
+            file_stamp,
+            ..Buffer::from_text(&content, file_format)
+        })
     }
 
-    pub fn to_file(&self, file_path: &PathBuf) -> GenericResult<()> {
-        let mut temp_file = NamedTempFile::new()?;
-        for line in &self.lines {
-            temp_file.write_all(line.as_bytes())?;
-            temp_file.write_all(b"\n")?;
+    /// Builds a buffer from text whose lines are all terminated by '\n'.
+    pub fn from_text(text: &str, file_format: FileFormat) -> Buffer {
+        Buffer {
+            text: Rope::from_str(text),
+            file_format,
+            read_only: false,
+            file_stamp: None,
+            syntax: None,
         }
-        temp_file.flush()?;
+    }
+
+    /// Returns the whole text, every line terminated by '\n'.
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    /// Writes the buffer and returns the number of bytes written.
+    pub fn to_file(&self, file_path: &Path) -> GenericResult<usize> {
+        let bytes = self.file_format.encode(&self.text())?;
+        write_file(file_path, &bytes)?;
+        Ok(bytes.len())
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.text.len_lines() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.len_chars() == 0
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.text.len_chars()
+    }
+
+    /// Returns line `row` without its terminating newline. Panics if `row` is out of range.
+    pub fn line(&self, row: usize) -> RopeSlice<'_> {
+        self.get_line(row)
+            .unwrap_or_else(|| panic!("row {} out of range ({} lines)", row, self.len_lines()))
+    }
 
-        if file_path.exists() {
-            let permissions = fs::metadata(file_path)?.permissions();
-            fs::set_permissions(temp_file.path(), permissions)?;
+    pub fn get_line(&self, row: usize) -> Option<RopeSlice<'_>> {
+        if row >= self.len_lines() {
+            return None;
         }
+        let line = self.text.line(row);
+        Some(line.slice(..line.len_chars() - 1))
+    }
 
-        std::fs::rename(temp_file.path(), file_path)?;
-        Ok(())
+    pub fn line_len(&self, row: usize) -> usize {
+        self.get_line(row).map(|line| line.len_chars()).unwrap_or(0)
+    }
+
+    /// Iterates over the lines of the buffer, each without its terminating newline.
+    pub fn lines(&self) -> BufferLines<'_> {
+        self.lines_at(0)
+    }
+
+    pub fn lines_at(&self, row: usize) -> BufferLines<'_> {
+        let row = row.min(self.len_lines());
+        BufferLines {
+            lines: self.text.lines_at(row),
+            remaining: self.len_lines() - row,
+        }
+    }
+
+    pub fn get_char(&self, row: usize, col: usize) -> Option<char> {
+        self.get_line(row)?.get_char(col)
+    }
+
+    /// Returns the text between two positions as a `String`, like `delete` would remove it.
+    pub fn slice(&self, start: CursorPositionInBuffer, end: CursorPositionInBuffer) -> String {
+        let (start, end) = if start > end { (end, start) } else { (start, end) };
+        let start_idx = self.char_index(start);
+        let end_idx = self.char_index(end);
+        self.text.slice(start_idx..end_idx).to_string()
     }
 
     pub fn insert_char(&mut self, row: usize, col: usize, c: char) -> GenericResult<()> {
-        let new_line = self.lines[row]
-            .chars()
-            .take(col)
-            .chain(std::iter::once(c))
-            .chain(self.lines[row].chars().skip(col))
-            .collect();
-        self.lines[row] = new_line;
+        self.changed(row);
+        self.ensure_line(row);
+        let idx = self.char_index(CursorPositionInBuffer { row, col });
+        self.text.insert_char(idx, c);
         Ok(())
     }
 
     pub fn insert(&mut self, row: usize, col: usize, s: &str) -> GenericResult<()> {
-        let lines_to_be_inserted = split_line(s);
-        if lines_to_be_inserted.len() == 0 {
-            panic!("lines.len() == 0, s: '{:?}'", s);
-        }
-        if lines_to_be_inserted.len() == 1 {
-            let new_line = self.lines[row]
-                .chars()
-                .take(col)
-                .chain(lines_to_be_inserted[0].chars())
-                .chain(self.lines[row].chars().skip(col))
-                .collect();
-            self.lines[row] = new_line;
-        } else if lines_to_be_inserted.len() >= 2 {
-            let new_first_line: String = self.lines[row].chars().take(col).collect();
-            let input_last_line: String = lines_to_be_inserted[lines_to_be_inserted.len() - 1].to_string();
-            let new_last_line: String = input_last_line + &self.lines[row].chars().skip(col).collect::<String>();
-            self.lines[row] = new_first_line + lines_to_be_inserted[0];
-            for i in 1..lines_to_be_inserted.len() - 1 {
-                self.lines.insert(row + i, lines_to_be_inserted[i].to_string());
+        self.changed(row);
+        if row >= self.len_lines() {
+            // After the last line `s` makes lines of its own.
+            if row > 0 {
+                self.ensure_line(row - 1);
+            }
+            self.text.insert(self.text.len_chars(), s);
+            if !s.ends_with('\n') {
+                self.text.insert_char(self.text.len_chars(), '\n');
             }
-            self.lines.insert(row + lines_to_be_inserted.len() - 1, new_last_line);
+            return Ok(());
         }
+        let idx = self.char_index(CursorPositionInBuffer { row, col });
+        self.text.insert(idx, s);
         Ok(())
     }
 
     pub fn delete_char(&mut self, row: usize, col: usize) -> GenericResult<()> {
-        let new_line = self.lines[row]
-            .chars()
-            .take(col)
-            .chain(self.lines[row].chars().skip(col + 1))
-            .collect();
-        self.lines[row] = new_line;
+        self.changed(row);
+        if col < self.line_len(row) {
+            let idx = self.char_index(CursorPositionInBuffer { row, col });
+            self.text.remove(idx..idx + 1);
+        }
         Ok(())
     }
 
-    pub fn get_char(&self, row: usize, col: usize) -> Option<char> {
-        self.lines.get(row)?.chars().nth(col)
-    }
-
     pub fn delete(
         &mut self,
-        mut start: CursorPositionInBuffer,
-        mut end: CursorPositionInBuffer,
+        start: CursorPositionInBuffer,
+        end: CursorPositionInBuffer,
     ) -> GenericResult<String> {
-        if start.cmp(&end) == std::cmp::Ordering::Greater {
-            let tmp = start;
-            start = end;
-            end = tmp;
+        let (start, end) = if start > end { (end, start) } else { (start, end) };
+        self.changed(start.row);
+        let start_idx = self.char_index(start);
+        let end_idx = self.char_index(end);
+        let deleted = self.text.slice(start_idx..end_idx).to_string();
+        self.text.remove(start_idx..end_idx);
+        Ok(deleted)
+    }
+
+    pub fn set_line(&mut self, row: usize, s: &str) {
+        self.changed(row);
+        self.ensure_line(row);
+        let start = self.text.line_to_char(row);
+        self.text.remove(start..start + self.line_len(row));
+        self.text.insert(start, s);
+    }
+
+    pub fn insert_line(&mut self, row: usize, s: &str) {
+        self.changed(row);
+        if row > 0 {
+            self.ensure_line(row - 1);
         }
-        if start.row == end.row {
-            let line = &mut self.lines[start.row];
-            let deleted: String = line
-                .chars()
-                .skip(start.col)
-                .take(end.col - start.col)
-                .collect();
-            let new_line: String = line
-                .chars()
-                .take(start.col)
-                .chain(line.chars().skip(end.col))
-                .collect();
-            *line = new_line;
-            Ok(deleted)
-        } else {
-            let first_line = self.lines[start.row].clone();
-            let last_line = self.lines[end.row].clone();
-            let new_first_line: String = first_line.chars().take(start.col).collect();
-            let first_line_deleted: String = first_line.chars().skip(start.col).collect();
-            let new_last_line: String = last_line.chars().skip(end.col).collect();
-            let last_line_deleted: String = last_line.chars().take(end.col).collect();
-            self.lines[start.row] = new_first_line + new_last_line.as_str();
-            let mut deleted_chars = first_line_deleted;
-            for i in 0..end.row - start.row {
-                deleted_chars.push('\n');
-                if i < end.row - start.row - 1 {
-                    deleted_chars.push_str(&self.lines[start.row + 1]);
-                }
-                self.lines.remove(start.row + 1);
-            }
-            deleted_chars.push_str(&last_line_deleted);
-            Ok(deleted_chars)
+        let idx = self.text.line_to_char(row);
+        self.text.insert_char(idx, '\n');
+        self.text.insert(idx, s);
+    }
+
+    pub fn push_line(&mut self, s: &str) {
+        self.insert_line(self.len_lines(), s);
+    }
+
+    pub fn remove_line(&mut self, row: usize) -> Option<String> {
+        if row >= self.len_lines() {
+            return None;
+        }
+        self.changed(row);
+        let start = self.text.line_to_char(row);
+        let end = self.text.line_to_char(row + 1);
+        let removed = self.text.slice(start..end - 1).to_string();
+        self.text.remove(start..end);
+        Some(removed)
+    }
+
+    /// Splits line `row` at `col`, moving the rest of the line onto a new line below.
+    pub fn split_line(&mut self, row: usize, col: usize) {
+        self.insert_char(row, col, '\n').unwrap();
+    }
+
+    /// Joins line `row + 1` onto the end of line `row` without inserting anything between them.
+    pub fn join_lines(&mut self, row: usize) {
+        if row + 1 < self.len_lines() {
+            self.changed(row);
+            let idx = self.text.line_to_char(row + 1) - 1;
+            self.text.remove(idx..idx + 1);
+        }
+    }
+
+    /// Returns the highlighted spans of line `row`.
+    pub fn highlight_line(&self, row: usize) -> Vec<Span> {
+        match &self.syntax {
+            Some(syntax) => syntax.highlight(self, row),
+            None => Vec::new(),
+        }
+    }
+
+    // Called before line `row` and the lines after it change.
+    fn changed(&self, row: usize) {
+        if let Some(syntax) = &self.syntax {
+            syntax.invalidate_from(row);
+        }
+    }
+
+    fn ensure_line(&mut self, row: usize) {
+        while self.len_lines() <= row {
+            self.text.insert_char(self.text.len_chars(), '\n');
+        }
+    }
+
+    fn char_index(&self, pos: CursorPositionInBuffer) -> usize {
+        if pos.row >= self.len_lines() {
+            return self.text.len_chars();
+        }
+        self.text.line_to_char(pos.row) + pos.col.min(self.line_len(pos.row))
+    }
+}
+
+impl PartialEq<[&str]> for Buffer {
+    fn eq(&self, other: &[&str]) -> bool {
+        self.len_lines() == other.len() && self.lines().zip(other).all(|(a, b)| a == *b)
+    }
+}
+
+pub struct BufferLines<'a> {
+    lines: Lines<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for BufferLines<'a> {
+    type Item = RopeSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
+        let line = self.lines.next()?;
+        Some(line.slice(..line.len_chars() - 1))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
 
     #[test]
@@ -188,84 +328,138 @@ mod tests {
         assert_eq!(d.cmp(&d), std::cmp::Ordering::Equal);
     }
 
+    #[test]
+    fn test_cursor_position_in_buffer_advanced_by() {
+        let a = CursorPositionInBuffer { row: 1, col: 2 };
+        assert_eq!(a.advanced_by(""), a);
+        assert_eq!(a.advanced_by("あい"), CursorPositionInBuffer { row: 1, col: 4 });
+        assert_eq!(a.advanced_by("x\nyz"), CursorPositionInBuffer { row: 2, col: 2 });
+        assert_eq!(a.advanced_by("x\n\n"), CursorPositionInBuffer { row: 3, col: 0 });
+    }
+
     #[test]
     fn test_buffer_insert_char() {
-        let mut buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string()],
-        };
+        let mut buffer = Buffer::from_lines(&["abc", "def"]);
         buffer.insert_char(0, 1, 'x').unwrap();
-        assert_eq!(buffer.lines, vec!["axbc".to_string(), "def".to_string()]);
+        assert_eq!(buffer, ["axbc", "def"][..]);
+    }
+
+    #[test]
+    fn test_buffer_insert_char_into_empty_buffer() {
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.len_lines(), 0);
+        buffer.insert_char(0, 0, 'x').unwrap();
+        assert_eq!(buffer, ["x"][..]);
     }
 
     #[test]
     fn test_buffer_delete_char() {
-        let mut buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string()],
-        };
+        let mut buffer = Buffer::from_lines(&["abc", "def"]);
         buffer.delete_char(0, 1).unwrap();
-        assert_eq!(buffer.lines, vec!["ac".to_string(), "def".to_string()]);
+        assert_eq!(buffer, ["ac", "def"][..]);
+        buffer.delete_char(0, 2).unwrap();
+        assert_eq!(buffer, ["ac", "def"][..]);
     }
 
     #[test]
     fn test_buffer_get_char() {
-        let buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string()],
-        };
+        let buffer = Buffer::from_lines(&["abc", "def"]);
         assert_eq!(buffer.get_char(0, 1), Some('b'));
         assert_eq!(buffer.get_char(0, 3), None);
+        assert_eq!(buffer.get_char(2, 0), None);
     }
 
     #[test]
     fn test_buffer_delete() {
-        let mut buffer = Buffer {
-            lines: vec!["abcdef".to_string()],
-        };
+        let mut buffer = Buffer::from_lines(&["abcdef"]);
         let deleted = buffer
             .delete(
                 CursorPositionInBuffer { row: 0, col: 1 },
                 CursorPositionInBuffer { row: 0, col: 4 },
             )
             .unwrap();
-        assert_eq!(buffer.lines, vec!["aef".to_string()]);
+        assert_eq!(buffer, ["aef"][..]);
         assert_eq!(deleted, "bcd");
 
-        buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string(), "ghi".to_string()],
-        };
+        buffer = Buffer::from_lines(&["abc", "def", "ghi"]);
         let deleted = buffer
             .delete(
                 CursorPositionInBuffer { row: 0, col: 1 },
                 CursorPositionInBuffer { row: 1, col: 1 },
             )
             .unwrap();
-        assert_eq!(buffer.lines, vec!["aef".to_string(), "ghi".to_string()]);
+        assert_eq!(buffer, ["aef", "ghi"][..]);
         assert_eq!(deleted, "bc\nd");
 
-        buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string(), "ghi".to_string(), "jkl".to_string()],
-        };
+        buffer = Buffer::from_lines(&["abc", "def", "ghi", "jkl"]);
         let deleted = buffer
             .delete(
                 CursorPositionInBuffer { row: 0, col: 0 },
                 CursorPositionInBuffer { row: 3, col: 0 },
             )
             .unwrap();
-        assert_eq!(buffer.lines, vec!["jkl".to_string()]);
+        assert_eq!(buffer, ["jkl"][..]);
         assert_eq!(deleted, "abc\ndef\nghi\n");
     }
 
     #[test]
     fn test_insert() {
-        let mut buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string()],
-        };
+        let mut buffer = Buffer::from_lines(&["abc", "def"]);
         buffer.insert(0, 1, "x").unwrap();
-        assert_eq!(buffer.lines, vec!["axbc".to_string(), "def".to_string()]);
+        assert_eq!(buffer, ["axbc", "def"][..]);
 
-        buffer = Buffer {
-            lines: vec!["abc".to_string(), "def".to_string()],
-        };
+        buffer = Buffer::from_lines(&["abc", "def"]);
         buffer.insert(0, 1, "x\ny").unwrap();
-        assert_eq!(buffer.lines, vec!["ax".to_string(), "ybc".to_string(), "def".to_string()]);
+        assert_eq!(buffer, ["ax", "ybc", "def"][..]);
+
+        // lines after the last one
+        buffer.insert(3, 0, "g\nh\n").unwrap();
+        assert_eq!(buffer, ["ax", "ybc", "def", "g", "h"][..]);
+        buffer.insert(5, 0, "i").unwrap();
+        assert_eq!(buffer, ["ax", "ybc", "def", "g", "h", "i"][..]);
+    }
+
+    #[test]
+    fn test_file_round_trip_keeps_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dos.txt");
+        fs::write(&path, b"\xEF\xBB\xBFabc\r\ndef").unwrap();
+
+        let mut buffer = Buffer::from_file(&path).unwrap();
+        assert_eq!(buffer, ["abc", "def"][..]);
+        buffer.insert_char(1, 3, 'g').unwrap();
+        buffer.to_file(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"\xEF\xBB\xBFabc\r\ndefg");
+    }
+
+    #[test]
+    fn test_line_editing() {
+        let mut buffer = Buffer::from_lines(&["abc", "def"]);
+        buffer.split_line(0, 1);
+        assert_eq!(buffer, ["a", "bc", "def"][..]);
+        buffer.join_lines(1);
+        assert_eq!(buffer, ["a", "bcdef"][..]);
+        buffer.insert_line(1, "xyz");
+        assert_eq!(buffer, ["a", "xyz", "bcdef"][..]);
+        assert_eq!(buffer.remove_line(0), Some("a".to_string()));
+        buffer.set_line(1, "ghi");
+        assert_eq!(buffer, ["xyz", "ghi"][..]);
+        assert_eq!(buffer.remove_line(2), None);
+    }
+
+    #[test]
+    fn test_lines_and_slice() {
+        let buffer = Buffer::from_lines(&["abc", "", "def"]);
+        let lines: Vec<String> = buffer.lines().map(|l| l.to_string()).collect();
+        assert_eq!(lines, vec!["abc", "", "def"]);
+        assert_eq!(buffer.lines_at(2).count(), 1);
+        assert_eq!(buffer.line_len(2), 3);
+        assert_eq!(
+            buffer.slice(
+                CursorPositionInBuffer { row: 2, col: 1 },
+                CursorPositionInBuffer { row: 0, col: 2 },
+            ),
+            "c\n\nd"
+        );
     }
 }
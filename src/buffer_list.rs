@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::buffer::Buffer;
+use crate::command::base::ExecutedCommand;
+use crate::editor::EditorCursorData;
+use crate::file_stamp::FileStamp;
+use crate::swap_file::SwapFile;
+
+// Everything that belongs to one buffer. The state of the current buffer
+// lives in the fields of `Editor`; the other buffers keep it here.
+#[derive(Default)]
+pub struct BufferState {
+    pub buffer: Buffer,
+    pub is_dirty: bool,
+    pub command_history: Vec<Vec<ExecutedCommand>>,
+    pub cursor_data: EditorCursorData,
+    pub swap_file: Option<SwapFile>,
+    pub keys_since_swap: usize,
+    pub reported_file_stamp: Option<FileStamp>,
+}
+
+pub struct BufferEntry {
+    pub number: usize,
+    pub file_path: Option<PathBuf>,
+    // `None` while this is the current buffer.
+    pub hidden_state: Option<BufferState>,
+}
+
+impl BufferEntry {
+    pub fn name(&self) -> String {
+        match &self.file_path {
+            Some(file_path) => file_path.to_string_lossy().to_string(),
+            None => "[No Name]".to_string(),
+        }
+    }
+}
+
+// The buffers of the editor, numbered from 1 in the order they were created
+// like vim's buffer list. There is always a current buffer.
+pub struct BufferList {
+    entries: Vec<BufferEntry>,
+    current: usize,
+    alternate_number: Option<usize>,
+    next_number: usize,
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BufferList {
+    pub fn new() -> BufferList {
+        BufferList {
+            entries: vec![BufferEntry {
+                number: 1,
+                file_path: None,
+                hidden_state: None,
+            }],
+            current: 0,
+            alternate_number: None,
+            next_number: 2,
+        }
+    }
+
+    pub fn entries(&self) -> &[BufferEntry] {
+        &self.entries
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &BufferEntry {
+        &self.entries[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut BufferEntry {
+        &mut self.entries[self.current]
+    }
+
+    pub fn alternate_number(&self) -> Option<usize> {
+        self.alternate_number
+    }
+
+    pub fn position_by_number(&self, number: usize) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.number == number)
+    }
+
+    pub fn position_by_path(&self, file_path: &Path) -> Option<usize> {
+        self.entries.iter().position(|entry| {
+            entry
+                .file_path
+                .as_deref()
+                .is_some_and(|path| same_file(path, file_path))
+        })
+    }
+
+    // The state of a buffer other than the current one.
+    pub fn hidden_state_mut(&mut self, index: usize) -> Option<&mut BufferState> {
+        self.entries[index].hidden_state.as_mut()
+    }
+
+    /// Adds a hidden buffer and returns its index.
+    pub fn push(&mut self, file_path: Option<PathBuf>, state: BufferState) -> usize {
+        self.entries.push(BufferEntry {
+            number: self.next_number,
+            file_path,
+            hidden_state: Some(state),
+        });
+        self.next_number += 1;
+        self.entries.len() - 1
+    }
+
+    /// Makes buffer `index` current. `state` is the state of the buffer that
+    /// was current until now; the state of the new current buffer is returned.
+    pub fn switch(&mut self, index: usize, state: BufferState) -> BufferState {
+        self.entries[self.current].hidden_state = Some(state);
+        self.alternate_number = Some(self.entries[self.current].number);
+        self.current = index;
+        self.entries[index].hidden_state.take().unwrap_or_default()
+    }
+
+    /// Removes a buffer other than the current one.
+    pub fn remove(&mut self, index: usize) -> BufferEntry {
+        assert_ne!(index, self.current, "the current buffer cannot be removed");
+        let entry = self.entries.remove(index);
+        if index < self.current {
+            self.current -= 1;
+        }
+        if self.alternate_number == Some(entry.number) {
+            self.alternate_number = None;
+        }
+        entry
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_line(line: &str) -> BufferState {
+        BufferState {
+            buffer: Buffer::from_lines(&[line]),
+            ..BufferState::default()
+        }
+    }
+
+    #[test]
+    fn test_buffer_list_switch_and_remove() {
+        let mut buffers = BufferList::new();
+        let index = buffers.push(Some(PathBuf::from("b.txt")), state_with_line("b"));
+        assert_eq!(buffers.entries()[index].number, 2);
+        assert_eq!(buffers.position_by_path(Path::new("b.txt")), Some(index));
+
+        let state = buffers.switch(index, state_with_line("a"));
+        assert_eq!(state.buffer, ["b"][..]);
+        assert_eq!(buffers.current().number, 2);
+        assert_eq!(buffers.alternate_number(), Some(1));
+
+        let entry = buffers.remove(0);
+        assert_eq!(entry.hidden_state.unwrap().buffer, ["a"][..]);
+        assert_eq!(buffers.current_index(), 0);
+        assert_eq!(buffers.current().number, 2);
+        assert_eq!(buffers.alternate_number(), None);
+        // Numbers are never reused.
+        assert_eq!(buffers.push(None, BufferState::default()), 1);
+        assert_eq!(buffers.entries()[1].number, 3);
+    }
+}
@@ -1,7 +1,6 @@
 use std::any::Any;
 
-use crossterm::event::{KeyCode, KeyModifiers};
-
+use crate::command::compose::KeyData;
 use crate::{editor::Editor, generic_error::GenericResult};
 
 #[allow(dead_code)]
@@ -20,6 +19,12 @@ pub trait Command {
         false
     }
 
+    // Whether the command does its count itself, as `3p` puts the text three
+    // times at once, instead of being executed that many times.
+    fn takes_count(&self) -> bool {
+        false
+    }
+
     fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
         let _ = editor;
         // do nothing
@@ -50,19 +55,23 @@ impl dyn Command {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// A motion and its count, the range of an operator.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct JumpCommandData {
     pub count: usize,
-    pub key_code: KeyCode,
-    pub modifiers: KeyModifiers,
+    // The keys of the motion, like `w` or `gg`.
+    pub keys: Vec<KeyData>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CommandData {
     pub count: usize,
-    pub key_code: KeyCode,
-    pub modifiers: KeyModifiers,
+    // The keys the command is bound to, like `x` or `ZZ`, followed by its
+    // argument for a command that takes one, like the `j` of `Ctrl-W j`.
+    pub keys: Vec<KeyData>,
     pub range: Option<JumpCommandData>,
+    // The register named with `"x` before the command.
+    pub register: Option<char>,
 }
 
 pub struct ExecutedCommand {
@@ -70,13 +79,13 @@ pub struct ExecutedCommand {
     pub command: Box<dyn Command>,
 }
 
-impl Into<CommandData> for JumpCommandData {
-    fn into(self) -> CommandData {
+impl From<JumpCommandData> for CommandData {
+    fn from(jump_command_data: JumpCommandData) -> Self {
         CommandData {
-            count: self.count,
-            key_code: self.key_code,
-            modifiers: self.modifiers,
+            count: jump_command_data.count,
+            keys: jump_command_data.keys,
             range: None,
+            register: None,
         }
     }
 }
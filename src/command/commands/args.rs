@@ -0,0 +1,151 @@
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentTarget {
+    Next,
+    Previous,
+    First,
+    Last,
+    // `:n file...` replaces the argument list.
+    NewList(Vec<String>),
+}
+
+// `:n`, `:N`, `:prev`, `:rew` and `:last`. `:wn` writes the current file first.
+pub struct EditArgumentCommand {
+    pub target: ArgumentTarget,
+    pub force: bool,
+    pub write: bool,
+}
+
+impl Command for EditArgumentCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let count = editor.argument_count();
+        let current = editor.current_argument_index();
+        let index = match &self.target {
+            ArgumentTarget::NewList(file_names) => {
+                if self.write {
+                    editor.save_file(self.force)?;
+                }
+                let file_paths = file_names.iter().map(PathBuf::from).collect();
+                return editor.set_arguments(file_paths, self.force);
+            }
+            _ if count == 0 => return Err("The argument list is empty".into()),
+            ArgumentTarget::Next if current + 1 >= count => {
+                return Err("E165: Cannot go beyond last file".into());
+            }
+            ArgumentTarget::Next => current + 1,
+            ArgumentTarget::Previous if current == 0 => {
+                return Err("E164: Cannot go before first file".into());
+            }
+            ArgumentTarget::Previous => current - 1,
+            ArgumentTarget::First => 0,
+            ArgumentTarget::Last => count - 1,
+        };
+        if self.write {
+            editor.save_file(self.force)?;
+        }
+        editor.edit_argument(index, self.force)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct ShowArgumentsCommand;
+
+impl Command for ShowArgumentsCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.status_line = editor.arguments_description();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn edit_argument(
+        editor: &mut Editor,
+        target: ArgumentTarget,
+        force: bool,
+    ) -> GenericResult<()> {
+        EditArgumentCommand {
+            target,
+            force,
+            write: false,
+        }
+        .execute(editor)
+    }
+
+    #[test]
+    fn test_empty_argument_list() {
+        let mut editor = Editor::new();
+        for target in [
+            ArgumentTarget::Next,
+            ArgumentTarget::Previous,
+            ArgumentTarget::First,
+            ArgumentTarget::Last,
+        ] {
+            let error = edit_argument(&mut editor, target, false).unwrap_err();
+            assert_eq!(error.to_string(), "The argument list is empty");
+        }
+    }
+
+    #[test]
+    fn test_argument_list_navigation() {
+        let dir = tempfile::tempdir().unwrap();
+        let names: Vec<String> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                fs::write(&path, format!("{}\n", name)).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let mut args = vec!["evi".to_string()];
+        args.extend(names.iter().cloned());
+        let mut editor = Editor::from_cmd_args(args);
+        assert_eq!(editor.buffer, ["a.txt"][..]);
+
+        edit_argument(&mut editor, ArgumentTarget::Next, false).unwrap();
+        assert_eq!(editor.buffer, ["b.txt"][..]);
+        edit_argument(&mut editor, ArgumentTarget::Last, false).unwrap();
+        assert_eq!(editor.buffer, ["c.txt"][..]);
+        assert!(edit_argument(&mut editor, ArgumentTarget::Next, false).is_err());
+
+        ShowArgumentsCommand.execute(&mut editor).unwrap();
+        assert_eq!(
+            editor.status_line,
+            format!("{} {} [{}]", names[0], names[1], names[2])
+        );
+
+        editor.buffer.insert(0, 0, "x").unwrap();
+        editor.is_dirty = true;
+        let error = edit_argument(&mut editor, ArgumentTarget::Previous, false).unwrap_err();
+        assert!(error.to_string().starts_with("E37"));
+        edit_argument(&mut editor, ArgumentTarget::Previous, true).unwrap();
+        assert_eq!(editor.buffer, ["b.txt"][..]);
+        assert!(!editor.is_dirty);
+
+        edit_argument(
+            &mut editor,
+            ArgumentTarget::NewList(vec![names[2].clone()]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(editor.buffer, ["c.txt"][..]);
+        assert_eq!(editor.argument_count(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+use std::any::Any;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+// `:ls`, `:buffers` and `:files`.
+pub struct ListBuffersCommand;
+
+impl Command for ListBuffersCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.status_line = editor.buffer_list_lines().join(" | ");
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:b N`
+pub struct BufferCommand {
+    pub number: usize,
+}
+
+impl Command for BufferCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.switch_to_buffer_number(self.number)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:bd[!] [N]`
+pub struct DeleteBufferCommand {
+    pub number: Option<usize>,
+    pub force: bool,
+}
+
+impl Command for DeleteBufferCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.delete_buffer(self.number, self.force)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Ctrl-^
+pub struct AlternateBufferCommand;
+
+impl Command for AlternateBufferCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.switch_to_alternate_buffer()
+    }
+
+    fn is_reusable(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
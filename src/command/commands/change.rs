@@ -0,0 +1,137 @@
+use std::any::Any;
+
+use crate::buffer::CursorPositionInBuffer;
+use crate::command::base::{Command, JumpCommandData};
+use crate::command::keymap::parse_keys;
+use crate::command::region::get_region;
+use crate::editor::{Editor, EditorCursorData};
+use crate::generic_error::GenericResult;
+use crate::register::{check_writable, Register};
+use crate::util::get_char_class;
+
+// `c{motion}`, `cc`, and `C`, `s` and `S` typed as them: deletes the text of
+// the motion into a register and inserts text in its place. The delete and
+// the text typed are undone together.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Change {
+    pub editor_cursor_data: Option<EditorCursorData>,
+    pub jump_command_data_opt: Option<JumpCommandData>,
+    // The register named with `"x`.
+    pub register: Option<char>,
+    // The text deleted, and the text typed in its place.
+    pub deleted: Option<String>,
+    pub text: Option<String>,
+}
+
+impl Command for Change {
+    fn is_reusable(&self) -> bool {
+        false
+    }
+
+    fn is_modeful(&self) -> bool {
+        true
+    }
+
+    fn is_undoable(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        check_writable(self.register)?;
+        let Some(jump_command_data) = self.jump_command_data_opt.clone() else {
+            return Ok(());
+        };
+        let jump_command_data = change_word(editor, jump_command_data);
+        let cursor_data = editor.snapshot_cursor_data();
+        let region = match get_region(editor, jump_command_data) {
+            Ok(region) => region,
+            Err(e) => {
+                editor.restore_cursor_data(cursor_data);
+                return Err(e);
+            }
+        };
+        let (start, end) = if region.start.cursor_position_in_buffer
+            <= region.end.cursor_position_in_buffer
+        {
+            (region.start, region.end)
+        } else {
+            (region.end, region.start)
+        };
+        let start_position = start.cursor_position_in_buffer;
+        let mut end_position = end.cursor_position_in_buffer;
+        if region.linewise {
+            // The lines are deleted but for an empty line to insert in.
+            let last_row = end_position.row - 1;
+            end_position = CursorPositionInBuffer {
+                row: last_row,
+                col: editor.buffer.line_len(last_row),
+            };
+        }
+        let deleted = editor.buffer.delete(start_position, end_position)?;
+        let register_text = if region.linewise {
+            format!("{}\n", deleted)
+        } else {
+            deleted.clone()
+        };
+        editor
+            .registers
+            .delete(self.register, Register::new(&register_text, region.linewise))?;
+        self.deleted = Some(deleted);
+
+        editor.is_dirty = true;
+        editor.restore_cursor_data(start);
+        self.editor_cursor_data = Some(start);
+        editor.set_insert_mode();
+        Ok(())
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = Some(text);
+    }
+
+    fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let Some(cursor_data) = self.editor_cursor_data {
+            let start = cursor_data.cursor_position_in_buffer;
+            if let Some(text) = &self.text {
+                editor.buffer.delete(start, start.advanced_by(text))?;
+            }
+            if let Some(deleted) = &self.deleted {
+                editor.buffer.insert(start.row, start.col, deleted)?;
+            }
+            editor.restore_cursor_data(cursor_data);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `cw` on a word changes to its end, like `ce`, and on the last character of
+// a word only that character. On blanks it is like `dw`.
+fn change_word(editor: &Editor, jump_command_data: JumpCommandData) -> JumpCommandData {
+    if jump_command_data.keys != parse_keys("w")
+        || editor.get_current_char().is_none_or(char::is_whitespace)
+    {
+        return jump_command_data;
+    }
+    let row = editor.cursor_position_in_buffer.row;
+    let col = editor.cursor_position_in_buffer.col;
+    let class = editor.get_current_char().map(get_char_class);
+    let at_end_of_word = editor.buffer.get_char(row, col + 1).map(get_char_class) != class;
+    match (at_end_of_word, jump_command_data.count) {
+        (true, 1) => JumpCommandData {
+            count: 1,
+            keys: parse_keys("l"),
+        },
+        (true, count) => JumpCommandData {
+            count: count - 1,
+            keys: parse_keys("e"),
+        },
+        (false, count) => JumpCommandData {
+            count,
+            keys: parse_keys("e"),
+        },
+    }
+}
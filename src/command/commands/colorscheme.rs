@@ -0,0 +1,55 @@
+use std::any::Any;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+pub struct ColorschemeCommand {
+    pub name: Option<String>,
+}
+
+impl Command for ColorschemeCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        match &self.name {
+            // Without a name the current color scheme is shown.
+            None => editor.status_line = editor.theme.name.clone(),
+            Some(name) => {
+                editor.theme = editor
+                    .themes
+                    .find(name)
+                    .ok_or_else(|| format!("E185: Cannot find color scheme '{}'", name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorscheme() {
+        let mut editor = Editor::new();
+        let mut command = ColorschemeCommand {
+            name: Some("light".to_string()),
+        };
+        command.execute(&mut editor).unwrap();
+        assert_eq!(editor.theme.name, "light");
+
+        ColorschemeCommand { name: None }
+            .execute(&mut editor)
+            .unwrap();
+        assert_eq!(editor.status_line, "light");
+
+        let mut command = ColorschemeCommand {
+            name: Some("nosuch".to_string()),
+        };
+        let error = command.execute(&mut editor).unwrap_err();
+        assert_eq!(error.to_string(), "E185: Cannot find color scheme 'nosuch'");
+    }
+}
@@ -1,23 +1,23 @@
 use std::any::Any;
 
+use crate::buffer::CursorPositionInBuffer;
 use crate::command::base::Command;
+use crate::command::commands::go_to_line::GoToLineCommand;
 use crate::command::region::get_region;
+use crate::data::{LineAddressType, SimpleLineAddressType};
 use crate::editor::Editor;
 use crate::generic_error::GenericResult;
+use crate::register::{check_writable, Register};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// `x`: deletes the character under the cursor and those after it up to the
+// count, but not past the end of the line.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct DeleteChar {
     pub editor_cursor_data: Option<crate::editor::EditorCursorData>,
-    pub char: Option<char>,
-}
-
-impl Default for DeleteChar {
-    fn default() -> Self {
-        Self {
-            editor_cursor_data: None,
-            char: None,
-        }
-    }
+    pub text: Option<String>,
+    pub count: usize,
+    // The register named with `"x`.
+    pub register: Option<char>,
 }
 
 impl Command for DeleteChar {
@@ -29,62 +29,52 @@ impl Command for DeleteChar {
         true
     }
 
+    fn takes_count(&self) -> bool {
+        true
+    }
+
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        editor.is_dirty = true;
+        check_writable(self.register)?;
         let row = editor.cursor_position_in_buffer.row;
         let col = editor.cursor_position_in_buffer.col;
+        let len = editor.buffer.line_len(row);
+        if col >= len {
+            return Ok(());
+        }
+        editor.is_dirty = true;
         self.editor_cursor_data = Some(editor.snapshot_cursor_data());
-        let line = &editor.buffer.lines[row];
-        let num_of_chars = line.chars().count();
-        if col < num_of_chars {
-            let char = line.chars().nth(col).unwrap();
-            self.char = Some(char);
-            let new_line: String = line
-                .chars()
-                .take(col)
-                .chain(line.chars().skip(col + 1))
-                .collect();
-            let new_num_of_chars = new_line.chars().count();
-            editor.buffer.lines[row] = new_line;
-            if col >= new_num_of_chars && new_num_of_chars > 0 {
-                editor.cursor_position_in_buffer.col = new_num_of_chars - 1;
-                if editor.cursor_position_on_screen.col > 0 {
-                    editor.cursor_position_on_screen.col -= 1;
-                } else {
-                    if editor.cursor_position_on_screen.row > 0 {
-                        editor.cursor_position_on_screen.row -= 1;
-                    } else if editor.window_position_in_buffer.row > 0 {
-                        editor.window_position_in_buffer.row -= 1;
-                    }
-                    editor.cursor_position_on_screen.col = editor.terminal_size.width - 1;
-                }
-            }
+        let start = editor.cursor_position_in_buffer;
+        let end = CursorPositionInBuffer {
+            row,
+            col: len.min(col + self.count.max(1)),
+        };
+        let deleted = editor.buffer.delete(start, end)?;
+        editor.registers.delete(self.register, Register::new(&deleted, false))?;
+        self.text = Some(deleted);
+        let new_num_of_chars = editor.buffer.line_len(row);
+        if col >= new_num_of_chars && new_num_of_chars > 0 {
+            editor.cursor_position_in_buffer.col = new_num_of_chars - 1;
+            editor.fit_cursor_in_window();
         }
         Ok(())
     }
 
     fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        let editor_cursor_data = self.editor_cursor_data.unwrap();
-        let row = editor_cursor_data.cursor_position_in_buffer.row;
-        let col = editor_cursor_data.cursor_position_in_buffer.col;
-        let char = self.char.unwrap();
-
-        let line = &editor.buffer.lines[row];
-        let new_line: String = line
-            .chars()
-            .take(col)
-            .chain(std::iter::once(char))
-            .chain(line.chars().skip(col))
-            .collect();
-        editor.buffer.lines[row] = new_line;
-        editor.restore_cursor_data(editor_cursor_data);
-
+        if let (Some(text), Some(cursor_data)) = (&self.text, self.editor_cursor_data) {
+            let row = cursor_data.cursor_position_in_buffer.row;
+            let col = cursor_data.cursor_position_in_buffer.col;
+            editor.buffer.insert(row, col, text)?;
+            editor.restore_cursor_data(cursor_data);
+        }
         Ok(())
     }
 
     fn redo(&mut self, editor: &mut Editor) -> GenericResult<Option<Box<dyn Command>>> {
-        editor.is_dirty = true;
-        let mut new_delete = Box::new(DeleteChar::default());
+        let mut new_delete = Box::new(DeleteChar {
+            count: self.count,
+            register: self.register,
+            ..Default::default()
+        });
         new_delete.execute(editor)?;
         Ok(Some(new_delete))
     }
@@ -94,21 +84,13 @@ impl Command for DeleteChar {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Delete {
     pub editor_cursor_data: Option<crate::editor::EditorCursorData>,
     pub text: Option<String>,
     pub jump_command_data_opt: Option<crate::command::base::JumpCommandData>,
-}
-
-impl Default for Delete {
-    fn default() -> Self {
-        Self {
-            editor_cursor_data: None,
-            text: None,
-            jump_command_data_opt: None,
-        }
-    }
+    // The register named with `"x`.
+    pub register: Option<char>,
 }
 
 impl Command for Delete {
@@ -121,7 +103,8 @@ impl Command for Delete {
     }
 
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        if let Some(jump_command_data) = self.jump_command_data_opt {
+        check_writable(self.register)?;
+        if let Some(jump_command_data) = self.jump_command_data_opt.clone() {
             let region = get_region(editor, jump_command_data);
             if let Ok(region) = region {
                 let start_cursor_data = region.start;
@@ -130,11 +113,12 @@ impl Command for Delete {
                     start_cursor_data.cursor_position_in_buffer,
                     end_cursor_data.cursor_position_in_buffer,
                 ) {
+                    editor
+                        .registers
+                        .delete(self.register, Register::new(&deleted, region.linewise))?;
                     self.text = Some(deleted);
-                    if start_cursor_data
-                        .cursor_position_in_buffer
-                        .cmp(&end_cursor_data.cursor_position_in_buffer)
-                        == std::cmp::Ordering::Greater
+                    if start_cursor_data.cursor_position_in_buffer
+                        > end_cursor_data.cursor_position_in_buffer
                     {
                         editor.restore_cursor_data(end_cursor_data);
                         self.editor_cursor_data = Some(end_cursor_data);
@@ -142,6 +126,10 @@ impl Command for Delete {
                         editor.restore_cursor_data(start_cursor_data);
                         self.editor_cursor_data = Some(start_cursor_data);
                     }
+                    // The last lines were deleted.
+                    if editor.cursor_position_in_buffer.row >= editor.buffer.len_lines() {
+                        editor.fit_cursor_in_window();
+                    }
                 }
             }
         }
@@ -170,21 +158,26 @@ pub struct DeleteLines {
     pub editor_cursor_data: Option<crate::editor::EditorCursorData>,
     pub line_range: crate::data::LineRange,
     pub text: Option<String>,
+    // The register of `:d x`.
+    pub register: Option<char>,
 }
 
 impl Command for DeleteLines {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        check_writable(self.register)?;
         let start_address = self.line_range.start.clone();
         let end_address = self.line_range.end.clone();
-        let start_row = editor.get_line_number_from(&start_address);
-        let end_row = editor.get_line_number_from(&end_address);
+        let start_row = editor.get_line_number_from(&start_address)?;
+        let end_row = editor.get_line_number_from(&end_address)?;
 
         let start_cursor_data = crate::buffer::CursorPositionInBuffer {
             row: start_row,
             col: 0,
         };
+        // Up to the start of the line after the last one, so that whole
+        // lines are deleted.
         let end_cursor_data = crate::buffer::CursorPositionInBuffer {
-            row: end_row,
+            row: end_row + 1,
             col: 0,
         };
 
@@ -195,10 +188,17 @@ impl Command for DeleteLines {
             start_cursor_data,
             end_cursor_data,
         ) {
+            editor
+                .registers
+                .delete(self.register, Register::new(&deleted, true))?;
             self.text = Some(deleted);
         }
 
-        Ok(())
+        // The cursor goes to the line after the deleted ones.
+        let line_address = LineAddressType::Absolute(SimpleLineAddressType::LineNumber(
+            start_row.min(editor.buffer.len_lines().saturating_sub(1)) + 1,
+        ));
+        GoToLineCommand { line_address }.execute(editor)
     }
 
     fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
@@ -208,6 +208,10 @@ impl Command for DeleteLines {
                 let col = editor_cursor_data.cursor_position_in_buffer.col;
                 editor.buffer.insert(row, col, text)?;
             }
+            let row = editor_cursor_data.cursor_position_in_buffer.row;
+            let line_address =
+                LineAddressType::Absolute(SimpleLineAddressType::LineNumber(row + 1));
+            GoToLineCommand { line_address }.execute(editor)?;
         }
         Ok(())
     }
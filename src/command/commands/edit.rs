@@ -0,0 +1,39 @@
+use std::any::Any;
+use std::path::Path;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+// `:e` and `:e!` read the current file again. `:e file` edits another file in
+// a new buffer, keeping the current one, and `:e #` edits the alternate file.
+// `:e! file` throws away the changes of the current buffer first.
+pub struct EditCommand {
+    pub force: bool,
+    pub file_name: Option<String>,
+}
+
+impl Command for EditCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let file_path = match self.file_name.as_deref() {
+            Some("#") => return editor.switch_to_alternate_buffer(),
+            Some(file_name) if !editor.is_current_file(Path::new(file_name)) => {
+                Path::new(file_name)
+            }
+            _ => {
+                if editor.is_dirty && !self.force {
+                    return Err("E37: No write since last change (add ! to override)".into());
+                }
+                return editor.reload_file();
+            }
+        };
+        if self.force && editor.is_dirty {
+            editor.discard_changes()?;
+        }
+        editor.edit_file(file_path)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
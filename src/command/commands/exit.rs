@@ -4,17 +4,33 @@ use crate::command::base::Command;
 use crate::editor::Editor;
 use crate::generic_error::GenericResult;
 
+// Like vim, evi does not exit while another buffer has unsaved changes.
+fn check_hidden_buffers(editor: &mut Editor) -> GenericResult<()> {
+    match editor.modified_hidden_buffer() {
+        Some(name) => Err(format!("E162: No write since last change for buffer \"{}\"", name).into()),
+        None => Ok(()),
+    }
+}
+
+// With several windows open, the quit commands close the current window only.
+fn quit(editor: &mut Editor) -> GenericResult<()> {
+    if editor.windows().count() > 1 {
+        return editor.close_window();
+    }
+    editor.should_exit = true;
+    Ok(())
+}
+
 pub struct ExitCommand;
 impl Command for ExitCommand {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if editor.windows().count() == 1 {
+            check_hidden_buffers(editor)?;
+        }
         if editor.is_dirty {
-            let result = editor.save_file();
-            if let Err(e) = result {
-                return Err(e);
-            }
+            editor.save_file(false)?;
         }
-        editor.should_exit = true;
-        Ok(())
+        quit(editor)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -22,15 +38,17 @@ impl Command for ExitCommand {
     }
 }
 
-pub struct ExitWithSaveCommand;
+pub struct ExitWithSaveCommand {
+    pub force: bool,
+}
+
 impl Command for ExitWithSaveCommand {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        let result = editor.save_file();
-        if let Err(e) = result {
-            return Err(e);
+        if editor.windows().count() == 1 {
+            check_hidden_buffers(editor)?;
         }
-        editor.should_exit = true;
-        Ok(())
+        editor.save_file(self.force)?;
+        quit(editor)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -41,8 +59,7 @@ impl Command for ExitWithSaveCommand {
 pub struct ExitWithoutSaveCommand;
 impl Command for ExitWithoutSaveCommand {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        editor.should_exit = true;
-        Ok(())
+        quit(editor)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -13,7 +13,7 @@ pub struct GoToLineCommand {
 impl Command for GoToLineCommand {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         log::info!("GoToLineCommand execute");
-        let line_number = editor.get_line_number_from(&self.line_address);
+        let line_number = editor.get_line_number_from(&self.line_address)?;
 
         log::info!("line_number: {}", line_number);
         editor.cursor_position_in_buffer.row = 0;
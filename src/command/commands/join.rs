@@ -0,0 +1,83 @@
+use std::any::Any;
+
+use crate::buffer::CursorPositionInBuffer;
+use crate::command::base::Command;
+use crate::editor::{Editor, EditorCursorData};
+use crate::generic_error::GenericResult;
+
+// `J`: joins the count of lines, at least two, from the cursor line. The
+// blanks at the start of a joined line become one space, none when the line
+// before ends with a blank or the joined line is empty or starts with `)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct JoinLines {
+    pub editor_cursor_data: Option<EditorCursorData>,
+    pub count: usize,
+    // The lines joined and the line they became, for undo.
+    pub text: Option<(String, String)>,
+}
+
+impl Command for JoinLines {
+    fn is_undoable(&self) -> bool {
+        true
+    }
+
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let row = editor.cursor_position_in_buffer.row;
+        let last_row =
+            (row + self.count.max(2) - 1).min(editor.buffer.len_lines().saturating_sub(1));
+        if last_row <= row {
+            return Ok(());
+        }
+        let mut joined = editor.buffer.line(row).to_string();
+        // On the space of the last join, or where the last line was joined.
+        let mut col = 0;
+        for next_row in row + 1..=last_row {
+            let line = editor.buffer.line(next_row).to_string();
+            let line = line.trim_start_matches([' ', '\t']);
+            col = joined.chars().count();
+            if !line.is_empty()
+                && !line.starts_with(')')
+                && !joined.is_empty()
+                && !joined.ends_with([' ', '\t'])
+            {
+                joined.push(' ');
+            }
+            joined.push_str(line);
+        }
+
+        editor.is_dirty = true;
+        self.editor_cursor_data = Some(editor.snapshot_cursor_data());
+        let start = CursorPositionInBuffer { row, col: 0 };
+        let end = CursorPositionInBuffer {
+            row: last_row,
+            col: editor.buffer.line_len(last_row),
+        };
+        let lines = editor.buffer.delete(start, end)?;
+        editor.buffer.insert(row, 0, &joined)?;
+        editor.cursor_position_in_buffer.col = col.min(joined.chars().count().saturating_sub(1));
+        editor.fit_cursor_in_window();
+        self.text = Some((lines, joined));
+        Ok(())
+    }
+
+    fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let (Some((lines, joined)), Some(cursor_data)) = (&self.text, self.editor_cursor_data) {
+            let start = CursorPositionInBuffer {
+                row: cursor_data.cursor_position_in_buffer.row,
+                col: 0,
+            };
+            editor.buffer.delete(start, start.advanced_by(joined))?;
+            editor.buffer.insert(start.row, 0, lines)?;
+            editor.restore_cursor_data(cursor_data);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,49 @@
+use std::any::Any;
+
+use crossterm::event::KeyCode;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+// `q` followed by a register: records the keys typed until the next `q` into
+// it. The `q` that stops the recording is handled where keys are read.
+pub struct RecordMacro {
+    pub key_code: KeyCode,
+}
+
+impl Command for RecordMacro {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        match self.key_code {
+            KeyCode::Char(register) => editor.start_recording(register),
+            _ => Ok(()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `@` followed by a register: types the keys in it, `count` times.
+pub struct ExecuteMacro {
+    pub key_code: KeyCode,
+    pub count: usize,
+}
+
+impl Command for ExecuteMacro {
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        match self.key_code {
+            KeyCode::Char(register) => editor.execute_register(register, self.count),
+            _ => Ok(()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
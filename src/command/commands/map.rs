@@ -0,0 +1,54 @@
+use std::any::Any;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+use crate::mapping::MapModes;
+
+// `:map {lhs} {rhs}`, `:noremap`, `:map!` and the others for each mode.
+// Without `rhs` the mappings starting with `lhs` are listed, and without
+// either all of them.
+pub struct MapCommand {
+    pub modes: MapModes,
+    pub lhs: Option<String>,
+    pub rhs: Option<String>,
+    pub noremap: bool,
+}
+
+impl Command for MapCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let (Some(lhs), Some(rhs)) = (&self.lhs, &self.rhs) {
+            editor.mappings.add(self.modes, lhs, rhs, self.noremap);
+            return Ok(());
+        }
+        let lines = editor
+            .mappings
+            .list(self.modes, self.lhs.as_deref().unwrap_or(""));
+        if lines.is_empty() {
+            editor.status_line = "No mapping found".to_string();
+        } else {
+            editor.show_lines(lines);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:unmap {lhs}`, `:unmap!` and the others for each mode.
+pub struct UnmapCommand {
+    pub modes: MapModes,
+    pub lhs: String,
+}
+
+impl Command for UnmapCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.mappings.remove(self.modes, &self.lhs)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
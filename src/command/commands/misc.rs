@@ -10,22 +10,21 @@ pub struct DisplayFile;
 impl Command for DisplayFile {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         let file_name = editor.current_file_name().unwrap_or("No Name".to_string());
+        let flags = if editor.buffer.read_only { " [readonly]" } else { "" };
 
-        if editor.buffer.lines.is_empty() {
-            editor.status_line = format!("\"{}\" -- No lines in buffer --", file_name);
+        if editor.buffer.is_empty() {
+            editor.status_line = format!("\"{}\"{} -- No lines in buffer --", file_name, flags);
         } else {
             // "file_name" line n of m --p%-- col c char d
             editor.status_line = format!(
-                "\"{}\" line {} of {} --{}%-- col {} char {}",
+                "\"{}\"{} line {} of {} --{}%-- col {} char {}",
                 file_name,
+                flags,
                 editor.cursor_position_in_buffer.row + 1,
-                editor.buffer.lines.len(),
-                (editor.cursor_position_in_buffer.row + 1) * 100 / editor.buffer.lines.len(),
+                editor.buffer.len_lines(),
+                (editor.cursor_position_in_buffer.row + 1) * 100 / editor.buffer.len_lines(),
                 editor.cursor_position_in_buffer.col + 1,
-                editor.buffer.lines[editor.cursor_position_in_buffer.row]
-                    .chars()
-                    .nth(editor.cursor_position_in_buffer.col)
-                    .unwrap_or(' ')
+                editor.get_current_char().unwrap_or(' ')
             );
         }
 
@@ -37,6 +36,19 @@ impl Command for DisplayFile {
     }
 }
 
+// Ctrl-L: repaints the whole terminal, over whatever else was written to it.
+pub struct Redraw;
+impl Command for Redraw {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.renderer.invalidate();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,7 +64,7 @@ mod tests {
     #[test]
     fn display_file_with_content() {
         let mut editor = Editor::new();
-        editor.buffer.lines = vec!["abc".to_string()];
+        editor.buffer = crate::buffer::Buffer::from_lines(&["abc"]);
         editor.cursor_position_in_buffer.col = 1;
         let mut cmd = DisplayFile;
         cmd.execute(&mut editor).unwrap();
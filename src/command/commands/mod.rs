@@ -5,8 +5,26 @@ pub mod misc;
 pub mod insert;
 pub mod esc;
 pub mod delete;
+pub mod change;
 pub mod undo;
+pub mod repeat;
+pub mod macros;
 pub mod append;
 pub mod print;
 pub mod go_to_line;
 pub mod substitute;
+pub mod set;
+pub mod write;
+pub mod recover;
+pub mod edit;
+pub mod args;
+pub mod buffers;
+pub mod window;
+pub mod colorscheme;
+pub mod scroll;
+pub mod map;
+pub mod yank;
+pub mod put;
+pub mod replace;
+pub mod join;
+pub mod switch_case;
@@ -1,32 +1,19 @@
 use std::any::Any;
 
 use crate::command::base::Command;
+use crate::command::commands::go_to_line::GoToLineCommand;
+use crate::data::{LineAddressType, SimpleLineAddressType};
 use crate::editor::Editor;
 use crate::generic_error::GenericResult;
-use crate::util::{get_char_width, get_line_height};
+use crate::util::{get_char_class, get_wrapped_position, CharClass};
 
 pub struct ForwardChar;
 impl Command for ForwardChar {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        let line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
-        let num_of_chars = line.chars().count();
+        let num_of_chars = editor.get_num_of_current_line_chars();
         if editor.cursor_position_in_buffer.col + 1 < num_of_chars {
-            let c = line
-                .chars()
-                .nth(editor.cursor_position_in_buffer.col)
-                .unwrap();
-            let char_width = get_char_width(c);
-
+            editor.advance_cursor_over(editor.cursor_position_in_buffer.col);
             editor.cursor_position_in_buffer.col += 1;
-            editor.cursor_position_on_screen.col += char_width;
-            if editor.cursor_position_on_screen.col >= editor.terminal_size.width {
-                editor.cursor_position_on_screen.col = 0;
-                if editor.cursor_position_on_screen.row < editor.content_height() - 1 {
-                    editor.cursor_position_on_screen.row += 1;
-                } else {
-                    editor.window_position_in_buffer.row += 1;
-                }
-            }
         }
         Ok(())
     }
@@ -41,22 +28,7 @@ impl Command for BackwardChar {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         if editor.cursor_position_in_buffer.col > 0 {
             editor.cursor_position_in_buffer.col -= 1;
-            let line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
-            let c = line
-                .chars()
-                .nth(editor.cursor_position_in_buffer.col)
-                .unwrap();
-            let char_width = get_char_width(c);
-            if editor.cursor_position_on_screen.col >= char_width {
-                editor.cursor_position_on_screen.col -= char_width;
-            } else {
-                if editor.cursor_position_on_screen.row > 0 {
-                    editor.cursor_position_on_screen.row -= 1;
-                } else if editor.window_position_in_buffer.row > 0 {
-                    editor.window_position_in_buffer.row -= 1;
-                }
-                editor.cursor_position_on_screen.col = editor.terminal_size.width - char_width;
-            }
+            editor.retreat_cursor_over(editor.cursor_position_in_buffer.col);
         }
         Ok(())
     }
@@ -84,8 +56,7 @@ impl Command for MoveBeginningOfLine {
 pub struct MoveEndOfLine;
 impl Command for MoveEndOfLine {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        let line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
-        let num_of_chars = line.chars().count();
+        let num_of_chars = editor.get_num_of_current_line_chars();
         let mut forward_char = ForwardChar {};
         while editor.cursor_position_in_buffer.col + 1 < num_of_chars {
             forward_char.execute(editor)?;
@@ -102,38 +73,28 @@ pub struct NextLine;
 impl Command for NextLine {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         let next_row = editor.cursor_position_in_buffer.row + 1;
-        if next_row < editor.buffer.lines.len() {
+        if next_row < editor.buffer.len_lines() {
             // 通常の場合：次の行に移動
             let current_cursor_col_in_buffer = editor.cursor_position_in_buffer.col;
 
             // 現在行の残りを一気に飛ばす。折り返し行数を計算し、
             // スクロールはここでのみ行う。
-            let current_line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
+            let current_line = editor.buffer.line(editor.cursor_position_in_buffer.row);
 
             // 現在のカーソル位置が行の何行目に相当するかを計算
-            let mut width = 0usize;
-            let mut cursor_row_in_line = 0usize;
-            for (i, c) in current_line.chars().enumerate() {
-                if i >= editor.cursor_position_in_buffer.col {
-                    break;
-                }
-                width += get_char_width(c) as usize;
-                if width >= editor.terminal_size.width as usize {
-                    width = 0;
-                    cursor_row_in_line += 1;
-                }
-            }
+            let cursor_row_in_line = if editor.wraps() {
+                get_wrapped_position(
+                    current_line.chars().take(editor.cursor_position_in_buffer.col),
+                    editor.content_width(),
+                    editor.options.tab_stop,
+                )
+                .0
+            } else {
+                0
+            };
 
             // 行全体が何行に折り返されるかを計算
-            width = 0;
-            let mut line_height = 1usize;
-            for c in current_line.chars() {
-                width += get_char_width(c) as usize;
-                if width >= editor.terminal_size.width as usize {
-                    width = 0;
-                    line_height += 1;
-                }
-            }
+            let line_height = editor.line_height(current_line.chars());
 
             let remaining_lines = line_height - cursor_row_in_line;
             let new_screen_row = editor.cursor_position_on_screen.row + remaining_lines as u16;
@@ -143,8 +104,8 @@ impl Command for NextLine {
                 let overflow = new_screen_row - (editor.content_height() - 1);
                 let mut removed_screen_lines = 0usize;
                 for i in 0..overflow as usize {
-                    let line = &editor.buffer.lines[editor.window_position_in_buffer.row + i];
-                    removed_screen_lines += get_line_height(line, editor.terminal_size.width);
+                    let line = editor.buffer.line(editor.window_position_in_buffer.row + i);
+                    removed_screen_lines += editor.line_height(line.chars());
                 }
                 editor.window_position_in_buffer.row += overflow as usize;
                 let new_row = editor.cursor_position_on_screen.row as isize
@@ -158,16 +119,15 @@ impl Command for NextLine {
             // Ensure the entire current line is visible. If the line would
             // extend beyond the bottom of the screen, scroll the window up
             // until it fits (leaving at least one blank line after it).
-            let next_line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
-            let next_line_height =
-                get_line_height(next_line, editor.terminal_size.width) as u16;
+            let next_line_height = editor
+                .line_height(editor.buffer.line(editor.cursor_position_in_buffer.row).chars())
+                as u16;
             while editor.cursor_position_on_screen.row + next_line_height
                 >= editor.content_height()
-                && editor.window_position_in_buffer.row + 1 < editor.buffer.lines.len()
+                && editor.window_position_in_buffer.row + 1 < editor.buffer.len_lines()
             {
-                let first_line = &editor.buffer.lines[editor.window_position_in_buffer.row];
-                let first_line_height =
-                    get_line_height(first_line, editor.terminal_size.width) as u16;
+                let first_line = editor.buffer.line(editor.window_position_in_buffer.row);
+                let first_line_height = editor.line_height(first_line.chars()) as u16;
                 editor.window_position_in_buffer.row += 1;
                 editor.cursor_position_on_screen.row = editor
                     .cursor_position_on_screen
@@ -176,7 +136,7 @@ impl Command for NextLine {
             }
 
             // 目的の列に移動
-            let num_of_chars_of_next_line = next_line.chars().count();
+            let num_of_chars_of_next_line = editor.get_num_of_current_line_chars();
             let destination_col = if current_cursor_col_in_buffer > num_of_chars_of_next_line {
                 num_of_chars_of_next_line
             } else {
@@ -185,7 +145,6 @@ impl Command for NextLine {
 
             editor.cursor_position_in_buffer.col = 0;
             editor.cursor_position_on_screen.col = 0;
-            editor.window_position_in_buffer.col = 0;
 
             let mut forward_char = ForwardChar {};
             for _ in 0..destination_col {
@@ -199,8 +158,8 @@ impl Command for NextLine {
                 // スクロール可能かチェック：現在のウィンドウ位置 + コンテンツ高さが
                 // バッファの総行数より小さい場合
                 let max_window_start =
-                    if editor.buffer.lines.len() > editor.content_height() as usize {
-                        editor.buffer.lines.len() - editor.content_height() as usize
+                    if editor.buffer.len_lines() > editor.content_height() as usize {
+                        editor.buffer.len_lines() - editor.content_height() as usize
                     } else {
                         0
                     };
@@ -234,9 +193,8 @@ impl Command for PreviousLine {
 
             editor.cursor_position_in_buffer.row -= 1;
 
-            let line = &editor.buffer.lines[editor.cursor_position_in_buffer.row];
-            let num_of_lines_on_screen =
-                get_line_height(line, editor.terminal_size.width);
+            let line = editor.buffer.line(editor.cursor_position_in_buffer.row);
+            let num_of_lines_on_screen = editor.line_height(line.chars());
 
             if editor.cursor_position_on_screen.row >= num_of_lines_on_screen as u16 {
                 editor.cursor_position_on_screen.row -= num_of_lines_on_screen as u16;
@@ -263,41 +221,73 @@ pub struct ForwardWord;
 impl Command for ForwardWord {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         let mut forward_char = ForwardChar {};
-        let mut num_of_chars = editor.get_num_of_current_line_chars();
-        forward_char.execute(editor)?;
-        'outer: loop {
-            if editor.cursor_position_in_buffer.col + 1 < num_of_chars {
-                while editor.cursor_position_in_buffer.col + 1 < num_of_chars {
-                    let c = editor.get_current_char().unwrap();
-                    if c.is_whitespace() {
-                        break;
-                    }
-                    forward_char.execute(editor)?;
-                }
-                while editor.cursor_position_in_buffer.col + 1 < num_of_chars {
-                    let c = editor.get_current_char().unwrap();
-                    if !c.is_whitespace() {
-                        break 'outer;
-                    }
-                    forward_char.execute(editor)?;
-                }
-            } else if editor.cursor_position_in_buffer.row + 1 < editor.buffer.lines.len() {
+        let mut class = editor.get_current_char().map(get_char_class);
+        // Past the rest of the word under the cursor and the blanks after it,
+        // the end of a line being a blank, to the next character of another
+        // class.
+        loop {
+            if editor.cursor_position_in_buffer.col + 1 < editor.get_num_of_current_line_chars() {
+                forward_char.execute(editor)?;
+            } else if editor.cursor_position_in_buffer.row + 1 < editor.buffer.len_lines() {
+                let mut next_line = NextLine {};
+                next_line.execute(editor)?;
+                let mut move_beginning_of_line = MoveBeginningOfLine {};
+                move_beginning_of_line.execute(editor)?;
+                class = Some(CharClass::Blank);
+            } else {
+                break;
+            }
+            let Some(c) = editor.get_current_char() else {
+                continue;
+            };
+            let c_class = get_char_class(c);
+            if c_class != CharClass::Blank && Some(c_class) != class {
+                break;
+            }
+            class = Some(c_class);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `e`: to the last character of the word, or of the next one when the cursor
+// is already there.
+pub struct ForwardWordEnd;
+impl Command for ForwardWordEnd {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let mut forward_char = ForwardChar {};
+        let at_end_of_line =
+            |editor: &Editor| editor.cursor_position_in_buffer.col + 1 >= editor.get_num_of_current_line_chars();
+        // Past the character under the cursor and the blanks after it, over
+        // the ends of lines.
+        loop {
+            if !at_end_of_line(editor) {
+                forward_char.execute(editor)?;
+            } else if editor.cursor_position_in_buffer.row + 1 < editor.buffer.len_lines() {
                 let mut next_line = NextLine {};
                 next_line.execute(editor)?;
                 let mut move_beginning_of_line = MoveBeginningOfLine {};
                 move_beginning_of_line.execute(editor)?;
-                num_of_chars = editor.get_num_of_current_line_chars();
-                while editor.cursor_position_in_buffer.col + 1 < num_of_chars {
-                    let c = editor.get_current_char().unwrap();
-                    if !c.is_whitespace() {
-                        break 'outer;
-                    }
-                    forward_char.execute(editor)?;
-                }
             } else {
+                return Ok(());
+            }
+            if editor.get_current_char().is_some_and(|c| !c.is_whitespace()) {
                 break;
             }
         }
+        while !at_end_of_line(editor) {
+            let row = editor.cursor_position_in_buffer.row;
+            let col = editor.cursor_position_in_buffer.col;
+            let class = editor.get_current_char().map(get_char_class);
+            if editor.buffer.get_char(row, col + 1).map(get_char_class) != class {
+                break;
+            }
+            forward_char.execute(editor)?;
+        }
         Ok(())
     }
 
@@ -310,47 +300,31 @@ pub struct BackwardWord;
 impl Command for BackwardWord {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
         let mut backward_char = BackwardChar {};
-        if editor.cursor_position_in_buffer.col > 0 {
-            backward_char.execute(editor)?;
-        }
-        'outer: loop {
+        // Back over blanks and the ends of lines to a word.
+        loop {
             if editor.cursor_position_in_buffer.col > 0 {
-                while editor.cursor_position_in_buffer.col > 0 {
-                    let c = editor.get_current_char().unwrap();
-                    if !c.is_whitespace() {
-                        break;
-                    }
-                    backward_char.execute(editor)?;
-                }
-                while editor.cursor_position_in_buffer.col > 0 {
-                    let c = editor.get_current_char().unwrap();
-                    if c.is_whitespace() {
-                        break 'outer;
-                    }
-                    backward_char.execute(editor)?;
-                }
-                if editor.cursor_position_in_buffer.col == 0 {
-                    break 'outer;
-                }
+                backward_char.execute(editor)?;
             } else if editor.cursor_position_in_buffer.row > 0 {
                 let mut previous_line = PreviousLine {};
                 previous_line.execute(editor)?;
                 let mut move_end_of_line = MoveEndOfLine {};
                 move_end_of_line.execute(editor)?;
-                while editor.cursor_position_in_buffer.col > 0 {
-                    let c = editor.get_current_char().unwrap();
-                    if c.is_whitespace() {
-                        break 'outer;
-                    }
-                    backward_char.execute(editor)?;
-                }
             } else {
+                return Ok(());
+            }
+            if editor.get_current_char().is_some_and(|c| !c.is_whitespace()) {
                 break;
             }
         }
-        if editor.cursor_position_in_buffer.col != 0 {
-            let mut forward_char = ForwardChar {};
-            forward_char.execute(editor)?;
+        // To its first character.
+        let class = editor.get_current_char().map(get_char_class);
+        while editor.cursor_position_in_buffer.col > 0 {
+            let row = editor.cursor_position_in_buffer.row;
+            let col = editor.cursor_position_in_buffer.col;
+            if editor.buffer.get_char(row, col - 1).map(get_char_class) != class {
+                break;
+            }
+            backward_char.execute(editor)?;
         }
         Ok(())
     }
@@ -359,3 +333,22 @@ impl Command for BackwardWord {
         self
     }
 }
+
+// `gg`: to the line of the count, the first one without a count.
+pub struct MoveToLine {
+    pub count: usize,
+}
+impl Command for MoveToLine {
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let line_address = LineAddressType::Absolute(SimpleLineAddressType::LineNumber(self.count));
+        GoToLineCommand { line_address }.execute(editor)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
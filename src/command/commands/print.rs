@@ -1,18 +1,64 @@
 use std::any::Any;
 
 use crate::command::base::Command;
-use crate::data::LineRange;
+use crate::command::commands::go_to_line::GoToLineCommand;
+use crate::data::{LineAddressType, LineRange, SimpleLineAddressType};
 use crate::editor::Editor;
 use crate::generic_error::GenericResult;
+use crate::options::ListChars;
+use crate::util::get_char_width_at;
 
+// `:p`, `:nu` (`:#`) and `:l`: show the lines of the range, with their
+// numbers or in the way of `:set list`, and move the cursor to the last one.
 pub struct PrintCommand {
-    pub line_range: LineRange
+    pub line_range: LineRange,
+    pub number: bool,
+    pub list: bool,
 }
 
 impl Command for PrintCommand {
-    fn execute(&mut self, _editor: &mut Editor) -> GenericResult<()> {
-        // TODO: Implement PrintCommand
-        log::info!("PrintCommand execute");
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let start = editor.get_line_number_from(&self.line_range.start)?;
+        let end = editor.get_line_number_from(&self.line_range.end)?;
+        if start > end || end >= editor.buffer.len_lines() {
+            return Err("E16: Invalid range".into());
+        }
+
+        let mut lines = Vec::new();
+        for row in start..=end {
+            let mut line = editor.buffer.line(row).to_string();
+            if self.list {
+                line = list_line(&line, editor.options.tab_stop, &editor.options.list_chars);
+            }
+            if self.number {
+                line = format!("{:>3} {}", row + 1, line);
+            }
+            lines.push(line);
+        }
+
+        GoToLineCommand {
+            line_address: LineAddressType::Absolute(SimpleLineAddressType::LineNumber(end + 1)),
+        }
+        .execute(editor)?;
+        editor.show_lines(lines);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:=`, `:.=` and `:/pattern/=`: show the number of the line, the last line
+// when no address is given.
+pub struct LineNumberCommand {
+    pub line_address: LineAddressType,
+}
+
+impl Command for LineNumberCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let row = editor.get_line_number_from(&self.line_address)?;
+        editor.show_lines(vec![(row + 1).to_string()]);
         Ok(())
     }
 
@@ -20,3 +66,94 @@ impl Command for PrintCommand {
         self
     }
 }
+
+// Shows the tabs and the end of `line` with 'listchars', tabs as `^I` when it
+// has no `tab`, and the other control characters as `^X`.
+fn list_line(line: &str, tab_stop: usize, list_chars: &ListChars) -> String {
+    let mut listed = String::new();
+    let mut col = 0;
+    for c in line.chars() {
+        match (c, list_chars.tab) {
+            ('\t', Some((first, fill, last))) => {
+                let width = get_char_width_at(c, col, tab_stop) as usize;
+                for cell in 0..width {
+                    listed.push(match last {
+                        Some(last) if cell + 1 == width => last,
+                        _ if cell == 0 => first,
+                        _ => fill,
+                    });
+                }
+                col += width;
+            }
+            _ if c.is_ascii_control() => {
+                listed.push('^');
+                listed.push(((c as u8) ^ 0x40) as char);
+                col += 2;
+            }
+            _ => {
+                listed.push(c);
+                col += get_char_width_at(c, col, tab_stop) as usize;
+            }
+        }
+    }
+    listed.extend(list_chars.eol);
+    listed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_line() {
+        let list_chars = ListChars::default();
+        assert_eq!(list_line("a\tb", 8, &list_chars), "a^Ib$");
+        let list_chars = ListChars::parse("tab:>-").unwrap();
+        assert_eq!(list_line("a\tb", 4, &list_chars), "a>--b");
+        let list_chars = ListChars::parse("tab:<->").unwrap();
+        assert_eq!(list_line("\tb", 4, &list_chars), "<-->b");
+    }
+
+    #[test]
+    fn test_print_lines() {
+        let mut editor = Editor::new();
+        editor.resize_terminal(20, 10);
+        editor.buffer = crate::buffer::Buffer::from_lines(&["one", "two", "three"]);
+        let range = |start, end| LineRange {
+            start: LineAddressType::Absolute(SimpleLineAddressType::LineNumber(start)),
+            end: LineAddressType::Absolute(SimpleLineAddressType::LineNumber(end)),
+        };
+
+        let mut print = PrintCommand {
+            line_range: range(2, 2),
+            number: true,
+            list: false,
+        };
+        print.execute(&mut editor).unwrap();
+        assert_eq!(editor.status_line, "  2 two");
+        assert_eq!(editor.cursor_position_in_buffer.row, 1);
+        assert!(editor.pager.is_none());
+
+        let mut print = PrintCommand {
+            line_range: range(1, 3),
+            number: false,
+            list: false,
+        };
+        print.execute(&mut editor).unwrap();
+        assert_eq!(
+            editor.pager.as_ref().unwrap().visible_rows(9),
+            ["one", "two", "three"]
+        );
+        assert_eq!(editor.cursor_position_in_buffer.row, 2);
+
+        let mut print = PrintCommand {
+            line_range: range(2, 4),
+            number: false,
+            list: false,
+        };
+        assert_eq!(
+            print.execute(&mut editor).unwrap_err().to_string(),
+            "E16: Invalid range"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::any::Any;
+
+use crate::buffer::CursorPositionInBuffer;
+use crate::command::base::Command;
+use crate::editor::{Editor, EditorCursorData};
+use crate::generic_error::GenericResult;
+
+// `p` and `P`: puts the text of a register after or before the cursor, or
+// below or above the cursor line when it is whole lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Put {
+    pub editor_cursor_data: Option<EditorCursorData>,
+    // `P`
+    pub before: bool,
+    // The number of times the text is put.
+    pub count: usize,
+    // The register named with `"x`.
+    pub register: Option<char>,
+    // The text put and where, for undo.
+    pub text: Option<String>,
+    pub start: Option<CursorPositionInBuffer>,
+}
+
+impl Command for Put {
+    fn is_undoable(&self) -> bool {
+        true
+    }
+
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let name = self.register.unwrap_or('"');
+        let register = editor
+            .registers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("E353: Nothing in register {}", name))?;
+        editor.is_dirty = true;
+        self.editor_cursor_data = Some(editor.snapshot_cursor_data());
+
+        let cursor = editor.cursor_position_in_buffer;
+        let text = register.text.repeat(self.count.max(1));
+        let (start, new_cursor) = if register.linewise {
+            let row = if self.before { cursor.row } else { cursor.row + 1 };
+            let start = CursorPositionInBuffer { row, col: 0 };
+            // On the first non-blank of the first line put.
+            let indent = text.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+            (start, CursorPositionInBuffer { row, col: indent })
+        } else {
+            let line_len = editor.buffer.line_len(cursor.row);
+            let col = if self.before || line_len == 0 {
+                cursor.col
+            } else {
+                (cursor.col + 1).min(line_len)
+            };
+            let start = CursorPositionInBuffer { row: cursor.row, col };
+            // On the last character put, or on the first when it is more
+            // than one line.
+            let new_cursor = if text.contains('\n') {
+                start
+            } else {
+                CursorPositionInBuffer {
+                    row: cursor.row,
+                    col: col + text.chars().count() - 1,
+                }
+            };
+            (start, new_cursor)
+        };
+        editor.buffer.insert(start.row, start.col, &text)?;
+        self.start = Some(start);
+        self.text = Some(text);
+
+        editor.cursor_position_in_buffer = new_cursor;
+        editor.fit_cursor_in_window();
+        Ok(())
+    }
+
+    fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let (Some(text), Some(start), Some(cursor_data)) =
+            (&self.text, self.start, self.editor_cursor_data)
+        {
+            editor.buffer.delete(start, start.advanced_by(text))?;
+            editor.restore_cursor_data(cursor_data);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
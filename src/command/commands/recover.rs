@@ -0,0 +1,22 @@
+use std::any::Any;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+pub struct RecoverCommand {
+    pub force: bool,
+}
+
+impl Command for RecoverCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if editor.is_dirty && !self.force {
+            return Err("E37: No write since last change (add ! to override)".into());
+        }
+        editor.recover()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,62 @@
+use std::any::Any;
+
+use crate::command::base::{Command, CommandData, JumpCommandData};
+use crate::command::factory::default_keymaps;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+// `.`: repeats the last change at the cursor, with the text typed after it
+// when it went to insert mode.
+pub struct Repeat {
+    // A count other than 1 replaces the count of the change, as `3.` after
+    // `dw` is `d3w`.
+    pub count: usize,
+}
+
+impl Command for Repeat {
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let Some(last_change) = editor.last_change.clone() else {
+            return Ok(());
+        };
+        let command_data = if self.count > 1 {
+            with_count(last_change.command_data, self.count)
+        } else {
+            last_change.command_data
+        };
+        editor.execute_command(command_data)?;
+        if editor.is_insert_mode() {
+            for c in last_change.text.chars() {
+                if c == '\n' {
+                    editor.append_new_line()?;
+                } else {
+                    editor.insert_char(c)?;
+                }
+            }
+            editor.set_command_mode();
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// The count of an operator is in its range.
+fn with_count(command_data: CommandData, count: usize) -> CommandData {
+    match command_data.range {
+        Some(range) if default_keymaps().is_operator(&command_data.keys) => CommandData {
+            count: 1,
+            range: Some(JumpCommandData { count, ..range }),
+            ..command_data
+        },
+        _ => CommandData {
+            count,
+            ..command_data
+        },
+    }
+}
@@ -0,0 +1,90 @@
+use std::any::Any;
+
+use crossterm::event::KeyCode;
+
+use crate::buffer::CursorPositionInBuffer;
+use crate::command::base::Command;
+use crate::editor::{Editor, EditorCursorData};
+use crate::generic_error::GenericResult;
+
+// `r` followed by a character: replaces the character under the cursor and
+// those after it up to the count with it, nothing when the line is shorter.
+// `r<CR>` replaces them with a line break.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ReplaceChar {
+    pub editor_cursor_data: Option<EditorCursorData>,
+    pub key_code: Option<KeyCode>,
+    pub count: usize,
+    // The text replaced and what replaced it, for undo.
+    pub text: Option<(String, String)>,
+}
+
+impl Command for ReplaceChar {
+    fn is_undoable(&self) -> bool {
+        true
+    }
+
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let count = self.count.max(1);
+        let start = editor.cursor_position_in_buffer;
+        let end = CursorPositionInBuffer {
+            row: start.row,
+            col: start.col + count,
+        };
+        if end.col > editor.buffer.line_len(start.row) {
+            return Ok(());
+        }
+        let (new_text, new_cursor) = match self.key_code {
+            Some(KeyCode::Char(c)) => (
+                c.to_string().repeat(count),
+                CursorPositionInBuffer {
+                    row: start.row,
+                    col: end.col - 1,
+                },
+            ),
+            Some(KeyCode::Tab) => (
+                "\t".repeat(count),
+                CursorPositionInBuffer {
+                    row: start.row,
+                    col: end.col - 1,
+                },
+            ),
+            Some(KeyCode::Enter) => (
+                "\n".to_string(),
+                CursorPositionInBuffer {
+                    row: start.row + 1,
+                    col: 0,
+                },
+            ),
+            _ => return Ok(()),
+        };
+        editor.is_dirty = true;
+        self.editor_cursor_data = Some(editor.snapshot_cursor_data());
+        let old_text = editor.buffer.delete(start, end)?;
+        editor.buffer.insert(start.row, start.col, &new_text)?;
+        self.text = Some((old_text, new_text));
+        editor.cursor_position_in_buffer = new_cursor;
+        editor.fit_cursor_in_window();
+        Ok(())
+    }
+
+    fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let (Some((old_text, new_text)), Some(cursor_data)) =
+            (&self.text, self.editor_cursor_data)
+        {
+            let start = cursor_data.cursor_position_in_buffer;
+            editor.buffer.delete(start, start.advanced_by(new_text))?;
+            editor.buffer.insert(start.row, start.col, old_text)?;
+            editor.restore_cursor_data(cursor_data);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
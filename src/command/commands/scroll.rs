@@ -0,0 +1,105 @@
+use std::any::Any;
+
+use crossterm::event::KeyCode;
+
+use crate::command::base::Command;
+use crate::command::commands::move_cursor::{BackwardChar, ForwardChar};
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+// `z` followed by `key`. `zh`, `zl`, `zs` and `ze` scroll the window sideways
+// when lines do not wrap; a count repeats `zh` and `zl`.
+pub struct ScrollCommand {
+    pub key_code: KeyCode,
+}
+
+impl Command for ScrollCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if editor.wraps() {
+            return Ok(());
+        }
+        let width = editor.content_width().max(1) as usize;
+        let off = editor.options.side_scroll_off.min((width - 1) / 2);
+        let col = editor.cursor_position_on_screen.col as usize;
+        let left = &mut editor.window_position_in_buffer.col;
+        match self.key_code {
+            KeyCode::Char('h') | KeyCode::Left => *left = left.saturating_sub(1),
+            KeyCode::Char('l') | KeyCode::Right => *left += 1,
+            KeyCode::Char('s') => *left = col.saturating_sub(off),
+            KeyCode::Char('e') => *left = (col + off + 1).saturating_sub(width),
+            _ => return Ok(()),
+        }
+        keep_cursor_in_view(editor)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Moves the cursor along its line into the columns the window shows, like vim
+// does when the text scrolls away from under it.
+fn keep_cursor_in_view(editor: &mut Editor) -> GenericResult<()> {
+    let (first, last) = editor.cursor_columns();
+    while (editor.cursor_position_on_screen.col as usize) < first {
+        let col = editor.cursor_position_in_buffer.col;
+        ForwardChar.execute(editor)?;
+        if editor.cursor_position_in_buffer.col == col {
+            break;
+        }
+    }
+    while editor.cursor_position_on_screen.col as usize > last {
+        let col = editor.cursor_position_in_buffer.col;
+        BackwardChar.execute(editor)?;
+        if editor.cursor_position_in_buffer.col == col {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::command::commands::set::SetCommand;
+
+    fn scroll(editor: &mut Editor, key: char) {
+        ScrollCommand {
+            key_code: KeyCode::Char(key),
+        }
+        .execute(editor)
+        .unwrap();
+    }
+
+    #[test]
+    fn test_scroll_sideways() {
+        let mut editor = Editor::new();
+        editor.resize_terminal(10, 3);
+        editor.buffer = Buffer::from_lines(&["0123456789abcdefghij"]);
+        SetCommand {
+            arguments: vec!["nowrap".to_string()],
+        }
+        .execute(&mut editor)
+        .unwrap();
+
+        // The cursor is moved to stay in the window.
+        scroll(&mut editor, 'l');
+        scroll(&mut editor, 'l');
+        assert_eq!(editor.window_position_in_buffer.col, 2);
+        assert_eq!(editor.cursor_position_in_buffer.col, 2);
+        scroll(&mut editor, 'h');
+        assert_eq!(editor.window_position_in_buffer.col, 1);
+        assert_eq!(editor.cursor_position_in_buffer.col, 2);
+
+        for _ in 0..13 {
+            ForwardChar.execute(&mut editor).unwrap();
+        }
+        scroll(&mut editor, 's');
+        assert_eq!(editor.window_position_in_buffer.col, 15);
+        scroll(&mut editor, 'e');
+        assert_eq!(editor.window_position_in_buffer.col, 6);
+        scroll(&mut editor, 'h');
+        assert_eq!(editor.cursor_position_in_buffer.col, 14);
+    }
+}
@@ -0,0 +1,349 @@
+use std::any::Any;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::file_format::{FileEncoding, LineEnding};
+use crate::generic_error::GenericResult;
+use crate::options::ListChars;
+use crate::syntax::Syntax;
+
+// `:set` arguments take the forms `name`, `noname`, `name=value` and `name?`.
+enum OptionArgument<'a> {
+    Show(&'a str),
+    Enable(&'a str),
+    Disable(&'a str),
+    Assign(&'a str, &'a str),
+}
+
+impl<'a> OptionArgument<'a> {
+    fn parse(argument: &'a str) -> OptionArgument<'a> {
+        if let Some((name, value)) = argument.split_once(['=', ':']) {
+            OptionArgument::Assign(name, value)
+        } else if let Some(name) = argument.strip_suffix('?') {
+            OptionArgument::Show(name)
+        } else if let Some(name) = argument.strip_prefix("no") {
+            OptionArgument::Disable(name)
+        } else {
+            OptionArgument::Enable(argument)
+        }
+    }
+}
+
+pub struct SetCommand {
+    pub arguments: Vec<String>,
+}
+
+impl Command for SetCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        for argument in &self.arguments {
+            if let Err(message) = set_option(editor, argument) {
+                editor.status_line = message;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn set_option(editor: &mut Editor, argument: &str) -> Result<(), String> {
+    let invalid_argument = || format!("E474: Invalid argument: {}", argument);
+    match OptionArgument::parse(argument) {
+        OptionArgument::Assign("fileformat" | "ff", value) => {
+            let line_ending = LineEnding::from_name(value).ok_or_else(invalid_argument)?;
+            if editor.buffer.file_format.line_ending != line_ending {
+                editor.buffer.file_format.line_ending = line_ending;
+                editor.is_dirty = true;
+            }
+        }
+        OptionArgument::Assign("fileencoding" | "fenc", value) => {
+            let encoding = FileEncoding::from_name(value).ok_or_else(invalid_argument)?;
+            if editor.buffer.file_format.encoding != encoding {
+                editor.buffer.file_format.encoding = encoding;
+                editor.is_dirty = true;
+            }
+        }
+        OptionArgument::Assign("syntax" | "syn", value) => {
+            editor.buffer.syntax = match value {
+                "" | "off" => None,
+                _ => Some(Syntax::new(
+                    editor.grammars.find(value).ok_or_else(invalid_argument)?,
+                )),
+            };
+        }
+        OptionArgument::Enable("bomb") => set_bom(editor, true),
+        OptionArgument::Disable("bomb") => set_bom(editor, false),
+        OptionArgument::Enable(
+            name @ ("number" | "nu" | "relativenumber" | "rnu" | "wrap" | "list"),
+        ) => set_window_flag(editor, name, true),
+        OptionArgument::Disable(
+            name @ ("number" | "nu" | "relativenumber" | "rnu" | "wrap" | "list"),
+        ) => set_window_flag(editor, name, false),
+        OptionArgument::Assign(name @ ("sidescroll" | "ss" | "sidescrolloff" | "siso"), value) => {
+            let value = value
+                .parse()
+                .map_err(|_| format!("E521: Number required after =: {}", argument))?;
+            if name.starts_with("sidescrollo") || name == "siso" {
+                editor.options.side_scroll_off = value;
+            } else {
+                editor.options.side_scroll = value;
+            }
+        }
+        OptionArgument::Assign("tabstop" | "ts", value) => {
+            let value: usize = value
+                .parse()
+                .map_err(|_| format!("E521: Number required after =: {}", argument))?;
+            if value == 0 {
+                return Err(format!("E487: Argument must be positive: {}", argument));
+            }
+            editor.options.tab_stop = value;
+            // The text moves under the cursor.
+            editor.fit_cursor_in_window();
+        }
+        OptionArgument::Enable(
+            name @ ("showmode" | "smd" | "showcmd" | "sc" | "ruler" | "ru" | "timeout" | "to"),
+        ) => set_flag(editor, name, true),
+        OptionArgument::Disable(
+            name @ ("showmode" | "smd" | "showcmd" | "sc" | "ruler" | "ru" | "timeout" | "to"),
+        ) => set_flag(editor, name, false),
+        OptionArgument::Assign("timeoutlen" | "tm", value) => {
+            editor.options.timeout_len = value
+                .parse()
+                .map_err(|_| format!("E521: Number required after =: {}", argument))?;
+        }
+        OptionArgument::Assign("statusline" | "stl", value) => {
+            editor.options.status_line = value.to_string();
+        }
+        OptionArgument::Assign("laststatus" | "ls", value) => {
+            let value = value
+                .parse()
+                .map_err(|_| format!("E521: Number required after =: {}", argument))?;
+            editor.set_last_status(value);
+        }
+        OptionArgument::Assign("listchars" | "lcs", value) => {
+            editor.options.list_chars = ListChars::parse(value).ok_or_else(invalid_argument)?;
+        }
+        OptionArgument::Show(name) | OptionArgument::Enable(name) => {
+            editor.status_line = show_option(editor, name)
+                .ok_or_else(|| format!("E518: Unknown option: {}", argument))?;
+        }
+        OptionArgument::Assign(..) | OptionArgument::Disable(_) => {
+            return Err(format!("E518: Unknown option: {}", argument));
+        }
+    }
+    Ok(())
+}
+
+fn set_bom(editor: &mut Editor, bom: bool) {
+    if editor.buffer.file_format.bom != bom {
+        editor.buffer.file_format.bom = bom;
+        editor.is_dirty = true;
+    }
+}
+
+fn set_flag(editor: &mut Editor, name: &str, value: bool) {
+    match name {
+        "showmode" | "smd" => editor.options.show_mode = value,
+        "showcmd" | "sc" => editor.options.show_cmd = value,
+        "timeout" | "to" => editor.options.timeout = value,
+        _ => editor.options.ruler = value,
+    }
+}
+
+fn set_window_flag(editor: &mut Editor, name: &str, value: bool) {
+    let mut options = editor.window_options();
+    match name {
+        "relativenumber" | "rnu" => options.relative_number = value,
+        "wrap" => options.wrap = value,
+        "list" => options.list = value,
+        _ => options.number = value,
+    }
+    editor.set_window_options(options);
+}
+
+fn show_flag(name: &str, value: bool) -> String {
+    if value {
+        format!("  {}", name)
+    } else {
+        format!("no{}", name)
+    }
+}
+
+fn show_option(editor: &Editor, name: &str) -> Option<String> {
+    let file_format = &editor.buffer.file_format;
+    let window_options = editor.window_options();
+    match name {
+        "number" | "nu" => Some(show_flag("number", window_options.number)),
+        "relativenumber" | "rnu" => {
+            Some(show_flag("relativenumber", window_options.relative_number))
+        }
+        "fileformat" | "ff" => Some(format!("  fileformat={}", file_format.line_ending.name())),
+        "fileencoding" | "fenc" => Some(format!("  fileencoding={}", file_format.encoding.name())),
+        "bomb" => Some(show_flag("bomb", file_format.bom)),
+        "wrap" => Some(show_flag("wrap", window_options.wrap)),
+        "list" => Some(show_flag("list", window_options.list)),
+        "tabstop" | "ts" => Some(format!("  tabstop={}", editor.options.tab_stop)),
+        "listchars" | "lcs" => Some(format!("  listchars={}", editor.options.list_chars)),
+        "showmode" | "smd" => Some(show_flag("showmode", editor.options.show_mode)),
+        "showcmd" | "sc" => Some(show_flag("showcmd", editor.options.show_cmd)),
+        "ruler" | "ru" => Some(show_flag("ruler", editor.options.ruler)),
+        "timeout" | "to" => Some(show_flag("timeout", editor.options.timeout)),
+        "timeoutlen" | "tm" => Some(format!("  timeoutlen={}", editor.options.timeout_len)),
+        "statusline" | "stl" => Some(format!("  statusline={}", editor.options.status_line)),
+        "laststatus" | "ls" => Some(format!("  laststatus={}", editor.last_status())),
+        "sidescroll" | "ss" => Some(format!("  sidescroll={}", editor.options.side_scroll)),
+        "sidescrolloff" | "siso" => Some(format!(
+            "  sidescrolloff={}",
+            editor.options.side_scroll_off
+        )),
+        "syntax" | "syn" => Some(format!(
+            "  syntax={}",
+            editor
+                .buffer
+                .syntax
+                .as_ref()
+                .map_or("", |syntax| syntax.name())
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::window::WindowOptions;
+
+    fn set(editor: &mut Editor, arguments: &[&str]) {
+        let mut command = SetCommand {
+            arguments: arguments.iter().map(|s| s.to_string()).collect(),
+        };
+        command.execute(editor).unwrap();
+    }
+
+    #[test]
+    fn test_set_file_format_options() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["ff=dos", "fenc=sjis", "bomb"]);
+        assert_eq!(editor.buffer.file_format.line_ending, LineEnding::Dos);
+        assert_eq!(editor.buffer.file_format.encoding, FileEncoding::ShiftJis);
+        assert!(editor.buffer.file_format.bom);
+        assert!(editor.is_dirty);
+
+        set(&mut editor, &["fileformat?"]);
+        assert_eq!(editor.status_line, "  fileformat=dos");
+        set(&mut editor, &["fenc"]);
+        assert_eq!(editor.status_line, "  fileencoding=sjis");
+    }
+
+    #[test]
+    fn test_set_invalid_options() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["ff=mac"]);
+        assert_eq!(editor.status_line, "E474: Invalid argument: ff=mac");
+        set(&mut editor, &["nosuchoption"]);
+        assert_eq!(editor.status_line, "E518: Unknown option: nosuchoption");
+        assert!(!editor.is_dirty);
+    }
+
+    #[test]
+    fn test_set_number_options() {
+        let mut editor = Editor::new();
+        editor.resize_terminal(20, 5);
+        editor.buffer = Buffer::from_lines(&["a"; 12]);
+        set(&mut editor, &["nu"]);
+        assert!(editor.window_options().number);
+        assert_eq!(editor.content_width(), 16);
+        set(&mut editor, &["rnu", "nonumber"]);
+        assert_eq!(
+            editor.window_options(),
+            WindowOptions {
+                number: false,
+                relative_number: true,
+                ..Default::default()
+            }
+        );
+        set(&mut editor, &["number?"]);
+        assert_eq!(editor.status_line, "nonumber");
+        set(&mut editor, &["rnu?"]);
+        assert_eq!(editor.status_line, "  relativenumber");
+    }
+
+    #[test]
+    fn test_set_wrap_options() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["nowrap", "ss=5", "sidescrolloff=2"]);
+        assert!(!editor.wraps());
+        assert_eq!(editor.options.side_scroll, 5);
+        assert_eq!(editor.options.side_scroll_off, 2);
+        set(&mut editor, &["wrap?"]);
+        assert_eq!(editor.status_line, "nowrap");
+        set(&mut editor, &["siso?"]);
+        assert_eq!(editor.status_line, "  sidescrolloff=2");
+        set(&mut editor, &["ss=x"]);
+        assert_eq!(editor.status_line, "E521: Number required after =: ss=x");
+    }
+
+    #[test]
+    fn test_set_list_options() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["list", "ts=4", "lcs=tab:>-,trail:~"]);
+        assert!(editor.window_options().list);
+        assert_eq!(editor.options.tab_stop, 4);
+        assert_eq!(editor.options.list_chars.tab, Some(('>', '-', None)));
+        set(&mut editor, &["listchars?"]);
+        assert_eq!(editor.status_line, "  listchars=tab:>-,trail:~");
+        set(&mut editor, &["tabstop"]);
+        assert_eq!(editor.status_line, "  tabstop=4");
+
+        set(&mut editor, &["ts=0"]);
+        assert_eq!(editor.status_line, "E487: Argument must be positive: ts=0");
+        set(&mut editor, &["lcs=tab:>"]);
+        assert_eq!(editor.status_line, "E474: Invalid argument: lcs=tab:>");
+    }
+
+    #[test]
+    fn test_set_status_line_options() {
+        let mut editor = Editor::new();
+        editor.resize_terminal(20, 5);
+        set(&mut editor, &["ruler", "sc", "nosmd", "stl=%f%=%l", "ls=2"]);
+        assert!(editor.options.ruler);
+        assert!(editor.options.show_cmd);
+        assert!(!editor.options.show_mode);
+        assert_eq!(editor.options.status_line, "%f%=%l");
+        assert_eq!(editor.content_height(), 3);
+        set(&mut editor, &["ru?"]);
+        assert_eq!(editor.status_line, "  ruler");
+        set(&mut editor, &["laststatus?"]);
+        assert_eq!(editor.status_line, "  laststatus=2");
+        set(&mut editor, &["ls=x"]);
+        assert_eq!(editor.status_line, "E521: Number required after =: ls=x");
+    }
+
+    #[test]
+    fn test_set_timeout_options() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["noto", "tm=500"]);
+        assert!(!editor.options.timeout);
+        assert_eq!(editor.options.timeout_len, 500);
+        set(&mut editor, &["timeoutlen?"]);
+        assert_eq!(editor.status_line, "  timeoutlen=500");
+        set(&mut editor, &["timeout?"]);
+        assert_eq!(editor.status_line, "notimeout");
+    }
+
+    #[test]
+    fn test_set_syntax() {
+        let mut editor = Editor::new();
+        set(&mut editor, &["syntax=python"]);
+        set(&mut editor, &["syntax?"]);
+        assert_eq!(editor.status_line, "  syntax=python");
+        set(&mut editor, &["syntax=cobol"]);
+        assert_eq!(editor.status_line, "E474: Invalid argument: syntax=cobol");
+        set(&mut editor, &["syn=off"]);
+        assert!(editor.buffer.syntax.is_none());
+    }
+}
@@ -17,8 +17,8 @@ pub struct SubstituteCommand {
 
 impl Command for SubstituteCommand {
     fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
-        let start = editor.get_line_number_from(&self.line_range.start);
-        let end = editor.get_line_number_from(&self.line_range.end);
+        let start = editor.get_line_number_from(&self.line_range.start)?;
+        let end = editor.get_line_number_from(&self.line_range.end)?;
 
         let re = RegexBuilder::new(&self.pattern)
             .multi_line(true)
@@ -27,16 +27,18 @@ impl Command for SubstituteCommand {
             .map_err(|e| GenericError::from(e.to_string()))?;
 
         for i in start..=end {
-            if i >= editor.buffer.lines.len() {
+            let Some(line) = editor.buffer.get_line(i) else {
                 continue;
-            }
-            let line = editor.buffer.lines[i].clone();
+            };
+            let line = line.to_string();
             let new_line = if self.global {
-                re.replace_all(&line, self.replacement.as_str()).to_string()
+                re.replace_all(&line, self.replacement.as_str())
             } else {
-                re.replace(&line, self.replacement.as_str()).to_string()
+                re.replace(&line, self.replacement.as_str())
             };
-            editor.buffer.lines[i] = new_line;
+            if let std::borrow::Cow::Owned(new_line) = new_line {
+                editor.buffer.set_line(i, &new_line);
+            }
         }
         Ok(())
     }
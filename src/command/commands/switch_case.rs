@@ -0,0 +1,75 @@
+use std::any::Any;
+
+use crate::buffer::CursorPositionInBuffer;
+use crate::command::base::Command;
+use crate::editor::{Editor, EditorCursorData};
+use crate::generic_error::GenericResult;
+
+// `~`: switches the case of the character under the cursor and those after
+// it up to the count, not past the end of the line, and moves past them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SwitchCase {
+    pub editor_cursor_data: Option<EditorCursorData>,
+    pub count: usize,
+    // The text before and after the switch, for undo.
+    pub text: Option<(String, String)>,
+}
+
+impl Command for SwitchCase {
+    fn is_undoable(&self) -> bool {
+        true
+    }
+
+    fn takes_count(&self) -> bool {
+        true
+    }
+
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        let start = editor.cursor_position_in_buffer;
+        let len = editor.buffer.line_len(start.row);
+        if start.col >= len {
+            return Ok(());
+        }
+        let end = CursorPositionInBuffer {
+            row: start.row,
+            col: len.min(start.col + self.count.max(1)),
+        };
+        let old_text = editor.buffer.slice(start, end);
+        let mut new_text = String::new();
+        for c in old_text.chars() {
+            if c.is_lowercase() {
+                new_text.extend(c.to_uppercase());
+            } else {
+                new_text.extend(c.to_lowercase());
+            }
+        }
+
+        editor.is_dirty = true;
+        self.editor_cursor_data = Some(editor.snapshot_cursor_data());
+        editor.buffer.delete(start, end)?;
+        editor.buffer.insert(start.row, start.col, &new_text)?;
+        let new_len = editor.buffer.line_len(start.row);
+        editor.cursor_position_in_buffer = start.advanced_by(&new_text);
+        editor.cursor_position_in_buffer.col =
+            editor.cursor_position_in_buffer.col.min(new_len - 1);
+        editor.fit_cursor_in_window();
+        self.text = Some((old_text, new_text));
+        Ok(())
+    }
+
+    fn undo(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        if let (Some((old_text, new_text)), Some(cursor_data)) =
+            (&self.text, self.editor_cursor_data)
+        {
+            let start = cursor_data.cursor_position_in_buffer;
+            editor.buffer.delete(start, start.advanced_by(new_text))?;
+            editor.buffer.insert(start.row, start.col, old_text)?;
+            editor.restore_cursor_data(cursor_data);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
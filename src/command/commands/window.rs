@@ -0,0 +1,87 @@
+use std::any::Any;
+use std::path::Path;
+
+use crossterm::event::KeyCode;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+use crate::window::Direction;
+
+// `:sp[lit] [file]` and `:vs[plit] [file]`
+pub struct SplitCommand {
+    pub vertical: bool,
+    pub file_name: Option<String>,
+}
+
+impl Command for SplitCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.split_window(self.vertical)?;
+        match &self.file_name {
+            Some(file_name) => editor.edit_file(Path::new(file_name)),
+            None => Ok(()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:clo[se]`
+pub struct CloseCommand;
+
+impl Command for CloseCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.close_window()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `:on[ly]`
+pub struct OnlyCommand;
+
+impl Command for OnlyCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        editor.close_other_windows();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// `Ctrl-W` followed by `key`. A count repeats the command, so `3 Ctrl-W +`
+// makes the window three lines taller.
+pub struct WindowCommand {
+    pub key_code: KeyCode,
+}
+
+impl Command for WindowCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        match self.key_code {
+            KeyCode::Char('h') | KeyCode::Left => editor.move_to_window(Direction::Left),
+            KeyCode::Char('j') | KeyCode::Down => editor.move_to_window(Direction::Down),
+            KeyCode::Char('k') | KeyCode::Up => editor.move_to_window(Direction::Up),
+            KeyCode::Char('l') | KeyCode::Right => editor.move_to_window(Direction::Right),
+            KeyCode::Char('w') => editor.move_to_next_window(),
+            KeyCode::Char('+') => editor.resize_window(1),
+            KeyCode::Char('-') => editor.resize_window(-1),
+            KeyCode::Char('=') => editor.equalize_windows(),
+            KeyCode::Char('s') | KeyCode::Char('S') => editor.split_window(false)?,
+            KeyCode::Char('v') => editor.split_window(true)?,
+            KeyCode::Char('c') => editor.close_window()?,
+            KeyCode::Char('o') => editor.close_other_windows(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,24 @@
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::command::base::Command;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+
+pub struct WriteCommand {
+    pub force: bool,
+    pub file_name: Option<String>,
+}
+
+impl Command for WriteCommand {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        match &self.file_name {
+            Some(file_name) => editor.save_file_as(&PathBuf::from(file_name), self.force),
+            None => editor.save_file(self.force),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
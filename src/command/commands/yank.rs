@@ -0,0 +1,54 @@
+use std::any::Any;
+
+use crate::command::base::{Command, JumpCommandData};
+use crate::command::region::get_region;
+use crate::editor::Editor;
+use crate::generic_error::GenericResult;
+use crate::register::{check_writable, Register};
+
+// `y{motion}`, `yy` and `Y`: copies text into a register.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Yank {
+    pub jump_command_data_opt: Option<JumpCommandData>,
+    // The register named with `"x`.
+    pub register: Option<char>,
+}
+
+impl Command for Yank {
+    fn execute(&mut self, editor: &mut Editor) -> GenericResult<()> {
+        check_writable(self.register)?;
+        let Some(jump_command_data) = self.jump_command_data_opt.clone() else {
+            return Ok(());
+        };
+        let cursor_data = editor.snapshot_cursor_data();
+        let region = match get_region(editor, jump_command_data) {
+            Ok(region) => region,
+            Err(e) => {
+                editor.restore_cursor_data(cursor_data);
+                return Err(e);
+            }
+        };
+        let start = region.start.cursor_position_in_buffer;
+        let end = region.end.cursor_position_in_buffer;
+        let text = editor.buffer.slice(start, end);
+        editor
+            .registers
+            .yank(self.register, Register::new(&text, region.linewise))?;
+
+        // The cursor stays unless the text starts before it, as with `yb`
+        // and `yk`, where it goes to the start of the text.
+        let first = if start <= end { region.start } else { region.end };
+        let cursor = cursor_data.cursor_position_in_buffer;
+        let moved = if region.linewise {
+            first.cursor_position_in_buffer.row < cursor.row
+        } else {
+            first.cursor_position_in_buffer < cursor
+        };
+        editor.restore_cursor_data(if moved { first } else { cursor_data });
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
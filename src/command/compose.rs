@@ -3,20 +3,21 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use log::info;
 
 use crate::command::base::{CommandData, JumpCommandData};
-use crate::command::key_codes::{
-    is_ctrl_command, is_editing_command_with_range, is_editing_command_without_range,
-    is_jump_command,
-};
+use crate::command::factory::default_keymaps;
+use crate::command::keymap::{parse_keys, Binding, Keymaps, Lookup};
 
-// list of command patterns
+// Commands are typed as
 //
-// - immediate movement commands (l, h, j, k, etc.)
-// - immediate editing commands (i, x, d, s, etc.)
-// - movement commands with repeat specifications (3l, 4h, 5j, 6k, etc.)
-// - Edit command with repeat specification (4x, 3i[str], etc.)
-// - Edit commands with ranges (d3w, c4e, 4dl, etc.)
+//   ["x] [count] command
+//   ["x] [count] operator [count] motion
+//   ["x] [count] operator operator
+//
+// where the register `"x`, the counts and the keys of the command are looked
+// up in the keymaps: `3j`, `x`, `ZZ`, `z.`, `"a2d3w`, `dd`, etc. The counts
+// before and after an operator multiply, so `2d3w` is `d6w`. Some keys are
+// typed as others, like `Y` as `yy`.
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct KeyData {
     pub key_code: KeyCode,
     pub modifiers: KeyModifiers,
@@ -32,325 +33,205 @@ impl From<KeyEvent> for KeyData {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum InputState {
-    Start,
-
-    AccumulateDigits(String),                          // Entering numbers
-    CommandComposing(KeyData),                         // 'd', 'c' etc.
-    CommandAndDigits(KeyData, String),                 // 'd3', 'c4' etc.
-    DigitsAndCommand(usize, KeyData),                  // '3d', '4c' etc.
-    DigitsAndCommandAndDigits(usize, KeyData, String), // '3d4', '4c3' etc.
+    // More keys are needed, as after `2`, `d` or `"a`.
+    Pending,
+    // The keys are a command, and also the start of a longer one: they mean
+    // the command when the next key does not go on with the longer one, or
+    // when no key comes for a while.
+    Ambiguous(CommandData),
 
     CommandCompleted(CommandData),
     CommandInvalid(String),
 }
 
 // Take vi command input, interpret it, and generate commands
-pub fn compose(key_events: &Vec<KeyEvent>) -> InputState {
-    info!("compose: {:?}", key_events);
+pub fn compose(key_events: &[KeyEvent]) -> InputState {
+    compose_with(default_keymaps(), key_events)
+}
 
-    let mut input_state = InputState::Start;
+/// Composes the keys typed so far into a command of `keymaps`.
+pub fn compose_with(keymaps: &Keymaps, key_events: &[KeyEvent]) -> InputState {
+    info!("compose: {:?}", key_events);
 
-    for event in key_events {
-        match event {
-            KeyEvent {
-                code: KeyCode::Esc,
+    let keys: Vec<KeyData> = key_events
+        .iter()
+        .map(|event| KeyData::from(*event).normalized())
+        .collect();
+    // Esc cancels whatever is being typed.
+    if keys.iter().any(is_escape) {
+        info!("Esc");
+        return InputState::CommandCompleted(CommandData {
+            count: 1,
+            keys: vec![KeyData {
+                key_code: KeyCode::Esc,
                 modifiers: KeyModifiers::NONE,
-                ..
-            }
-            | KeyEvent {
-                code: KeyCode::Char('['),
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } => {
-                info!("Esc");
-                return InputState::CommandCompleted(CommandData {
-                    count: 1,
-                    key_code: KeyCode::Esc,
-                    modifiers: KeyModifiers::NONE,
-                    range: None,
-                });
-            }
-            KeyEvent {
-                code: KeyCode::Char(c),
+            }],
+            range: None,
+            register: None,
+        });
+    }
+
+    let mut keys = &keys[..];
+    let mut register = None;
+    if keys.first().map(|key| key.key_code) == Some(KeyCode::Char('"')) {
+        match keys.get(1) {
+            None => return InputState::Pending,
+            Some(KeyData {
+                key_code: KeyCode::Char(c),
                 modifiers: KeyModifiers::NONE,
-                ..
-            } if c.is_digit(10) => {
-                if let InputState::Start = input_state {
-                    // 1st digit
-                    if c == &'0' {
-                        return InputState::CommandCompleted(CommandData {
-                            count: 1,
-                            key_code: KeyCode::Char('0'),
-                            modifiers: KeyModifiers::NONE,
-                            range: None,
-                        });
-                    } else {
-                        input_state = InputState::AccumulateDigits(c.to_string());
-                    }
-                } else if let InputState::AccumulateDigits(digits) = input_state {
-                    // 2nd or later digit
-                    input_state = InputState::AccumulateDigits(format!("{}{}", digits, c));
-                } else if let InputState::CommandComposing(command) = input_state {
-                    // 1st digit after command
-                    input_state = InputState::CommandAndDigits(command, c.to_string());
-                } else if let InputState::CommandAndDigits(command, digits) = input_state {
-                    // 2nd or later digit after command
-                    input_state = InputState::CommandAndDigits(command, format!("{}{}", digits, c));
-                } else if let InputState::DigitsAndCommand(count, command) = input_state {
-                    // When a number follows a cursor movement command that specifies the number of times
-                    input_state =
-                        InputState::DigitsAndCommandAndDigits(count, command, c.to_string());
-                } else if let InputState::DigitsAndCommandAndDigits(count, command, digits) =
-                    input_state
-                {
-                    // When a number follows a cursor movement command that specifies the number of times
-                    input_state = InputState::DigitsAndCommandAndDigits(
-                        count,
-                        command,
-                        format!("{}{}", digits, c),
-                    );
-                } else {
-                    return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
+            }) if is_register_name(*c) => register = Some(*c),
+            Some(key) => return invalid(key),
+        }
+        keys = &keys[2..];
+    }
+    let (count, keys) = take_count(keys);
+    if keys.is_empty() {
+        return InputState::Pending;
+    }
+
+    let (binding, len) = match keymaps.normal.lookup(keys) {
+        Lookup::NotFound => return invalid(&keys[0]),
+        Lookup::Prefix => return InputState::Pending,
+        Lookup::Found(binding, len) => (binding, len),
+        Lookup::Ambiguous(Binding::Command(_) | Binding::Motion(_)) => {
+            return InputState::Ambiguous(CommandData {
+                count: count.unwrap_or(1),
+                keys: keys.to_vec(),
+                range: None,
+                register,
+            });
+        }
+        // The operator waits for its motion.
+        Lookup::Ambiguous(binding) => (binding, keys.len()),
+    };
+    let (command, rest) = keys.split_at(len);
+    let command_data = |count, keys: &[KeyData], range| CommandData {
+        count,
+        keys: keys.to_vec(),
+        range,
+        register,
+    };
+
+    match binding {
+        Binding::Command(_) | Binding::Motion(_) => match rest.first() {
+            Some(key) => invalid(key),
+            None => InputState::CommandCompleted(command_data(count.unwrap_or(1), command, None)),
+        },
+        Binding::Synonym(other_keys) => match rest.first() {
+            Some(key) => invalid(key),
+            None => {
+                let mut keys = Vec::new();
+                if let Some(register) = register {
+                    keys.push('"'.to_string());
+                    keys.push(register.to_string());
                 }
+                keys.extend(count.map(|count| count.to_string()));
+                keys.push(other_keys.to_string());
+                let key_events: Vec<KeyEvent> = parse_keys(&keys.concat())
+                    .iter()
+                    .map(|key| KeyEvent::new(key.key_code, key.modifiers))
+                    .collect();
+                compose_with(keymaps, &key_events)
             }
-            KeyEvent {
-                code, modifiers, ..
-            } if (*modifiers == KeyModifiers::NONE || *modifiers == KeyModifiers::SHIFT)
-                && is_jump_command(code) =>
-            {
-                if let InputState::Start = input_state {
-                    return InputState::CommandCompleted(CommandData {
-                        count: 1,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                        range: None,
-                    });
-                } else if let InputState::AccumulateDigits(digits) = input_state {
-                    let count = digits.parse().unwrap();
-                    return InputState::CommandCompleted(CommandData {
-                        count,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                        range: None,
-                    });
-                } else if let InputState::CommandComposing(composing) = input_state {
-                    let range = Some(JumpCommandData {
-                        count: 1,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                    return InputState::CommandCompleted(CommandData {
-                        count: 1,
-                        key_code: composing.key_code,
-                        modifiers: composing.modifiers,
-                        range,
-                    });
-                } else if let InputState::CommandAndDigits(composing, digits) = input_state {
-                    let range = Some(JumpCommandData {
-                        count: digits.parse().unwrap(),
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                    return InputState::CommandCompleted(CommandData {
-                        count: 1,
-                        key_code: composing.key_code,
-                        modifiers: composing.modifiers,
-                        range,
-                    });
-                } else if let InputState::DigitsAndCommand(count, composing) = input_state {
-                    let range = Some(JumpCommandData {
-                        count: 1,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                    return InputState::CommandCompleted(CommandData {
-                        count,
-                        key_code: composing.key_code,
-                        modifiers: composing.modifiers,
-                        range,
-                    });
-                } else if let InputState::DigitsAndCommandAndDigits(count, composing, digits) =
-                    input_state
-                {
-                    let range = Some(JumpCommandData {
-                        count: digits.parse().unwrap(),
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                    return InputState::CommandCompleted(CommandData {
-                        count,
-                        key_code: composing.key_code,
-                        modifiers: composing.modifiers,
-                        range,
-                    });
-                } else {
-                    return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
-                }
+        },
+        Binding::Argument(_) => match rest {
+            [] => InputState::Pending,
+            // the argument is the last key of the command
+            [_] => InputState::CommandCompleted(command_data(count.unwrap_or(1), keys, None)),
+            [_, key, ..] => invalid(key),
+        },
+        Binding::Operator(_) => {
+            let (motion_count, motion) = take_count(rest);
+            if motion.is_empty() {
+                return InputState::Pending;
             }
-            KeyEvent {
-                code, modifiers, ..
-            } if (*modifiers == KeyModifiers::NONE || *modifiers == KeyModifiers::SHIFT)
-                && is_editing_command_without_range(code) =>
-            {
-                if let InputState::Start = input_state {
-                    return InputState::CommandCompleted(CommandData {
-                        count: 1,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                        range: None,
-                    });
-                } else if let InputState::AccumulateDigits(digits) = input_state {
-                    let count = digits.parse().unwrap();
-                    return InputState::CommandCompleted(CommandData {
-                        count,
-                        key_code: *code,
-                        modifiers: *modifiers,
-                        range: None,
-                    });
-                } else {
-                    return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
-                }
+            let range = |keys: &[KeyData]| {
+                Some(JumpCommandData {
+                    count: count.unwrap_or(1).saturating_mul(motion_count.unwrap_or(1)),
+                    keys: keys.to_vec(),
+                })
+            };
+            // dd, cc, yy, etc.
+            if motion[0] == command[command.len() - 1] {
+                return match motion {
+                    [_] => InputState::CommandCompleted(command_data(1, command, range(motion))),
+                    _ => invalid(&motion[1]),
+                };
             }
-            KeyEvent {
-                code, modifiers, ..
-            } if (*modifiers == KeyModifiers::NONE || *modifiers == KeyModifiers::SHIFT)
-                && is_editing_command_with_range(code) =>
-            {
-                if let InputState::Start = input_state {
-                    input_state = InputState::CommandComposing(KeyData {
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                } else if let InputState::AccumulateDigits(digits) = input_state {
-                    let count = digits.parse().unwrap();
-                    input_state = InputState::DigitsAndCommand(count, KeyData {
-                        key_code: *code,
-                        modifiers: *modifiers,
-                    });
-                } else if let InputState::CommandComposing(composing) = input_state {
-                    if composing.key_code == *code {
-                        // dd, cc, yy, etc.
-                        let range = Some(JumpCommandData {
-                            count: 1,
-                            key_code: *code,
-                            modifiers: *modifiers,
-                        });
-                        return InputState::CommandCompleted(CommandData {
-                            count: 1,
-                            key_code: composing.key_code,
-                            modifiers: composing.modifiers,
-                            range,
-                        });
-                    } else {
-                        return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
-                    }
-                } else if let InputState::CommandAndDigits(command, digits) = input_state {
-                    return InputState::CommandInvalid(format!(
-                        "Invalid command: {:?}{:?}{:?}",
-                        command, digits, event
-                    ));
-                } else if let InputState::DigitsAndCommand(count, composing) = input_state {
-                    if composing.key_code == *code {
-                        // 3dd, 4cc, 5yy, etc.
-                        let range = Some(JumpCommandData {
-                            count: count,
-                            key_code: *code,
-                            modifiers: *modifiers,
-                        });
-                        return InputState::CommandCompleted(CommandData {
-                            count: 1,
-                            key_code: composing.key_code,
-                            modifiers: composing.modifiers,
-                            range,
-                        });
-                    } else {
-                        return InputState::CommandInvalid(format!(
-                            "Invalid command: {:?}{:?}{:?}",
-                            composing.key_code, count, event
-                        ));
-                    }
-                } else if let InputState::DigitsAndCommandAndDigits(count, command, digits) =
-                    input_state
-                {
-                    return InputState::CommandInvalid(format!(
-                        "Invalid command: {:?}{:?}{:?}{:?}",
-                        command, count, digits, event
-                    ));
-                } else {
-                    return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
+            match keymaps.operator_pending.lookup(motion) {
+                Lookup::Found(_, len) if len == motion.len() => {
+                    InputState::CommandCompleted(command_data(1, command, range(motion)))
                 }
-            }
-            KeyEvent {
-                code,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } if is_ctrl_command(code) => {
-                if let InputState::Start = input_state {
-                    return InputState::CommandCompleted(CommandData {
-                        count: 1,
-                        key_code: *code,
-                        modifiers: KeyModifiers::CONTROL,
-                        range: None,
-                    });
-                } else if let InputState::AccumulateDigits(digits) = input_state {
-                    let count = digits.parse().unwrap();
-                    return InputState::CommandCompleted(CommandData {
-                        count,
-                        key_code: *code,
-                        modifiers: KeyModifiers::CONTROL,
-                        range: None,
-                    });
-                } else {
-                    return InputState::CommandInvalid(format!("Invalid command: {:?}", event));
+                Lookup::Found(_, len) => invalid(&motion[len]),
+                Lookup::Prefix => InputState::Pending,
+                Lookup::Ambiguous(_) => {
+                    InputState::Ambiguous(command_data(1, command, range(motion)))
                 }
+                Lookup::NotFound => invalid(&motion[0]),
             }
-            KeyEvent {
-                code: KeyCode::Enter,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => {
-                info!("Enter");
-                return InputState::CommandCompleted(CommandData {
-                    count: 1,
-                    key_code: KeyCode::Enter,
-                    modifiers: KeyModifiers::NONE,
-                    range: None,
-                });
-            }
-            _ => {
-                info!("Other key: {:?}", event);
-                ()
+        }
+    }
+}
+
+fn is_escape(key: &KeyData) -> bool {
+    matches!(
+        (key.key_code, key.modifiers),
+        (KeyCode::Esc, KeyModifiers::NONE) | (KeyCode::Char('['), KeyModifiers::CONTROL)
+    )
+}
+
+// The registers of vi and vim that `"x` can name.
+fn is_register_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "\"-_.:/+*".contains(c)
+}
+
+// Takes the count at the start of `keys`. A `0` there is not a count but the
+// motion to the start of the line.
+fn take_count(keys: &[KeyData]) -> (Option<usize>, &[KeyData]) {
+    let mut count: Option<usize> = None;
+    for (i, key) in keys.iter().enumerate() {
+        match (key.key_code, key.modifiers) {
+            (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE) if count.is_some() || c != '0' => {
+                let digit = c.to_digit(10).unwrap() as usize;
+                count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
             }
+            _ => return (count, &keys[i..]),
         }
     }
+    (count, &[])
+}
 
-    input_state
+fn invalid(key: &KeyData) -> InputState {
+    InputState::CommandInvalid(format!("Invalid command: {:?}", key))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::command::keymap::parse_keys;
+
     #[test]
     fn test_escape() {
         use super::compose;
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Esc,
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 1,
-                key_code: KeyCode::Esc,
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("<Esc>"),
                 range: None,
+                register: None,
             })
         );
     }
@@ -361,22 +242,23 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('g'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('g'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 1,
-                key_code: KeyCode::Char('g'),
-                modifiers: KeyModifiers::CONTROL,
+                keys: parse_keys("<C-G>"),
                 range: None,
+                register: None,
             })
         );
     }
@@ -387,22 +269,23 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('j'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 1,
-                key_code: KeyCode::Char('j'),
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("j"),
                 range: None,
+                register: None,
             })
         );
     }
@@ -413,28 +296,29 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('4'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('j'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('4'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 4,
-                key_code: KeyCode::Char('j'),
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("j"),
                 range: None,
+                register: None,
             })
         );
     }
@@ -445,32 +329,32 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('d'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('d'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 1,
-                key_code: KeyCode::Char('d'),
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("d"),
                 range: Some(super::JumpCommandData {
                     count: 1,
-                    key_code: KeyCode::Char('d'),
-                    modifiers: KeyModifiers::NONE,
+                    keys: parse_keys("d"),
                 }),
+                register: None,
             })
         );
     }
@@ -481,38 +365,38 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('d'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('2'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('j'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('2'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
             InputState::CommandCompleted(super::CommandData {
                 count: 1,
-                key_code: KeyCode::Char('d'),
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("d"),
                 range: Some(super::JumpCommandData {
                     count: 2,
-                    key_code: KeyCode::Char('j'),
-                    modifiers: KeyModifiers::NONE,
+                    keys: parse_keys("j"),
                 }),
+                register: None,
             })
         );
     }
@@ -523,45 +407,194 @@ mod tests {
         use super::InputState;
         use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-        let mut key_events: Vec<KeyEvent> = Vec::new();
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('3'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('d'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('4'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
-        key_events.push(KeyEvent {
-            code: KeyCode::Char('j'),
-            modifiers: KeyModifiers::NONE,
-            kind: crossterm::event::KeyEventKind::Press,
-            state: crossterm::event::KeyEventState::NONE,
-        });
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent {
+                code: KeyCode::Char('3'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('4'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::NONE,
+                kind: crossterm::event::KeyEventKind::Press,
+                state: crossterm::event::KeyEventState::NONE,
+            },
+        ];
 
         let input_state = compose(&key_events);
         assert_eq!(
             input_state,
+            InputState::CommandCompleted(super::CommandData {
+                count: 1,
+                keys: parse_keys("d"),
+                range: Some(super::JumpCommandData {
+                    count: 12,
+                    keys: parse_keys("j"),
+                }),
+                register: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_2_ctrl_w_plus() {
+        use super::compose;
+        use super::InputState;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent::new(KeyCode::Char('2'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+        ];
+        assert_eq!(compose(&key_events), InputState::Pending);
+
+        let mut key_events = key_events;
+        key_events.push(KeyEvent::new(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert_eq!(
+            compose(&key_events),
+            InputState::CommandCompleted(super::CommandData {
+                count: 2,
+                keys: parse_keys("<C-W>+"),
+                range: None,
+                register: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_3_z_l() {
+        use super::compose;
+        use super::InputState;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let key_events: Vec<KeyEvent> = vec![
+            KeyEvent::new(KeyCode::Char('3'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE),
+        ];
+        assert_eq!(
+            compose(&key_events),
             InputState::CommandCompleted(super::CommandData {
                 count: 3,
-                key_code: KeyCode::Char('d'),
-                modifiers: KeyModifiers::NONE,
+                keys: parse_keys("zl"),
+                range: None,
+                register: None,
+            })
+        );
+    }
+
+    fn key_events(keys: &str) -> Vec<crossterm::event::KeyEvent> {
+        crate::command::keymap::parse_keys(keys)
+            .into_iter()
+            .map(|key| crossterm::event::KeyEvent::new(key.key_code, key.modifiers))
+            .collect()
+    }
+
+    #[test]
+    fn test_register_and_counts() {
+        use super::{compose, InputState};
+
+        assert_eq!(compose(&key_events("\"")), InputState::Pending);
+        assert_eq!(compose(&key_events("\"a2")), InputState::Pending);
+        assert_eq!(
+            compose(&key_events("\"a2dd")),
+            InputState::CommandCompleted(super::CommandData {
+                count: 1,
+                keys: parse_keys("d"),
                 range: Some(super::JumpCommandData {
-                    count: 4,
-                    key_code: KeyCode::Char('j'),
-                    modifiers: KeyModifiers::NONE,
+                    count: 2,
+                    keys: parse_keys("d"),
                 }),
+                register: Some('a'),
+            })
+        );
+        assert!(matches!(
+            compose(&key_events("\"<C-A>")),
+            InputState::CommandInvalid(_)
+        ));
+
+        // `0` is a motion unless a count has begun
+        let InputState::CommandCompleted(command_data) = compose(&key_events("d0")) else {
+            panic!("d0 is not complete");
+        };
+        assert_eq!(command_data.range.unwrap().keys, parse_keys("0"));
+        let InputState::CommandCompleted(command_data) = compose(&key_events("10j")) else {
+            panic!("10j is not complete");
+        };
+        assert_eq!(command_data.count, 10);
+    }
+
+    #[test]
+    fn test_multi_key_commands() {
+        use super::{compose, InputState};
+
+        assert_eq!(compose(&key_events("Z")), InputState::Pending);
+        let InputState::CommandCompleted(command_data) = compose(&key_events("ZZ")) else {
+            panic!("ZZ is not complete");
+        };
+        assert_eq!(command_data.keys, parse_keys("ZZ"));
+        assert_eq!(command_data.range, None);
+        assert!(matches!(compose(&key_events("Zx")), InputState::CommandInvalid(_)));
+        assert!(matches!(compose(&key_events("dx")), InputState::CommandInvalid(_)));
+
+        // a motion of two keys after an operator
+        assert_eq!(compose(&key_events("dg")), InputState::Pending);
+        let InputState::CommandCompleted(command_data) = compose(&key_events("d3gg")) else {
+            panic!("d3gg is not complete");
+        };
+        assert_eq!(command_data.keys, parse_keys("d"));
+        assert_eq!(
+            command_data.range,
+            Some(super::JumpCommandData {
+                count: 3,
+                keys: parse_keys("gg"),
             })
         );
+        assert!(matches!(compose(&key_events("<C-Q>")), InputState::CommandInvalid(_)));
+    }
+
+    #[test]
+    fn test_synonyms() {
+        use super::{compose, InputState};
+
+        // `"a3Y` is `"a3yy`
+        assert_eq!(compose(&key_events("\"a3Y")), compose(&key_events("\"a3yy")));
+        assert!(matches!(compose(&key_events("Y")), InputState::CommandCompleted(_)));
+        assert!(matches!(compose(&key_events("Yx")), InputState::CommandInvalid(_)));
+    }
+
+    #[test]
+    fn test_ambiguous_keys() {
+        use super::{compose_with, InputState};
+        use crate::command::base::{Command, CommandData};
+        use crate::command::commands::no_op_command::NoOpCommand;
+        use crate::command::keymap::Keymaps;
+
+        fn no_op(_: &CommandData) -> Box<dyn Command> {
+            Box::new(NoOpCommand {})
+        }
+        let mut keymaps = Keymaps::default();
+        keymaps.add_motion("g", no_op);
+        keymaps.add_motion("gg", no_op);
+        keymaps.add_operator("d", no_op);
+
+        assert!(matches!(compose_with(&keymaps, &key_events("2g")), InputState::Ambiguous(_)));
+        assert!(matches!(compose_with(&keymaps, &key_events("gg")), InputState::CommandCompleted(_)));
+        assert!(matches!(compose_with(&keymaps, &key_events("gx")), InputState::CommandInvalid(_)));
+        assert!(matches!(compose_with(&keymaps, &key_events("dg")), InputState::Ambiguous(_)));
+        assert!(matches!(compose_with(&keymaps, &key_events("dgg")), InputState::CommandCompleted(_)));
     }
 }
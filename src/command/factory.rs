@@ -1,128 +1,190 @@
-use crate::command::base::{Command, CommandData, JumpCommandData};
+use std::sync::OnceLock;
+
+use crate::command::base::{Command, CommandData};
 use crate::command::commands::exit::ExitCommand;
 use crate::command::commands::move_cursor::*;
 use crate::command::commands::no_op_command::NoOpCommand;
+use crate::command::keymap::Keymaps;
 use crossterm::event::KeyCode;
 
 use super::commands::append::Append;
+use super::commands::buffers::AlternateBufferCommand;
+use super::commands::change::Change;
 use super::commands::delete::{Delete, DeleteChar};
 use super::commands::insert::Insert;
-use super::commands::misc::DisplayFile;
+use super::commands::join::JoinLines;
+use super::commands::macros::{ExecuteMacro, RecordMacro};
+use super::commands::misc::{DisplayFile, Redraw};
+use super::commands::put::Put;
+use super::commands::repeat::Repeat;
+use super::commands::replace::ReplaceChar;
+use super::commands::scroll::ScrollCommand;
+use super::commands::switch_case::SwitchCase;
 use super::commands::undo::Undo;
+use super::commands::window::WindowCommand;
+use super::commands::yank::Yank;
 
-pub fn command_factory(command_data: &CommandData) -> Box<dyn Command> {
-    match command_data {
-        CommandData {
-            key_code: KeyCode::Char('j'),
-            ..
+/// Returns the keys of the commands of normal mode and what they make.
+/// Adding a command is adding it here.
+pub fn default_keymaps() -> &'static Keymaps {
+    static KEYMAPS: OnceLock<Keymaps> = OnceLock::new();
+    KEYMAPS.get_or_init(|| {
+        let mut keymaps = Keymaps::default();
+
+        // motions
+        for keys in ["j", "<Down>"] {
+            keymaps.add_motion(keys, |_| Box::new(NextLine {}));
         }
-        | CommandData {
-            key_code: KeyCode::Down,
-            ..
-        } => Box::new(NextLine {}),
-        CommandData {
-            key_code: KeyCode::Char('k'),
-            ..
+        for keys in ["k", "<Up>"] {
+            keymaps.add_motion(keys, |_| Box::new(PreviousLine {}));
         }
-        | CommandData {
-            key_code: KeyCode::Up,
-            ..
-        } => Box::new(PreviousLine {}),
-        CommandData {
-            key_code: KeyCode::Char('l'),
-            ..
+        for keys in ["l", "<Right>"] {
+            keymaps.add_motion(keys, |_| Box::new(ForwardChar {}));
         }
-        | CommandData {
-            key_code: KeyCode::Right,
-            ..
-        } => Box::new(ForwardChar {}),
-        CommandData {
-            key_code: KeyCode::Char('h'),
-            ..
+        for keys in ["h", "<Left>"] {
+            keymaps.add_motion(keys, |_| Box::new(BackwardChar {}));
         }
-        | CommandData {
-            key_code: KeyCode::Left,
-            ..
-        } => Box::new(BackwardChar {}),
-        CommandData {
-            key_code: KeyCode::Char('0'),
-            ..
-        } => Box::new(MoveBeginningOfLine {}),
-        CommandData {
-            key_code: KeyCode::Char('$'),
-            ..
-        } => Box::new(MoveEndOfLine {}),
+        keymaps.add_motion("0", |_| Box::new(MoveBeginningOfLine {}));
+        keymaps.add_motion("$", |_| Box::new(MoveEndOfLine {}));
+        keymaps.add_motion("w", |_| Box::new(ForwardWord {}));
+        keymaps.add_motion("b", |_| Box::new(BackwardWord {}));
+        keymaps.add_motion("e", |_| Box::new(ForwardWordEnd {}));
+        keymaps.add_motion("gg", |command_data| {
+            Box::new(MoveToLine {
+                count: command_data.count,
+            })
+        });
 
-        // jump commands
-        CommandData {
-            key_code: KeyCode::Char('w'),
-            ..
-        } => Box::new(ForwardWord {}),
-        CommandData {
-            key_code: KeyCode::Char('b'),
-            ..
-        } => Box::new(BackwardWord {}),
+        // insert and append
+        keymaps.add_command("i", |_| Box::new(Insert::default()));
+        keymaps.add_command("a", |_| Box::new(Append::default()));
 
-        // insert commands
-        CommandData {
-            key_code: KeyCode::Char('i'),
-            ..
-        } => Box::new(Insert::default()),
+        // delete
+        keymaps.add_command("x", |command_data| {
+            Box::new(DeleteChar {
+                count: command_data.count,
+                register: command_data.register,
+                ..Default::default()
+            })
+        });
+        keymaps.add_operator("d", |command_data| {
+            Box::new(Delete {
+                jump_command_data_opt: command_data.range.clone(),
+                register: command_data.register,
+                ..Default::default()
+            })
+        });
 
-        // append commands
-        CommandData {
-            key_code: KeyCode::Char('a'),
-            ..
-        } => Box::new(Append::default()),
+        // change
+        keymaps.add_operator("c", |command_data| {
+            Box::new(Change {
+                jump_command_data_opt: command_data.range.clone(),
+                register: command_data.register,
+                ..Default::default()
+            })
+        });
+        keymaps.add_synonym("C", "c$");
+        keymaps.add_synonym("s", "cl");
+        keymaps.add_synonym("S", "cc");
 
-        // delete commands
-        CommandData {
-            key_code: KeyCode::Char('x'),
-            ..
-        } => Box::new(DeleteChar::default()),
+        // yank and put
+        keymaps.add_operator("y", |command_data| {
+            Box::new(Yank {
+                jump_command_data_opt: command_data.range.clone(),
+                register: command_data.register,
+            })
+        });
+        keymaps.add_synonym("Y", "yy");
+        keymaps.add_command("p", |command_data| {
+            Box::new(Put {
+                count: command_data.count,
+                register: command_data.register,
+                ..Default::default()
+            })
+        });
+        keymaps.add_command("P", |command_data| {
+            Box::new(Put {
+                before: true,
+                count: command_data.count,
+                register: command_data.register,
+                ..Default::default()
+            })
+        });
 
-        CommandData {
-            key_code: KeyCode::Char('d'),
-            range,
-            ..
-        } => Box::new(Delete {
-            jump_command_data_opt: range.clone(),
-            ..Default::default()
-        }),
+        // replace, join and switch case
+        keymaps.add_argument("r", |command_data| {
+            Box::new(ReplaceChar {
+                key_code: Some(argument(command_data)),
+                count: command_data.count,
+                ..Default::default()
+            })
+        });
+        keymaps.add_command("J", |command_data| {
+            Box::new(JoinLines {
+                count: command_data.count,
+                ..Default::default()
+            })
+        });
+        keymaps.add_command("~", |command_data| {
+            Box::new(SwitchCase {
+                count: command_data.count,
+                ..Default::default()
+            })
+        });
 
-        // undo command
-        CommandData {
-            key_code: KeyCode::Char('u'),
-            ..
-        } => Box::new(Undo {}),
+        keymaps.add_command("u", |_| Box::new(Undo {}));
+        keymaps.add_command(".", |command_data| {
+            Box::new(Repeat {
+                count: command_data.count,
+            })
+        });
+        keymaps.add_command("<C-G>", |_| Box::new(DisplayFile {}));
+        keymaps.add_command("<C-L>", |_| Box::new(Redraw {}));
+        // Ctrl-^, reported as Ctrl-6 by most terminals
+        for keys in ["<C-^>", "<C-6>"] {
+            keymaps.add_command(keys, |_| Box::new(AlternateBufferCommand {}));
+        }
+        keymaps.add_command("ZZ", |_| Box::new(ExitCommand {}));
 
-        // Control + g
-        CommandData {
-            key_code: KeyCode::Char('g'),
-            modifiers,
-            ..
-        } if *modifiers == crossterm::event::KeyModifiers::CONTROL => Box::new(DisplayFile {}),
+        // Ctrl-W followed by a window command, z by a scroll command
+        keymaps.add_argument("<C-W>", |command_data| {
+            Box::new(WindowCommand {
+                key_code: argument(command_data),
+            })
+        });
+        keymaps.add_argument("z", |command_data| {
+            Box::new(ScrollCommand {
+                key_code: argument(command_data),
+            })
+        });
 
-        // ZZ
-        CommandData {
-            key_code: KeyCode::Char('Z'),
-            ..
-        } => {
-            if let Some(JumpCommandData {
-                count,
-                key_code: command,
-                ..
-            }) = command_data.range
-            {
-                if count == 1 && command == KeyCode::Char('Z') {
-                    Box::new(ExitCommand {})
-                } else {
-                    Box::new(NoOpCommand {})
-                }
-            } else {
-                Box::new(NoOpCommand {})
-            }
-        }
-        _ => Box::new(NoOpCommand {}),
-    }
+        // q and @ followed by a register
+        keymaps.add_argument("q", |command_data| {
+            Box::new(RecordMacro {
+                key_code: argument(command_data),
+            })
+        });
+        keymaps.add_argument("@", |command_data| {
+            Box::new(ExecuteMacro {
+                key_code: argument(command_data),
+                count: command_data.count,
+            })
+        });
+
+        keymaps
+    })
+}
+
+// The key after a command that takes one, like the `j` of `Ctrl-W j`.
+fn argument(command_data: &CommandData) -> KeyCode {
+    command_data
+        .keys
+        .last()
+        .map_or(KeyCode::Null, |key| key.key_code)
+}
+
+pub fn command_factory(command_data: &CommandData) -> Box<dyn Command> {
+    default_keymaps()
+        .command(command_data)
+        .unwrap_or_else(|| Box::new(NoOpCommand {}))
 }
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::command::base::{Command, CommandData};
+use crate::command::compose::KeyData;
+
+// Makes the command for keys that have been composed.
+pub type CommandFactory = fn(&CommandData) -> Box<dyn Command>;
+
+// What a sequence of keys is bound to.
+#[derive(Clone, Copy)]
+pub enum Binding {
+    // A command complete in its keys, like `x` or `ZZ`.
+    Command(CommandFactory),
+    // A command that moves the cursor, like `j`. It also gives the range of
+    // an operator.
+    Motion(CommandFactory),
+    // A command that waits for a motion, like `d`. Typed twice, as in `dd`, it
+    // acts on whole lines.
+    Operator(CommandFactory),
+    // A command completed by the next key, whatever it is, like `z.` and
+    // `Ctrl-W j`.
+    Argument(CommandFactory),
+    // Keys that are typed as others, like `Y` as `yy`. Their count and
+    // register go to the others.
+    Synonym(&'static str),
+}
+
+impl Binding {
+    pub fn factory(&self) -> Option<CommandFactory> {
+        match self {
+            Binding::Command(factory)
+            | Binding::Motion(factory)
+            | Binding::Operator(factory)
+            | Binding::Argument(factory) => Some(*factory),
+            Binding::Synonym(_) => None,
+        }
+    }
+}
+
+// Where some keys lead in a keymap.
+#[derive(Clone, Copy)]
+pub enum Lookup<B = Binding> {
+    // No binding starts with the keys.
+    NotFound,
+    // The keys start a longer binding.
+    Prefix,
+    // The first `len` keys are bound; the keys after them start nothing.
+    Found(B, usize),
+    // The keys are bound and also start a longer binding, so what they mean
+    // depends on the next key, or on no key coming for a while.
+    Ambiguous(B),
+}
+
+struct KeymapNode<B> {
+    binding: Option<B>,
+    children: HashMap<KeyData, KeymapNode<B>>,
+}
+
+impl<B> Default for KeymapNode<B> {
+    fn default() -> Self {
+        KeymapNode {
+            binding: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+impl<B> KeymapNode<B> {
+    // Unbinds `keys` below this node, dropping the nodes left empty, and
+    // returns what they were bound to.
+    fn remove(&mut self, keys: &[KeyData]) -> Option<B> {
+        let Some((first, rest)) = keys.split_first() else {
+            return self.binding.take();
+        };
+        let child = self.children.get_mut(first)?;
+        let binding = child.remove(rest);
+        if child.binding.is_none() && child.children.is_empty() {
+            self.children.remove(first);
+        }
+        binding
+    }
+
+    fn collect<'a>(&'a self, bindings: &mut Vec<&'a B>) {
+        bindings.extend(&self.binding);
+        for child in self.children.values() {
+            child.collect(bindings);
+        }
+    }
+}
+
+// A tree of key sequences: each node is a key, and the nodes of complete
+// sequences hold what they are bound to, the commands of a mode or the user's
+// mappings.
+pub struct Keymap<B = Binding> {
+    root: KeymapNode<B>,
+}
+
+impl<B> Default for Keymap<B> {
+    fn default() -> Self {
+        Keymap {
+            root: KeymapNode::default(),
+        }
+    }
+}
+
+impl<B: Clone> Keymap<B> {
+    /// Binds `keys`, in the notation of `parse_keys`, replacing what they were
+    /// bound to.
+    pub fn add(&mut self, keys: &str, binding: B) {
+        self.add_keys(&parse_keys(keys), binding);
+    }
+
+    pub fn add_keys(&mut self, keys: &[KeyData], binding: B) {
+        let mut node = &mut self.root;
+        for key in keys {
+            node = node.children.entry(key.normalized()).or_default();
+        }
+        node.binding = Some(binding);
+    }
+
+    /// Unbinds `keys` and returns what they were bound to.
+    pub fn remove(&mut self, keys: &[KeyData]) -> Option<B> {
+        let keys: Vec<KeyData> = keys.iter().map(|key| key.normalized()).collect();
+        self.root.remove(&keys)
+    }
+
+    /// Returns everything bound, in no order.
+    pub fn bindings(&self) -> Vec<&B> {
+        let mut bindings = Vec::new();
+        self.root.collect(&mut bindings);
+        bindings
+    }
+
+    /// Follows `keys` as far as they go.
+    pub fn lookup(&self, keys: &[KeyData]) -> Lookup<B> {
+        let mut node = &self.root;
+        let mut found = None;
+        for (i, key) in keys.iter().enumerate() {
+            match node.children.get(&key.normalized()) {
+                Some(child) => node = child,
+                None => {
+                    return match found {
+                        Some(binding) => Lookup::Found(binding, i),
+                        None => Lookup::NotFound,
+                    }
+                }
+            }
+            found = node.binding.clone();
+        }
+        match (&node.binding, node.children.is_empty()) {
+            (Some(binding), true) => Lookup::Found(binding.clone(), keys.len()),
+            (Some(binding), false) => Lookup::Ambiguous(binding.clone()),
+            (None, false) => Lookup::Prefix,
+            (None, true) => Lookup::NotFound,
+        }
+    }
+}
+
+// The keymaps of the modes that take commands: normal mode, and the mode after
+// an operator where only motions are looked up.
+#[derive(Default)]
+pub struct Keymaps {
+    pub normal: Keymap,
+    pub operator_pending: Keymap,
+}
+
+impl Keymaps {
+    pub fn add_command(&mut self, keys: &str, factory: CommandFactory) {
+        self.normal.add(keys, Binding::Command(factory));
+    }
+
+    pub fn add_motion(&mut self, keys: &str, factory: CommandFactory) {
+        self.normal.add(keys, Binding::Motion(factory));
+        self.operator_pending.add(keys, Binding::Motion(factory));
+    }
+
+    pub fn add_operator(&mut self, keys: &str, factory: CommandFactory) {
+        self.normal.add(keys, Binding::Operator(factory));
+    }
+
+    pub fn add_argument(&mut self, keys: &str, factory: CommandFactory) {
+        self.normal.add(keys, Binding::Argument(factory));
+    }
+
+    /// Makes `keys` the same as `other_keys`, like `Y` and `yy`.
+    pub fn add_synonym(&mut self, keys: &str, other_keys: &'static str) {
+        self.normal.add(keys, Binding::Synonym(other_keys));
+    }
+
+    /// Returns whether `keys` are an operator, as the range of `dd` is.
+    pub fn is_operator(&self, keys: &[KeyData]) -> bool {
+        match self.normal.lookup(keys) {
+            Lookup::Found(Binding::Operator(_), len) => len == keys.len(),
+            Lookup::Ambiguous(binding) => matches!(binding, Binding::Operator(_)),
+            _ => false,
+        }
+    }
+
+    /// Returns the command for keys composed into `command_data`, which are
+    /// the keys it is bound to, followed by its argument if it takes one.
+    pub fn command(&self, command_data: &CommandData) -> Option<Box<dyn Command>> {
+        let keys = &command_data.keys;
+        let binding = match self.normal.lookup(keys) {
+            Lookup::Found(binding, len) if len == keys.len() => binding,
+            Lookup::Found(binding @ Binding::Argument(_), len) if len + 1 == keys.len() => binding,
+            Lookup::Ambiguous(binding) => binding,
+            _ => return None,
+        };
+        Some(binding.factory()?(command_data))
+    }
+}
+
+impl KeyData {
+    /// The key as keymaps hold it: Shift is in the character itself.
+    pub fn normalized(self) -> KeyData {
+        match self.key_code {
+            KeyCode::Char(_) => KeyData {
+                key_code: self.key_code,
+                modifiers: self.modifiers - KeyModifiers::SHIFT,
+            },
+            _ => self,
+        }
+    }
+}
+
+/// Parses keys in vim's notation: characters stand for themselves, and names
+/// in angle brackets for other keys, like `<Esc>`, `<CR>`, `<Left>` and
+/// `<C-W>`. Anything else in angle brackets is taken as it is.
+pub fn parse_keys(text: &str) -> Vec<KeyData> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                if let Some(key) = parse_key_name(&rest[1..end]) {
+                    keys.push(key);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        keys.push(KeyData {
+            key_code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE,
+        });
+        rest = &rest[c.len_utf8()..];
+    }
+    keys
+}
+
+fn parse_key_name(name: &str) -> Option<KeyData> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(c) = lower.strip_prefix("c-") {
+        let mut chars = c.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(KeyData {
+                key_code: KeyCode::Char(c),
+                modifiers: KeyModifiers::CONTROL,
+            }),
+            _ => None,
+        };
+    }
+    let key_code = match lower.as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "bar" => KeyCode::Char('|'),
+        "bslash" => KeyCode::Char('\\'),
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    };
+    Some(KeyData {
+        key_code,
+        modifiers: KeyModifiers::NONE,
+    })
+}
+
+/// Writes keys in the notation of `parse_keys`, like `:map` lists them.
+pub fn key_names(keys: &[KeyData]) -> String {
+    keys.iter().map(|key| key_name(*key)).collect()
+}
+
+fn key_name(key: KeyData) -> String {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        if let KeyCode::Char(c) = key.key_code {
+            return format!("<C-{}>", c.to_ascii_uppercase());
+        }
+    }
+    let name = match key.key_code {
+        KeyCode::Char('<') => "lt",
+        KeyCode::Char(c) => return c.to_string(),
+        KeyCode::Esc => "Esc",
+        KeyCode::Enter => "CR",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "BS",
+        KeyCode::Delete => "Del",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Insert => "Insert",
+        KeyCode::F(n) => return format!("<F{}>", n),
+        _ => return format!("<{:?}>", key.key_code),
+    };
+    format!("<{}>", name)
+}
+
+/// Writes keys as the text of a register a macro is recorded in, like vim:
+/// control keys are control characters, `<Esc>` being `^[` and `<CR>` `^M`,
+/// and keys that are no character are in the notation of `parse_keys`.
+pub fn keys_to_text(keys: &[KeyData]) -> String {
+    keys.iter()
+        .map(|key| {
+            let c = match key.key_code {
+                KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    ((c.to_ascii_uppercase() as u8) & 0x1f) as char
+                }
+                KeyCode::Char(c) => c,
+                KeyCode::Esc => '\x1b',
+                KeyCode::Enter => '\r',
+                KeyCode::Tab => '\t',
+                KeyCode::Backspace => '\x7f',
+                _ => return key_name(*key),
+            };
+            c.to_string()
+        })
+        .collect()
+}
+
+/// Reads the keys of a macro from the text of a register, the other way
+/// round from `keys_to_text`. A line break is `<CR>`.
+pub fn text_to_keys(text: &str) -> Vec<KeyData> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        // only the names of keys that are no character, so that `<lt>` typed
+        // in insert mode stays as it is
+        let special = rest
+            .find('>')
+            .filter(|_| c == '<')
+            .and_then(|end| Some((parse_key_name(&rest[1..end])?, end)))
+            .filter(|(key, _)| !matches!(key.key_code, KeyCode::Char(_)));
+        if let Some((key, end)) = special {
+            keys.push(key);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let (key_code, modifiers) = match c {
+            '\x1b' => (KeyCode::Esc, KeyModifiers::NONE),
+            '\r' | '\n' => (KeyCode::Enter, KeyModifiers::NONE),
+            '\t' => (KeyCode::Tab, KeyModifiers::NONE),
+            '\x7f' => (KeyCode::Backspace, KeyModifiers::NONE),
+            '\x00'..='\x1f' => (
+                KeyCode::Char(((c as u8) | 0x40).to_ascii_lowercase() as char),
+                KeyModifiers::CONTROL,
+            ),
+            _ => (KeyCode::Char(c), KeyModifiers::NONE),
+        };
+        keys.push(KeyData {
+            key_code,
+            modifiers,
+        });
+        rest = &rest[c.len_utf8()..];
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::commands::no_op_command::NoOpCommand;
+
+    fn no_op(_: &CommandData) -> Box<dyn Command> {
+        Box::new(NoOpCommand {})
+    }
+
+    fn lookup(keymap: &Keymap, keys: &str) -> String {
+        match keymap.lookup(&parse_keys(keys)) {
+            Lookup::NotFound => "not found".to_string(),
+            Lookup::Prefix => "prefix".to_string(),
+            Lookup::Found(_, len) => format!("found {}", len),
+            Lookup::Ambiguous(_) => "ambiguous".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let key = |key_code, modifiers| KeyData {
+            key_code,
+            modifiers,
+        };
+        assert_eq!(
+            parse_keys("a<C-W><cr><lt>x>"),
+            [
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Char('<'), KeyModifiers::NONE),
+                key(KeyCode::Char('x'), KeyModifiers::NONE),
+                key(KeyCode::Char('>'), KeyModifiers::NONE),
+            ]
+        );
+        assert_eq!(parse_keys("<F12><foo>").len(), 6);
+        assert_eq!(parse_keys("a<Bar>"), parse_keys("a|"));
+        assert_eq!(key_names(&parse_keys("a<c-w><Esc><lt><F2>")), "a<C-W><Esc><lt><F2>");
+    }
+
+    #[test]
+    fn test_keys_to_text() {
+        let keys = parse_keys("ia<lt>lt><Left><BS><Esc><C-W>j:w<CR>");
+        let text = keys_to_text(&keys);
+        assert_eq!(text, "ia<lt><Left>\x7f\x1b\x17j:w\r");
+        assert_eq!(text_to_keys(&text), keys);
+        assert_eq!(key_names(&text_to_keys("x\n<Up>")), "x<CR><Up>");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut keymap = Keymap::default();
+        keymap.add("x", Binding::Command(no_op));
+        keymap.add("ZZ", Binding::Command(no_op));
+        keymap.add("g", Binding::Command(no_op));
+        keymap.add("gg", Binding::Motion(no_op));
+
+        assert_eq!(lookup(&keymap, "x"), "found 1");
+        assert_eq!(lookup(&keymap, "Z"), "prefix");
+        assert_eq!(lookup(&keymap, "ZZ"), "found 2");
+        assert_eq!(lookup(&keymap, "Zx"), "not found");
+        assert_eq!(lookup(&keymap, "q"), "not found");
+        assert_eq!(lookup(&keymap, "g"), "ambiguous");
+        assert_eq!(lookup(&keymap, "gg"), "found 2");
+        assert_eq!(lookup(&keymap, "gx"), "found 1");
+
+        assert!(keymap.remove(&parse_keys("gg")).is_some());
+        assert_eq!(lookup(&keymap, "g"), "found 1");
+        assert!(keymap.remove(&parse_keys("Z")).is_none());
+        assert!(keymap.remove(&parse_keys("ZZ")).is_some());
+        assert_eq!(lookup(&keymap, "Z"), "not found");
+        assert_eq!(keymap.bindings().len(), 2);
+    }
+}
@@ -1,6 +1,6 @@
 pub mod base;
-pub mod key_codes;
 pub mod compose;
+pub mod keymap;
 pub mod factory;
 pub mod commands;
 pub mod region;
\ No newline at end of file
@@ -1,50 +1,66 @@
-use crossterm::event::{KeyCode, KeyModifiers};
-
+use crate::buffer::CursorPositionInBuffer;
 use crate::command::base::Command;
 use crate::command::base::CommandData;
 use crate::command::base::JumpCommandData;
-use crate::command::factory::command_factory;
+use crate::command::factory::{command_factory, default_keymaps};
+use crate::command::keymap::parse_keys;
 use crate::editor::Editor;
+use crate::editor::EditorCursorData;
 use crate::editor::Region;
 use crate::generic_error::GenericResult;
 use crate::command::commands::move_cursor::MoveBeginningOfLine;
 use super::commands::move_cursor::MoveEndOfLine;
 use super::commands::move_cursor::NextLine;
-use super::key_codes::is_editing_command_with_range;
 
-fn is_line_oriented_command(jump_command_data: JumpCommandData) -> bool {
-    let key_code = jump_command_data.key_code;
-    let modifiers = jump_command_data.modifiers;
-    // If key_code is ‘j’, ‘k’, Ctrl-f, Ctrl-b, etc., the command is regarded as line-oriented.
-    match key_code {
-        KeyCode::Char('j') | KeyCode::Char('k') => true,
-        KeyCode::Char('f') | KeyCode::Char('b') => {
-            if modifiers == KeyModifiers::CONTROL {
-                true
-            } else {
-                false
-            }
-        }
-        _ => false,
-    }
+// Whether the motion of the range is one of `notations`, in the notation of
+// `parse_keys`.
+fn is_motion(jump_command_data: &JumpCommandData, notations: &[&str]) -> bool {
+    notations
+        .iter()
+        .any(|notation| jump_command_data.keys == parse_keys(notation))
 }
 
-fn is_end_of_line_command(jump_command_data: JumpCommandData) -> bool {
-    let key_code = jump_command_data.key_code;
-    // If key_code is ‘$’, it is regarded as a command to move to the end of the line.
-    match key_code {
-        KeyCode::Char('$') => true,
-        _ => false,
-    }
+fn is_line_oriented_command(jump_command_data: &JumpCommandData) -> bool {
+    // ‘j’, ‘k’, Ctrl-f, Ctrl-b, ‘gg’, etc. are regarded as line-oriented.
+    is_motion(jump_command_data, &["j", "k", "<Down>", "<Up>", "<C-F>", "<C-B>", "gg"])
+}
+
+fn is_end_of_line_command(jump_command_data: &JumpCommandData) -> bool {
+    // ‘$’ is regarded as a command to move to the end of the line.
+    is_motion(jump_command_data, &["$"])
+}
+
+// `e` takes in the character it moves to.
+fn is_inclusive_command(jump_command_data: &JumpCommandData) -> bool {
+    is_motion(jump_command_data, &["e"])
+}
+
+// `l` after an operator may go past the last character of the line, so that
+// `dl` and `s` take it.
+fn is_forward_char_command(jump_command_data: &JumpCommandData) -> bool {
+    is_motion(jump_command_data, &["l", "<Right>"])
+}
+
+// The range of `dd`, `3cc`, etc. is the operator itself.
+fn is_operator_twice(jump_command_data: &JumpCommandData) -> bool {
+    default_keymaps().is_operator(&jump_command_data.keys)
 }
 
 pub fn get_region(editor: &mut Editor, jump_command_data: JumpCommandData) -> GenericResult<Region> {
-    if is_line_oriented_command(jump_command_data) {
+    if is_line_oriented_command(&jump_command_data) {
         get_region_from_line_oriented_command(editor, jump_command_data)
-    } else if is_end_of_line_command(jump_command_data) {
+    } else if is_end_of_line_command(&jump_command_data) {
         get_region_from_end_of_line_command(editor)
-    } else if is_editing_command_with_range(&jump_command_data.key_code) {
-        get_region_on_this_line(editor)
+    } else if is_operator_twice(&jump_command_data) {
+        get_region_on_these_lines(editor, jump_command_data.count)
+    } else if is_forward_char_command(&jump_command_data) {
+        Ok(get_region_from_forward_char_command(editor, jump_command_data.count))
+    } else if is_inclusive_command(&jump_command_data) {
+        let mut region = get_region_from_command(editor, jump_command_data)?;
+        if region.start.cursor_position_in_buffer < region.end.cursor_position_in_buffer {
+            region.end.cursor_position_in_buffer.col += 1;
+        }
+        Ok(region)
     } else {
         get_region_from_command(editor, jump_command_data)
     }
@@ -54,22 +70,10 @@ fn get_region_from_line_oriented_command(editor: &mut Editor, jump_command_data:
     let mut move_beginning_of_line = MoveBeginningOfLine;
     move_beginning_of_line.execute(editor)?;
     let start_cursor_data = editor.snapshot_cursor_data();
-    let command_data: CommandData = jump_command_data.into();
-    for _ in 0..command_data.count {
-        let mut jump_command = command_factory(&command_data);
-        jump_command.execute(editor)?;
-    }
-    let current_cursor_data = editor.snapshot_cursor_data();
-    if start_cursor_data.cursor_position_in_buffer < current_cursor_data.cursor_position_in_buffer {
-        let mut next_line = NextLine;
-        next_line.execute(editor)?;
-    }
+    execute_motion(editor, jump_command_data.into())?;
     move_beginning_of_line.execute(editor)?;
     let end_cursor_data = editor.snapshot_cursor_data();
-    Ok(Region {
-        start: start_cursor_data,
-        end: end_cursor_data,
-    })
+    Ok(whole_lines(start_cursor_data, end_cursor_data))
 }
 
 fn get_region_from_end_of_line_command(editor: &mut Editor) -> GenericResult<Region> {
@@ -77,38 +81,78 @@ fn get_region_from_end_of_line_command(editor: &mut Editor) -> GenericResult<Reg
     let mut move_end_of_line = MoveEndOfLine;
     move_end_of_line.execute(editor)?;
     let mut end_cursor_data = editor.snapshot_cursor_data();
-    end_cursor_data.cursor_position_in_buffer.col = editor.buffer.lines[end_cursor_data.cursor_position_in_buffer.row].len();
+    end_cursor_data.cursor_position_in_buffer.col = editor.buffer.line_len(end_cursor_data.cursor_position_in_buffer.row);
     Ok(Region {
         start: start_cursor_data,
         end: end_cursor_data,
+        linewise: false,
     })
 }
 
-fn get_region_on_this_line(editor: &mut Editor) -> GenericResult<Region> {
+fn get_region_from_forward_char_command(editor: &mut Editor, count: usize) -> Region {
+    let start_cursor_data = editor.snapshot_cursor_data();
+    let mut end_cursor_data = start_cursor_data;
+    let position = &mut end_cursor_data.cursor_position_in_buffer;
+    position.col = (position.col + count).min(editor.buffer.line_len(position.row));
+    Region {
+        start: start_cursor_data,
+        end: end_cursor_data,
+        linewise: false,
+    }
+}
+
+// `count` lines from the cursor line.
+fn get_region_on_these_lines(editor: &mut Editor, count: usize) -> GenericResult<Region> {
     let mut move_beginning_of_line = MoveBeginningOfLine;
     move_beginning_of_line.execute(editor)?;
     let start_cursor_data = editor.snapshot_cursor_data();
     let mut next_line = NextLine;
-    next_line.execute(editor)?;
-    let mut move_beginning_of_line = MoveBeginningOfLine;
+    for _ in 1..count.max(1) {
+        next_line.execute(editor)?;
+    }
     move_beginning_of_line.execute(editor)?;
     let end_cursor_data = editor.snapshot_cursor_data();
-    Ok(Region {
-        start: start_cursor_data,
-        end: end_cursor_data,
-    })
+    Ok(whole_lines(start_cursor_data, end_cursor_data))
+}
+
+// The lines from one cursor to the other, whichever comes first. The region
+// ends at the start of the line after the last one, past the end of the
+// buffer for its last line.
+fn whole_lines(a: EditorCursorData, b: EditorCursorData) -> Region {
+    let (start, mut end) = if a.cursor_position_in_buffer <= b.cursor_position_in_buffer {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    end.cursor_position_in_buffer = CursorPositionInBuffer {
+        row: end.cursor_position_in_buffer.row + 1,
+        col: 0,
+    };
+    Region {
+        start,
+        end,
+        linewise: true,
+    }
+}
+
+// Moves the cursor by the motion `count` times, or once for a motion that
+// does its count itself, like `3gg`.
+fn execute_motion(editor: &mut Editor, command_data: CommandData) -> GenericResult<()> {
+    let mut motion = command_factory(&command_data);
+    let times = if motion.takes_count() { 1 } else { command_data.count };
+    for _ in 0..times {
+        motion.execute(editor)?;
+    }
+    Ok(())
 }
 
 fn get_region_from_command(editor: &mut Editor, jump_command_data: JumpCommandData) -> GenericResult<Region> {
     let start_cursor_data = editor.snapshot_cursor_data();
-    let command_data: CommandData = jump_command_data.into();
-    for _ in 0..command_data.count {
-        let mut jump_command = command_factory(&command_data);
-        jump_command.execute(editor)?;
-    }
+    execute_motion(editor, jump_command_data.into())?;
     let end_cursor_data = editor.snapshot_cursor_data();
     Ok(Region {
         start: start_cursor_data,
         end: end_cursor_data,
+        linewise: false,
     })
 }
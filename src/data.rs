@@ -9,6 +9,8 @@ pub enum TokenType {
     AddressPattern,
     Replacement,
     Filename,
+    // The keys of a mapping, in vim's notation like `<CR>`.
+    Keys,
     Separator,
     EndOfInput,
     Illegal,
@@ -1,15 +1,34 @@
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-use crossterm::{
-    terminal::{self, ClearType},
-    ExecutableCommand,
-};
-
-use log::info;
+use crossterm::event::KeyEvent;
+use log::{error, info};
+use regex::Regex;
 
 use crate::{command::factory::command_factory, data::{LineAddressType, SimpleLineAddressType}}; // SimpleLineAddressType needed for Absolute matching
-use crate::render::render;
+use crate::command::commands::go_to_line::GoToLineCommand;
+use crate::command::compose::KeyData;
+use crate::command::keymap::{keys_to_text, parse_keys, text_to_keys};
+use crate::file_stamp::FileStamp;
+use crate::buffer_list::{BufferList, BufferState};
+use crate::backend::Backend;
+use crate::mapping::{MapMode, Mappings, TypeaheadKey};
+use crate::options::Options;
+use crate::pager::Pager;
+use crate::register::Registers;
+use crate::render::{render, Renderer};
+use crate::status_line::WindowStatus;
+use crate::swap_file::{self, SwapFile};
+use crate::syntax::{Grammars, Syntax};
+use crate::theme::{ColorDepth, Theme, Themes};
+use crate::util::{
+    config_dir, get_char_width, get_char_width_at, get_line_height, get_line_width,
+    get_wrapped_position,
+};
+use crate::window::{Direction, WindowLayout, WindowOptions};
 use crate::{buffer::Buffer, command::base::ExecutedCommand, generic_error::GenericResult};
 use crate::{
     buffer::CursorPositionInBuffer,
@@ -23,13 +42,13 @@ pub struct TerminalSize {
     pub height: u16,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct CursorPositionOnScreen {
     pub row: u16,
     pub col: u16,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct EditorCursorData {
     pub cursor_position_on_screen: CursorPositionOnScreen,
     pub cursor_position_in_buffer: CursorPositionInBuffer,
@@ -40,6 +59,24 @@ pub struct EditorCursorData {
 pub struct Region {
     pub start: EditorCursorData,
     pub end: EditorCursorData,
+    // Whole lines, as with `dj` and `dd`.
+    pub linewise: bool,
+}
+
+// The last change to the buffer, which `.` repeats: its command, and the
+// text typed when it went to insert mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastChange {
+    pub command_data: CommandData,
+    pub text: String,
+}
+
+// A macro being recorded with `q`: the register it goes to, and the keys
+// typed so far.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    pub register: char,
+    pub keys: Vec<KeyData>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -49,8 +86,14 @@ pub enum Mode {
     ExCommand,
 }
 
+// Like vim's 'updatecount': the swap file is written after this many keys.
+const SWAP_UPDATE_COUNT: usize = 200;
+
 pub struct Editor {
     pub buffer: Buffer,
+    buffers: BufferList,
+    windows: WindowLayout,
+    // The argument list: the files given on the command line or to `:n`.
     editing_file_paths: Vec<PathBuf>,
     current_file_index: usize,
     pub is_dirty: bool,
@@ -59,17 +102,63 @@ pub struct Editor {
     pub terminal_size: TerminalSize,
     pub cursor_position_on_screen: CursorPositionOnScreen,
     pub cursor_position_in_buffer: CursorPositionInBuffer,
+    // The first line shown and, when lines do not wrap, the first screen
+    // column shown.
     pub window_position_in_buffer: CursorPositionInBuffer,
+    // The message on the command line, like an error or what `:set ts?`
+    // shows. The mode, the ruler and 'showcmd' are drawn apart from it.
     pub status_line: String,
+    // The keys of the command being typed, for 'showcmd'.
+    pub pending_keys: String,
+    // Output longer than the command line, shown over the windows until a
+    // key is typed.
+    pub pager: Option<Pager>,
+    pub mappings: Mappings,
+    // The keys typed, or put in place of a mapping, that are still to be
+    // handled.
+    pub typeahead: VecDeque<TypeaheadKey>,
+    // Deleted and yanked text, shared by the buffers.
+    pub registers: Registers,
     pub command_history: Vec<Vec<ExecutedCommand>>,
+    pub last_change: Option<LastChange>,
+    pub recording: Option<Recording>,
+    // The register `@` last executed, which `@@` executes again, `:` for
+    // `@:`.
+    pub last_executed_register: Option<char>,
+    // The last ex command, which `@:` executes again.
+    pub last_ex_command: Option<String>,
     pub last_input_string: String,
     pub ex_command_data: String,
+    pub renderer: Renderer,
+    // The bell rings at the next render.
+    pub ring_bell: bool,
+    rendered_text_width: u16,
+    pub options: Options,
+    pub grammars: Grammars,
+    pub themes: Themes,
+    pub theme: Rc<Theme>,
+    // The colors of the terminal, which decide the colors of the theme used.
+    pub color_depth: ColorDepth,
+    swap_file: Option<SwapFile>,
+    keys_since_swap: usize,
+    // The change on disk that was last warned about, so it is reported only once.
+    reported_file_stamp: Option<FileStamp>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
     pub fn new() -> Editor {
+        let themes = Themes::builtin();
+        let theme = themes.find("default").expect("the default theme is built in");
         Editor {
             buffer: Buffer::new(),
+            buffers: BufferList::new(),
+            windows: WindowLayout::new(1),
             editing_file_paths: Vec::new(),
             current_file_index: 0,
             is_dirty: false,
@@ -83,53 +172,962 @@ impl Editor {
             cursor_position_in_buffer: CursorPositionInBuffer { row: 0, col: 0 },
             window_position_in_buffer: CursorPositionInBuffer { row: 0, col: 0 },
             status_line: "".to_string(),
+            pending_keys: String::new(),
+            pager: None,
+            mappings: Mappings::default(),
+            typeahead: VecDeque::new(),
+            registers: Registers::default(),
             command_history: Vec::new(),
+            last_change: None,
+            recording: None,
+            last_executed_register: None,
+            last_ex_command: None,
             last_input_string: "".to_string(),
             ex_command_data: "".to_string(),
+            renderer: Renderer::default(),
+            ring_bell: false,
+            rendered_text_width: 0,
+            options: Options::default(),
+            grammars: Grammars::builtin(),
+            themes,
+            theme,
+            color_depth: ColorDepth::Ansi16,
+            swap_file: None,
+            keys_since_swap: 0,
+            reported_file_stamp: None,
         }
     }
 
-    pub fn open_file(&mut self, file_path: &PathBuf) {
-        self.buffer = Buffer::from_file(file_path);
-        self.editing_file_paths.push(file_path.clone());
+    // Like vi, a file that does not exist yet or cannot be read still becomes
+    // the current file; only the status line tells what happened.
+    pub fn open_file(&mut self, file_path: &Path) -> GenericResult<()> {
+        self.editing_file_paths.push(file_path.to_path_buf());
         self.current_file_index = self.editing_file_paths.len() - 1;
+        self.edit_file(file_path)
     }
 
-    pub fn save_file(&self) -> GenericResult<()> {
-        if let Some(file_path) = self.editing_file_paths.get(self.current_file_index) {
-            self.buffer.to_file(file_path)
-        } else {
-            println!("No file to save");
-            GenericResult::Err("No file to save".to_string().into())
+    /// Makes `file_path` the current buffer, loading it unless it is already
+    /// in the buffer list. The buffer that was current stays in the list.
+    pub fn edit_file(&mut self, file_path: &Path) -> GenericResult<()> {
+        if let Some(index) = self.buffers.position_by_path(file_path) {
+            self.switch_to_buffer(index);
+            return Ok(());
         }
+        // A fresh editor's empty buffer is reused, like vim does.
+        let reusable = self.current_file_path().is_none() && !self.is_dirty && self.buffer.is_empty();
+        if !reusable {
+            let index = self.buffers.push(None, BufferState::default());
+            self.switch_to_buffer(index);
+        }
+        self.buffers.current_mut().file_path = Some(file_path.to_path_buf());
+        self.load_current_file()
+    }
+
+    fn load_current_file(&mut self) -> GenericResult<()> {
+        self.remove_swap_file();
+        self.buffer = Buffer::new();
+        self.is_dirty = false;
+        self.reported_file_stamp = None;
+        self.command_history.clear();
+        self.restore_cursor_data(EditorCursorData::default());
+
+        let file_path = self.current_file_path().cloned().ok_or("E32: No file name")?;
+        let file_name = file_path.to_string_lossy();
+        if file_path.is_dir() {
+            return Err(format!("\"{}\" is a directory", file_name).into());
+        }
+        self.buffer.file_stamp = Some(FileStamp::of(&file_path));
+        let result = match Buffer::from_file(&file_path) {
+            Ok(buffer) => {
+                self.buffer = buffer;
+                let mut messages = Vec::new();
+                if !is_writable(&file_path) {
+                    self.buffer.read_only = true;
+                    messages.push(format!("\"{}\" [readonly]", file_name));
+                }
+                if let Some(swap_path) = SwapFile::existing(&file_path).next() {
+                    messages.push(format!(
+                        "E325: ATTENTION: Found a swap file \"{}\"; use :recover to restore it",
+                        swap_path.to_string_lossy()
+                    ));
+                }
+                if !messages.is_empty() {
+                    self.status_line = messages.join(" ");
+                }
+                Ok(())
+            }
+            Err(e) => match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+                Some(ErrorKind::NotFound) => {
+                    self.status_line = format!("\"{}\" [New File]", file_name);
+                    Ok(())
+                }
+                Some(ErrorKind::PermissionDenied) => {
+                    self.buffer.read_only = true;
+                    self.status_line = format!("\"{}\" [Permission Denied]", file_name);
+                    Ok(())
+                }
+                _ => Err(format!("\"{}\" {}", file_name, e).into()),
+            },
+        };
+        self.detect_syntax();
+        result
+    }
+
+    /// Highlights the buffer with the grammar for the current file, if any.
+    pub fn detect_syntax(&mut self) {
+        let first_line = self.buffer.get_line(0).map(|line| line.to_string());
+        let grammar = self.current_file_path().and_then(|path| {
+            self.grammars
+                .detect(path, first_line.as_deref().unwrap_or_default())
+        });
+        self.buffer.syntax = grammar.map(Syntax::new);
+    }
+
+    pub fn save_file(&mut self, force: bool) -> GenericResult<()> {
+        if self.buffer.read_only && !force {
+            return Err("E45: 'readonly' option is set (add ! to override)".into());
+        }
+        let file_path = self.current_file_path().cloned().ok_or("E32: No file name")?;
+        let disk_file_stamp = FileStamp::of(&file_path);
+        if !force
+            && disk_file_stamp != FileStamp::Missing
+            && Some(disk_file_stamp) != self.buffer.file_stamp
+        {
+            return Err(
+                "WARNING: The file has been changed since reading it!!! (add ! to override)".into(),
+            );
+        }
+        let bytes = self.buffer.to_file(&file_path)?;
+        self.buffer.file_stamp = Some(FileStamp::of(&file_path));
+        self.status_line = format!(
+            "\"{}\" {}L, {}B written",
+            file_path.to_string_lossy(),
+            self.buffer.len_lines(),
+            bytes
+        );
+        self.is_dirty = false;
+        self.remove_swap_file();
+        Ok(())
+    }
+
+    // `:w file`. Writing to a file other than the current one leaves the
+    // buffer modified, unless the buffer had no file name yet.
+    pub fn save_file_as(&mut self, file_path: &PathBuf, force: bool) -> GenericResult<()> {
+        if self.current_file_path() == Some(file_path) {
+            return self.save_file(force);
+        }
+        if file_path.exists() && !force {
+            return Err("E13: File exists (add ! to override)".into());
+        }
+        let bytes = self.buffer.to_file(file_path)?;
+        if self.current_file_path().is_none() {
+            self.buffers.current_mut().file_path = Some(file_path.clone());
+            self.buffer.file_stamp = Some(FileStamp::of(file_path));
+            self.is_dirty = false;
+            self.remove_swap_file();
+        }
+        self.status_line = format!(
+            "\"{}\" [New] {}L, {}B written",
+            file_path.to_string_lossy(),
+            self.buffer.len_lines(),
+            bytes
+        );
+        Ok(())
     }
 
     pub fn from_cmd_args(args: Vec<String>) -> Editor {
         let mut editor = Editor::new();
+        if let Some(dir) = config_dir() {
+            if let Err(e) = editor.grammars.load_dir(&dir.join("syntax")) {
+                editor.status_line = e.to_string();
+            }
+            if let Err(e) = editor.themes.load_dir(&dir.join("colors")) {
+                editor.status_line = e.to_string();
+            }
+            // A user's theme named "default" replaces the built in one.
+            if let Some(theme) = editor.themes.find("default") {
+                editor.theme = theme;
+            }
+        }
+        let mut file_names = args.get(1..).unwrap_or_default();
+        // `evi -r file` recovers the file from its swap file.
+        let recover = file_names.first().is_some_and(|arg| arg == "-r");
+        if recover {
+            file_names = &file_names[1..];
+        }
         // args で与えられた複数のファイル名のうち、最初のファイルを開き、残りを editing_file_paths に追加する
-        if args.len() > 1 {
-            editor.open_file(&PathBuf::from(&args[1]));
-            for file_name in &args[2..] {
+        if let Some((first, rest)) = file_names.split_first() {
+            let result = editor.open_file(&PathBuf::from(first)).and_then(|_| {
+                if recover {
+                    editor.recover()
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = result {
+                editor.status_line = e.to_string();
+            }
+            for file_name in rest {
                 editor.editing_file_paths.push(PathBuf::from(file_name));
             }
+        } else if recover {
+            editor.status_line = "E32: No file name".to_string();
         }
         editor
     }
 
+    /// Replaces the buffer with the contents of the swap file left by another session.
+    pub fn recover(&mut self) -> GenericResult<()> {
+        let file_path = self.current_file_path().cloned().ok_or("E32: No file name")?;
+        let own_swap_path = self.swap_file.as_ref().map(|swap_file| &swap_file.path);
+        let swap_path = SwapFile::existing(&file_path)
+            .find(|path| Some(path) != own_swap_path)
+            .ok_or_else(|| format!("E305: No swap file found for {}", file_path.to_string_lossy()))?;
+        let recovered = swap_file::recover(&swap_path)?;
+
+        let mut buffer = recovered.buffer;
+        buffer.read_only = self.buffer.read_only;
+        buffer.file_stamp = self.buffer.file_stamp;
+        self.buffer = buffer;
+        self.detect_syntax();
+        self.is_dirty = true;
+        // The swap file of the other session is left as it is; the recovered
+        // text goes to a swap file of this one at the next update.
+        self.keys_since_swap += 1;
+
+        let line_address = LineAddressType::Absolute(SimpleLineAddressType::LineNumber(
+            recovered.cursor_row.min(self.buffer.len_lines().saturating_sub(1)) + 1,
+        ));
+        GoToLineCommand { line_address }.execute(self)?;
+        self.status_line = "Recovery completed. You should check if everything is OK.".to_string();
+        Ok(())
+    }
+
+    /// Reads the current file again, discarding the changes made in the buffer.
+    pub fn reload_file(&mut self) -> GenericResult<()> {
+        let file_path = self.current_file_path().cloned().ok_or("E32: No file name")?;
+        let mut buffer = Buffer::from_file(&file_path)
+            .map_err(|e| format!("\"{}\" {}", file_path.to_string_lossy(), e))?;
+        buffer.read_only = !is_writable(&file_path);
+        self.buffer = buffer;
+        self.detect_syntax();
+        self.is_dirty = false;
+        self.reported_file_stamp = None;
+        self.command_history.clear();
+        self.remove_swap_file();
+        self.status_line = self.file_info();
+
+        let line_address = LineAddressType::Absolute(SimpleLineAddressType::LineNumber(
+            self.cursor_position_in_buffer.row.min(self.buffer.len_lines().saturating_sub(1)) + 1,
+        ));
+        GoToLineCommand { line_address }.execute(self)
+    }
+
+    /// Warns once when another program changed or removed the current file.
+    /// Returns true if the status line was updated.
+    pub fn check_file_changed(&mut self) -> bool {
+        let Some(file_path) = self.current_file_path() else {
+            return false;
+        };
+        let disk_file_stamp = Some(FileStamp::of(file_path));
+        if self.buffer.file_stamp.is_none()
+            || disk_file_stamp == self.buffer.file_stamp
+            || disk_file_stamp == self.reported_file_stamp
+        {
+            return false;
+        }
+        let file_name = file_path.to_string_lossy();
+        self.status_line = if disk_file_stamp == Some(FileStamp::Missing) {
+            format!("E211: File \"{}\" no longer available", file_name)
+        } else if self.is_dirty {
+            format!(
+                "W12: Warning: File \"{}\" has changed and the buffer was changed in evi as well",
+                file_name
+            )
+        } else {
+            format!(
+                "W11: Warning: File \"{}\" has changed since editing started; :e! to reload",
+                file_name
+            )
+        };
+        self.reported_file_stamp = disk_file_stamp;
+        true
+    }
+
+    /// Counts a typed key, writing the swap file every `SWAP_UPDATE_COUNT` keys.
+    pub fn record_key_for_swap(&mut self) {
+        self.keys_since_swap += 1;
+        if self.keys_since_swap >= SWAP_UPDATE_COUNT {
+            self.update_swap_file();
+        }
+    }
+
+    /// Writes the unsaved buffer to the swap file if keys were typed since the last write.
+    pub fn update_swap_file(&mut self) {
+        if !self.is_dirty || self.keys_since_swap == 0 {
+            return;
+        }
+        let Some(file_path) = self.buffers.current().file_path.as_ref() else {
+            return;
+        };
+        if self.swap_file.is_none() {
+            self.swap_file = SwapFile::create_for(file_path);
+        }
+        let Some(swap_file) = &self.swap_file else {
+            self.status_line = "E326: Too many swap files found".to_string();
+            return;
+        };
+        if let Err(e) = swap_file.write(file_path, &self.buffer, self.cursor_position_in_buffer.row) {
+            error!("Cannot write swap file {:?}: {}", swap_file.path, e);
+            self.status_line = "E303: Unable to write swap file, recovery impossible".to_string();
+        }
+        self.keys_since_swap = 0;
+    }
+
+    pub fn remove_swap_file(&mut self) {
+        if let Some(swap_file) = self.swap_file.take() {
+            swap_file.remove();
+        }
+        self.keys_since_swap = 0;
+    }
+
+    /// Removes the swap files of all buffers, when evi exits normally.
+    pub fn remove_swap_files(&mut self) {
+        self.remove_swap_file();
+        for index in 0..self.buffers.entries().len() {
+            if let Some(state) = self.buffers.hidden_state_mut(index) {
+                if let Some(swap_file) = state.swap_file.take() {
+                    swap_file.remove();
+                }
+            }
+        }
+    }
+
+    /// Returns true if `file_path` names the file of the current buffer.
+    pub fn is_current_file(&self, file_path: &Path) -> bool {
+        self.buffers.position_by_path(file_path) == Some(self.buffers.current_index())
+    }
+
+    /// Throws away the unsaved changes of the current buffer, like `:e!`.
+    pub fn discard_changes(&mut self) -> GenericResult<()> {
+        if self.current_file_path().is_some() {
+            return self.load_current_file();
+        }
+        self.remove_swap_file();
+        self.buffer = Buffer::new();
+        self.is_dirty = false;
+        self.command_history.clear();
+        self.restore_cursor_data(EditorCursorData::default());
+        Ok(())
+    }
+
+    fn current_file_path(&self) -> Option<&PathBuf> {
+        self.buffers.current().file_path.as_ref()
+    }
+
     pub fn current_file_name(&self) -> Option<String> {
-        self.editing_file_paths
-            .get(self.current_file_index)
+        self.current_file_path()
             .map(|path| path.to_string_lossy().to_string())
     }
 
+    // `"name" [Modified] 3L, 12C`, shown when a buffer becomes current.
+    fn file_info(&self) -> String {
+        format!(
+            "\"{}\"{} {}L, {}C",
+            self.buffers.current().name(),
+            if self.is_dirty { " [Modified]" } else { "" },
+            self.buffer.len_lines(),
+            self.buffer.len_chars()
+        )
+    }
+
+    fn take_buffer_state(&mut self) -> BufferState {
+        BufferState {
+            buffer: std::mem::take(&mut self.buffer),
+            is_dirty: std::mem::take(&mut self.is_dirty),
+            command_history: std::mem::take(&mut self.command_history),
+            cursor_data: self.snapshot_cursor_data(),
+            swap_file: self.swap_file.take(),
+            keys_since_swap: std::mem::take(&mut self.keys_since_swap),
+            reported_file_stamp: self.reported_file_stamp.take(),
+        }
+    }
+
+    fn set_buffer_state(&mut self, state: BufferState) {
+        self.buffer = state.buffer;
+        self.is_dirty = state.is_dirty;
+        self.command_history = state.command_history;
+        self.restore_cursor_data(state.cursor_data);
+        self.swap_file = state.swap_file;
+        self.keys_since_swap = state.keys_since_swap;
+        self.reported_file_stamp = state.reported_file_stamp;
+    }
+
+    /// Makes the buffer at `index` in the buffer list current.
+    pub fn switch_to_buffer(&mut self, index: usize) {
+        self.swap_buffer(index);
+        self.status_line = self.file_info();
+    }
+
+    fn swap_buffer(&mut self, index: usize) {
+        if index != self.buffers.current_index() {
+            let state = self.take_buffer_state();
+            let state = self.buffers.switch(index, state);
+            self.set_buffer_state(state);
+        }
+    }
+
+    /// Switches to buffer `number`, like `:b N`.
+    pub fn switch_to_buffer_number(&mut self, number: usize) -> GenericResult<()> {
+        let index = self
+            .buffers
+            .position_by_number(number)
+            .ok_or_else(|| format!("E86: Buffer {} does not exist", number))?;
+        self.switch_to_buffer(index);
+        Ok(())
+    }
+
+    /// Switches to the alternate buffer, like `Ctrl-^` and `:e #`.
+    pub fn switch_to_alternate_buffer(&mut self) -> GenericResult<()> {
+        let number = self.buffers.alternate_number().ok_or("E23: No alternate file")?;
+        self.switch_to_buffer_number(number)
+    }
+
+    /// Removes buffer `number` (the current buffer if `None`) from the buffer list, like `:bd`.
+    pub fn delete_buffer(&mut self, number: Option<usize>, force: bool) -> GenericResult<()> {
+        let number = number.unwrap_or(self.buffers.current().number);
+        let index = self
+            .buffers
+            .position_by_number(number)
+            .ok_or_else(|| format!("E516: No buffers were deleted: bd {}", number))?;
+        let is_dirty = match self.buffers.hidden_state_mut(index) {
+            Some(state) => state.is_dirty,
+            None => self.is_dirty,
+        };
+        if is_dirty && !force {
+            return Err(format!(
+                "E89: No write since last change for buffer {} (add ! to override)",
+                number
+            )
+            .into());
+        }
+        if index == self.buffers.current_index() {
+            let next_index = self
+                .buffers
+                .alternate_number()
+                .and_then(|number| self.buffers.position_by_number(number))
+                .or_else(|| (0..self.buffers.entries().len()).find(|&i| i != index))
+                .unwrap_or_else(|| self.buffers.push(None, BufferState::default()));
+            self.switch_to_buffer(next_index);
+        }
+        self.windows.close_showing(number);
+        self.fit_cursor_in_window();
+        let index = self.buffers.position_by_number(number).unwrap();
+        if let Some(swap_file) = self
+            .buffers
+            .remove(index)
+            .hidden_state
+            .and_then(|state| state.swap_file)
+        {
+            swap_file.remove();
+        }
+        Ok(())
+    }
+
+    /// Returns the name of a buffer other than the current one that has unsaved changes.
+    pub fn modified_hidden_buffer(&mut self) -> Option<String> {
+        let current = self.buffers.current_index();
+        (0..self.buffers.entries().len())
+            .filter(|&index| index != current)
+            .find(|&index| {
+                self.buffers
+                    .hidden_state_mut(index)
+                    .is_some_and(|state| state.is_dirty)
+            })
+            .map(|index| self.buffers.entries()[index].name())
+    }
+
+    /// Lists the buffers like `:ls`: number, `%` current / `#` alternate,
+    /// `a` active / `h` hidden, `+` modified, name and cursor line.
+    pub fn buffer_list_lines(&self) -> Vec<String> {
+        let current = self.buffers.current_index();
+        self.buffers
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let (is_dirty, row) = match &entry.hidden_state {
+                    Some(state) => (
+                        state.is_dirty,
+                        state.cursor_data.cursor_position_in_buffer.row,
+                    ),
+                    None => (self.is_dirty, self.cursor_position_in_buffer.row),
+                };
+                let flag = if index == current {
+                    '%'
+                } else if self.buffers.alternate_number() == Some(entry.number) {
+                    '#'
+                } else {
+                    ' '
+                };
+                format!(
+                    "{:>3} {}{} {} \"{}\" line {}",
+                    entry.number,
+                    flag,
+                    if index == current { 'a' } else { 'h' },
+                    if is_dirty { '+' } else { ' ' },
+                    entry.name(),
+                    row + 1
+                )
+            })
+            .collect()
+    }
+
+    pub fn argument_count(&self) -> usize {
+        self.editing_file_paths.len()
+    }
+
+    pub fn current_argument_index(&self) -> usize {
+        self.current_file_index
+    }
+
+    /// Edits the file at `index` in the argument list. With `force` the
+    /// changes of the current buffer are discarded, like `:n!`.
+    pub fn edit_argument(&mut self, index: usize, force: bool) -> GenericResult<()> {
+        if self.is_dirty && !force {
+            return Err("E37: No write since last change (add ! to override)".into());
+        }
+        if self.is_dirty {
+            self.discard_changes()?;
+        }
+        self.current_file_index = index;
+        self.status_line.clear();
+        self.edit_file(&self.editing_file_paths[index].clone())?;
+        if self.status_line.is_empty() {
+            self.status_line = self.file_info();
+        }
+        Ok(())
+    }
+
+    /// Replaces the argument list with `file_paths` and edits the first one.
+    pub fn set_arguments(&mut self, file_paths: Vec<PathBuf>, force: bool) -> GenericResult<()> {
+        if self.is_dirty && !force {
+            return Err("E37: No write since last change (add ! to override)".into());
+        }
+        self.editing_file_paths = file_paths;
+        self.edit_argument(0, force)
+    }
+
+    /// Lists the argument list with the current file in brackets, like `:args`.
+    pub fn arguments_description(&self) -> String {
+        self.editing_file_paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                if index == self.current_file_index {
+                    format!("[{}]", path.to_string_lossy())
+                } else {
+                    path.to_string_lossy().to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     pub fn resize_terminal(&mut self, width: u16, height: u16) {
         info!("Resize terminal to width: {}, height: {}", width, height);
         self.terminal_size = TerminalSize { width, height };
+        // The last line is the command line.
+        self.windows.set_area(width, height.saturating_sub(1));
+        self.fit_cursor_in_window();
+    }
+
+    pub fn windows(&self) -> &WindowLayout {
+        &self.windows
+    }
+
+    /// The buffer shown in window `id`, its name and whether it is modified.
+    pub fn window_buffer(&self, id: usize) -> (&Buffer, String, bool) {
+        let number = self.windows.window(id).buffer_number;
+        let current = self.buffers.current();
+        if id == self.windows.current_id() || number == current.number {
+            return (&self.buffer, current.name(), self.is_dirty);
+        }
+        self.buffers
+            .entries()
+            .iter()
+            .find(|entry| entry.number == number)
+            .and_then(|entry| {
+                let state = entry.hidden_state.as_ref()?;
+                Some((&state.buffer, entry.name(), state.is_dirty))
+            })
+            .unwrap_or((&self.buffer, current.name(), self.is_dirty))
+    }
+
+    /// What the status line and the ruler of window `id` show.
+    pub fn window_status(&self, id: usize) -> WindowStatus {
+        let (buffer, name, modified) = self.window_buffer(id);
+        let window = self.windows.window(id);
+        let (cursor, top_row) = if id == self.windows.current_id() {
+            (self.cursor_position_in_buffer, self.window_position_in_buffer.row)
+        } else {
+            (
+                window.cursor_data.cursor_position_in_buffer,
+                window.cursor_data.window_position_in_buffer.row,
+            )
+        };
+        // The last line that fits in the window, wrapped or not.
+        let width = self.text_width(id);
+        let mut height = self.windows.text_height(id) as usize;
+        let mut bottom_row = top_row;
+        for (row, line) in (top_row..).zip(buffer.lines_at(top_row)) {
+            let line_height = if window.options.wrap {
+                get_line_height(line.chars(), width, self.options.tab_stop)
+            } else {
+                1
+            };
+            if line_height > height {
+                break;
+            }
+            height -= line_height;
+            bottom_row = row;
+        }
+        WindowStatus {
+            name,
+            buffer_number: window.buffer_number,
+            modified,
+            read_only: buffer.read_only,
+            line: buffer.get_line(cursor.row).map_or(String::new(), |line| line.to_string()),
+            row: cursor.row,
+            col: cursor.col,
+            tab_stop: self.options.tab_stop,
+            line_count: buffer.len_lines(),
+            top_row,
+            bottom_row,
+        }
+    }
+
+    /// The mode shown on the command line when there is no message.
+    pub fn mode_message(&self) -> Option<String> {
+        let mode = match self.mode {
+            Mode::Insert if self.options.show_mode => "-- INSERT --",
+            _ => "",
+        };
+        let recording = self
+            .recording
+            .as_ref()
+            .map_or(String::new(), |recording| format!("recording @{}", recording.register));
+        let message = mode.to_string() + &recording;
+        (!message.is_empty()).then_some(message)
+    }
+
+    /// Starts recording the keys typed into `register`, like `qa`.
+    pub fn start_recording(&mut self, register: char) -> GenericResult<()> {
+        if !(register.is_ascii_alphanumeric() || register == '"') {
+            return Err(format!("E354: Invalid register name: '{}'", register).into());
+        }
+        self.recording = Some(Recording {
+            register,
+            keys: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Adds a key typed to the macro being recorded, if any.
+    pub fn record_key(&mut self, key: KeyData) {
+        if let Some(recording) = &mut self.recording {
+            recording.keys.push(key);
+        }
+    }
+
+    /// Stops recording at the `q` just typed, which is left out, and stores
+    /// the keys in the register as text.
+    pub fn stop_recording(&mut self) {
+        if let Some(mut recording) = self.recording.take() {
+            recording.keys.pop();
+            self.registers
+                .record(recording.register, &keys_to_text(&recording.keys));
+        }
+    }
+
+    /// Executes the keys in `register` `count` times, like `@a`, by putting
+    /// them in the typeahead as if typed. `@@` executes the register last
+    /// executed and `@:` the last ex command.
+    pub fn execute_register(&mut self, register: char, count: usize) -> GenericResult<()> {
+        let register = match register {
+            '@' => self
+                .last_executed_register
+                .ok_or("E748: No previously used register")?,
+            register => register,
+        };
+        if register == ':' {
+            let ex_command = self
+                .last_ex_command
+                .clone()
+                .ok_or("E30: No previous command line")?;
+            self.last_executed_register = Some(register);
+            for _ in 0..count {
+                self.execute_ex_command(ex_command.clone())?;
+            }
+            return Ok(());
+        }
+        if !(register.is_ascii_alphanumeric() || matches!(register, '"' | '-')) {
+            return Err(format!("E354: Invalid register name: '{}'", register).into());
+        }
+        self.last_executed_register = Some(register);
+        let Some(text) = self.registers.get(register).map(|register| register.text.clone()) else {
+            return Ok(());
+        };
+        let keys = text_to_keys(&text);
+        for _ in 0..count {
+            for key in keys.iter().rev() {
+                self.typeahead.push_front(TypeaheadKey {
+                    key: KeyEvent::new(key.key_code, key.modifiers),
+                    remap: true,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the current window in two, like `:split` and `:vsplit`. The
+    /// new window shows the same buffer and becomes the current one.
+    pub fn split_window(&mut self, vertical: bool) -> GenericResult<()> {
+        self.leave_window();
+        let cursor_data = self.snapshot_cursor_data();
+        self.windows.split(vertical, cursor_data)?;
+        self.fit_cursor_in_window();
+        Ok(())
+    }
+
+    /// Closes the current window, like `:close`. Its buffer stays loaded.
+    pub fn close_window(&mut self) -> GenericResult<()> {
+        let next = self.windows.close(self.windows.current_id())?;
+        self.load_window(next);
+        Ok(())
+    }
+
+    /// Closes all windows but the current one, like `:only`.
+    pub fn close_other_windows(&mut self) {
+        self.windows.only();
+        self.fit_cursor_in_window();
+    }
+
+    /// Makes the current window `delta` lines taller, like `Ctrl-W +`.
+    pub fn resize_window(&mut self, delta: i32) {
+        self.windows.resize(delta);
+        self.fit_cursor_in_window();
+    }
+
+    /// Makes all windows (almost) the same size, like `Ctrl-W =`.
+    pub fn equalize_windows(&mut self) {
+        self.windows.equalize();
+        self.fit_cursor_in_window();
+    }
+
+    /// Goes to the window in `direction`, like `Ctrl-W h/j/k/l`.
+    pub fn move_to_window(&mut self, direction: Direction) {
+        let position = self.cursor_position_on_screen;
+        let col = position.col.saturating_sub(self.window_position_in_buffer.col as u16);
+        if let Some(id) = self.windows.neighbour(direction, position.row, col) {
+            self.enter_window(id);
+        }
+    }
+
+    /// Goes to the next window, like `Ctrl-W w`.
+    pub fn move_to_next_window(&mut self) {
+        self.enter_window(self.windows.next());
+    }
+
+    fn enter_window(&mut self, id: usize) {
+        if id != self.windows.current_id() {
+            self.leave_window();
+            self.windows.set_current(id);
+            self.load_window(id);
+        }
+    }
+
+    // Saves the state of the current window before another one becomes current.
+    fn leave_window(&mut self) {
+        let buffer_number = self.buffers.current().number;
+        let cursor_data = self.snapshot_cursor_data();
+        let window = self.windows.window_mut(self.windows.current_id());
+        window.buffer_number = buffer_number;
+        window.cursor_data = cursor_data;
+    }
+
+    // Makes the buffer and the cursor of window `id` current.
+    fn load_window(&mut self, id: usize) {
+        let window = self.windows.window(id);
+        let cursor_data = window.cursor_data;
+        if let Some(index) = self.buffers.position_by_number(window.buffer_number) {
+            self.swap_buffer(index);
+        }
+        self.restore_cursor_data(cursor_data);
+        self.fit_cursor_in_window();
+    }
+
+    /// Scrolls the current window so the cursor is visible and recomputes its
+    /// position on the screen, after the window or the buffer changed under it.
+    pub fn fit_cursor_in_window(&mut self) {
+        let width = self.content_width().max(1);
+        let height = self.content_height().max(1) as usize;
+        let wraps = self.wraps();
+        let tab_stop = self.options.tab_stop;
+        let cursor = &mut self.cursor_position_in_buffer;
+        cursor.row = cursor.row.min(self.buffer.len_lines().saturating_sub(1));
+        cursor.col = cursor.col.min(self.buffer.line_len(cursor.row));
+
+        let line_height = |row: usize| match self.buffer.get_line(row) {
+            Some(line) if wraps => get_line_height(line.chars(), width, tab_stop),
+            _ => 1,
+        };
+        // The screen lines above the cursor in its own line, and its column.
+        let (rows_in_line, col) = match self.buffer.get_line(cursor.row) {
+            Some(line) if wraps => {
+                get_wrapped_position(line.chars().take(cursor.col), width, tab_stop)
+            }
+            Some(line) => (0, get_line_width(line.chars().take(cursor.col), tab_stop) as u16),
+            None => (0, 0),
+        };
+        let top = &mut self.window_position_in_buffer.row;
+        *top = (*top).min(cursor.row);
+        let mut rows: usize = (*top..cursor.row).map(line_height).sum::<usize>() + rows_in_line;
+        while rows >= height && *top < cursor.row {
+            rows -= line_height(*top);
+            *top += 1;
+        }
+        self.cursor_position_on_screen = CursorPositionOnScreen {
+            row: rows.min(height - 1) as u16,
+            col,
+        };
+    }
+
+    /// Whether the current window wraps long lines, like vim's 'wrap'.
+    pub fn wraps(&self) -> bool {
+        self.window_options().wrap
+    }
+
+    /// The number of screen lines `line` takes in the current window.
+    pub fn line_height<I: IntoIterator<Item = char>>(&self, line: I) -> usize {
+        if self.wraps() {
+            get_line_height(line, self.content_width(), self.options.tab_stop)
+        } else {
+            1
+        }
+    }
+
+    /// Returns the columns the character at `col` of the cursor line takes.
+    pub fn char_width_in_line(&self, col: usize) -> u16 {
+        let row = self.cursor_position_in_buffer.row;
+        match self.buffer.get_char(row, col) {
+            // Only a tab depends on where it is.
+            Some('\t') => {
+                let line_col = get_line_width(self.buffer.line(row).chars().take(col), self.options.tab_stop);
+                get_char_width_at('\t', line_col, self.options.tab_stop)
+            }
+            Some(c) => get_char_width(c),
+            None => 0,
+        }
+    }
+
+    /// Moves the cursor on the screen right over the character at `col` of
+    /// the cursor line.
+    pub fn advance_cursor_over(&mut self, col: usize) {
+        let width = self.char_width_in_line(col);
+        if self.buffer.get_char(self.cursor_position_in_buffer.row, col) == Some('\t') {
+            // A tab is made of single cells, which wrap one by one.
+            for _ in 0..width {
+                self.advance_cursor_on_screen(1);
+            }
+        } else {
+            self.advance_cursor_on_screen(width);
+        }
+    }
+
+    /// Moves the cursor on the screen left over the character at `col` of
+    /// the cursor line.
+    pub fn retreat_cursor_over(&mut self, col: usize) {
+        let width = self.char_width_in_line(col);
+        if self.buffer.get_char(self.cursor_position_in_buffer.row, col) == Some('\t') {
+            for _ in 0..width {
+                self.retreat_cursor_on_screen(1);
+            }
+        } else {
+            self.retreat_cursor_on_screen(width);
+        }
+    }
+
+    // Moves the cursor on the screen right over a character `width` columns
+    // wide, to the next screen line if the line wraps there.
+    fn advance_cursor_on_screen(&mut self, width: u16) {
+        self.cursor_position_on_screen.col += width;
+        if self.wraps() && self.cursor_position_on_screen.col >= self.content_width() {
+            self.cursor_position_on_screen.col = 0;
+            if self.cursor_position_on_screen.row < self.content_height() - 1 {
+                self.cursor_position_on_screen.row += 1;
+            } else {
+                self.window_position_in_buffer.row += 1;
+            }
+        }
+    }
+
+    // Moves the cursor on the screen left over a character `width` columns
+    // wide, to the end of the previous screen line if the line wraps there.
+    fn retreat_cursor_on_screen(&mut self, width: u16) {
         if self.cursor_position_on_screen.col >= width {
-            self.cursor_position_on_screen.col = width - 1;
+            self.cursor_position_on_screen.col -= width;
+        } else {
+            if self.cursor_position_on_screen.row > 0 {
+                self.cursor_position_on_screen.row -= 1;
+            } else if self.window_position_in_buffer.row > 0 {
+                self.window_position_in_buffer.row -= 1;
+            }
+            self.cursor_position_on_screen.col = self.content_width() - width;
         }
     }
 
+    /// The screen columns of its line the cursor may be on without scrolling
+    /// the window sideways, first and last.
+    pub fn cursor_columns(&self) -> (usize, usize) {
+        let width = self.content_width().max(1) as usize;
+        let left = self.window_position_in_buffer.col;
+        let off = self.options.side_scroll_off.min((width - 1) / 2);
+        // Nothing is left of the first column to be kept in view.
+        let first = if left == 0 { 0 } else { left + off };
+        (first, left + width - 1 - off)
+    }
+
+    // Scrolls the current window sideways so that the cursor is in
+    // `cursor_columns`, by 'sidescroll' columns at least. A long way or a
+    // 'sidescroll' of 0 puts the cursor in the middle.
+    fn scroll_horizontally(&mut self) {
+        if self.wraps() {
+            self.window_position_in_buffer.col = 0;
+            return;
+        }
+        let width = self.content_width().max(1) as usize;
+        let off = self.options.side_scroll_off.min((width - 1) / 2);
+        let col = self.cursor_position_on_screen.col as usize;
+        let left = self.window_position_in_buffer.col;
+        let (first, last) = self.cursor_columns();
+        let needed = if col < first {
+            col.saturating_sub(off)
+        } else if col > last {
+            col + off + 1 - width
+        } else {
+            return;
+        };
+        let step = self.options.side_scroll;
+        self.window_position_in_buffer.col = if step == 0 || needed.abs_diff(left) > width / 2 {
+            col.saturating_sub(width / 2)
+        } else if needed > left {
+            (left + step).max(needed).min(col.saturating_sub(off))
+        } else {
+            left.saturating_sub(step).min(needed).max((col + off + 1).saturating_sub(width))
+        };
+    }
+
     pub fn set_command_mode(&mut self) {
         match self.mode {
             Mode::Command => {}
@@ -139,6 +1137,9 @@ impl Editor {
             }
             Mode::Insert => {
                 self.mode = Mode::Command;
+                if let Some(last_change) = &mut self.last_change {
+                    last_change.text = self.last_input_string.clone();
+                }
                 self.convert_repetitive_command_history_to_commands_history();
                 self.status_line = "".to_string();
             }
@@ -157,7 +1158,7 @@ impl Editor {
                 self.command_history.push(vec![last_executed_command]);
             } else if count >= 2 {
                 last_executed_command.command_data.count = 1;
-                let command_data: CommandData = last_executed_command.command_data.clone();
+                let command_data: CommandData = last_executed_command.command_data;
                 self.do_repetitive_command(
                     count,
                     command_data,
@@ -195,7 +1196,7 @@ impl Editor {
         }
         if let Some(command) = command_opt {
             command_series.push(ExecutedCommand {
-                command_data: command_data.clone(),
+                command_data,
                 command,
             });
             info!("command_series.len(): {}", command_series.len());
@@ -212,7 +1213,7 @@ impl Editor {
             }
             Mode::Command => {
                 self.mode = Mode::Insert;
-                self.status_line = "-- INSERT --".to_string();
+                self.status_line = "".to_string();
                 self.last_input_string = "".to_string();
             }
             Mode::Insert => {}
@@ -231,6 +1232,19 @@ impl Editor {
         self.mode == Mode::ExCommand
     }
 
+    /// Returns the mode whose mappings apply to the next key; none do while
+    /// the pager is shown.
+    pub fn map_mode(&self) -> Option<MapMode> {
+        if self.pager.is_some() {
+            return None;
+        }
+        Some(match self.mode {
+            Mode::Command => MapMode::Normal,
+            Mode::Insert => MapMode::Insert,
+            Mode::ExCommand => MapMode::CommandLine,
+        })
+    }
+
     pub fn set_ex_command_mode(&mut self) {
         self.mode = Mode::ExCommand;
         self.status_line = ":".to_string();
@@ -242,6 +1256,9 @@ impl Editor {
 
     pub fn execute_ex_command(&mut self, ex_command_str: String) -> GenericResult<()> {
         let ex_command_str = ex_command_str.trim();
+        if !ex_command_str.is_empty() {
+            self.last_ex_command = Some(ex_command_str.to_string());
+        }
         let mut parser = Parser::new(ex_command_str);
         let result = parser.parse();
         if let Err(e) = result {
@@ -251,12 +1268,17 @@ impl Editor {
             return Ok(());
         }
         let mut command = result.unwrap();
-        command.execute(self)?;
+        if let Err(e) = command.execute(self) {
+            info!("Error: {}", e);
+            self.status_line = e.to_string();
+            self.ex_command_data = "".to_string();
+            return Ok(());
+        }
         let command_data = CommandData {
             count: 1,
-            key_code: crossterm::event::KeyCode::Char(':'),
-            modifiers: crossterm::event::KeyModifiers::NONE,
+            keys: parse_keys(":"),
             range: None,
+            register: None,
         };
         self.command_history.push(vec![ExecutedCommand {
             command_data,
@@ -266,7 +1288,28 @@ impl Editor {
         Ok(())
     }
 
-    pub fn append_ex_command(&mut self, key_data: crate::command::compose::KeyData) {
+    /// Shows the output of a command: one line on the command line, more in
+    /// the pager.
+    pub fn show_lines(&mut self, lines: Vec<String>) {
+        if lines.len() <= 1 {
+            self.status_line = lines.concat();
+            return;
+        }
+        self.status_line.clear();
+        self.pager = Some(Pager::new(
+            &lines,
+            self.terminal_size.width as usize,
+            self.options.tab_stop,
+            self.pager_height(),
+        ));
+    }
+
+    /// Returns the rows the pager shows output in, above its prompt.
+    pub fn pager_height(&self) -> usize {
+        self.terminal_size.height.saturating_sub(1) as usize
+    }
+
+    pub fn append_ex_command(&mut self,key_data: crate::command::compose::KeyData) {
         if let crate::command::compose::KeyData {
             key_code: crossterm::event::KeyCode::Char(c),
             ..
@@ -302,7 +1345,22 @@ impl Editor {
 
     pub fn execute_command(&mut self, command_data: CommandData) -> GenericResult<()> {
         let mut command = command_factory(&command_data);
-        if !command.is_modeful() && command.is_reusable() {
+        let last_change = command.is_undoable().then(|| LastChange {
+            command_data: command_data.clone(),
+            text: String::new(),
+        });
+        if command.takes_count() {
+            command.execute(self)?;
+            if command.is_undoable() {
+                self.command_history.push(vec![ExecutedCommand {
+                    command_data: CommandData {
+                        count: 1,
+                        ..command_data
+                    },
+                    command,
+                }]);
+            }
+        } else if !command.is_modeful() && command.is_reusable() {
             for _ in 0..command_data.count {
                 command.execute(self)?;
             }
@@ -328,7 +1386,7 @@ impl Editor {
                     });
                 }
             }
-            if command_chunk.len() > 0 {
+            if !command_chunk.is_empty() {
                 self.command_history.push(command_chunk);
             }
         } else {
@@ -340,6 +1398,9 @@ impl Editor {
                 }]);
             }
         }
+        if last_change.is_some() {
+            self.last_change = last_change;
+        }
         Ok(())
     }
 
@@ -356,18 +1417,66 @@ impl Editor {
         }
     }
 
-    pub fn render(self: &mut Editor, stdout: &mut std::io::Stdout) -> GenericResult<()> {
-        render(self, stdout)
+    pub fn render(self: &mut Editor, backend: &mut impl Backend) -> GenericResult<()> {
+        // The line number column widens as lines are added, which rewraps the text.
+        let text_width = self.content_width();
+        if text_width != self.rendered_text_width {
+            self.fit_cursor_in_window();
+            self.rendered_text_width = text_width;
+        }
+        self.scroll_horizontally();
+        render(self, backend)
     }
 
+    /// The number of lines of text the current window shows.
     pub fn content_height(&self) -> u16 {
-        self.terminal_size.height - 1
+        self.windows.text_height(self.windows.current_id())
+    }
+
+    /// The number of columns of text the current window shows, right of its
+    /// line numbers.
+    pub fn content_width(&self) -> u16 {
+        self.text_width(self.windows.current_id())
+    }
+
+    pub fn text_width(&self, id: usize) -> u16 {
+        self.windows.rect(id).width - self.number_width(id)
+    }
+
+    /// The width of the line number column of window `id`. At least one
+    /// column is left for the text.
+    pub fn number_width(&self, id: usize) -> u16 {
+        let (buffer, _, _) = self.window_buffer(id);
+        let width = self.windows.window(id).options.number_width(
+            buffer.len_lines(),
+            self.windows.text_height(id),
+        );
+        width.min(self.windows.rect(id).width.saturating_sub(1))
+    }
+
+    pub fn last_status(&self) -> usize {
+        self.windows.last_status()
+    }
+
+    /// Sets 'laststatus'; with 2 a lone window gets a status line.
+    pub fn set_last_status(&mut self, last_status: usize) {
+        self.windows.set_last_status(last_status);
+        self.fit_cursor_in_window();
+    }
+
+    pub fn window_options(&self) -> WindowOptions {
+        self.windows.window(self.windows.current_id()).options
+    }
+
+    /// Changes the options of the current window.
+    pub fn set_window_options(&mut self, options: WindowOptions) {
+        let id = self.windows.current_id();
+        self.windows.window_mut(id).options = options;
+        self.fit_cursor_in_window();
     }
 
     pub fn display_visual_bell(&mut self) -> GenericResult<()> {
-        let mut stdout = std::io::stdout();
-        stdout.write_all(b"\x07")?;
-        stdout.flush()?;
+        self.ring_bell = true;
         Ok(())
     }
 
@@ -379,11 +1488,7 @@ impl Editor {
     }
 
     pub fn get_num_of_current_line_chars(&self) -> usize {
-        self.buffer
-            .lines
-            .get(self.cursor_position_in_buffer.row)
-            .map(|line| line.chars().count())
-            .unwrap_or(0)
+        self.buffer.line_len(self.cursor_position_in_buffer.row)
     }
 
     pub fn insert_char(&mut self, c: char) -> GenericResult<()> {
@@ -393,55 +1498,29 @@ impl Editor {
             c,
         )?;
         self.last_input_string.push(c);
-        let char_width = crate::util::get_char_width(c);
+        self.advance_cursor_over(self.cursor_position_in_buffer.col);
         self.cursor_position_in_buffer.col += 1;
-        self.cursor_position_on_screen.col += char_width;
-        if self.cursor_position_on_screen.col >= self.terminal_size.width {
-            self.cursor_position_on_screen.col = 0;
-            if self.cursor_position_on_screen.row < self.content_height() - 1 {
-                self.cursor_position_on_screen.row += 1;
-            } else {
-                self.window_position_in_buffer.row += 1;
-            }
-        }
 
         Ok(())
     }
 
     pub fn backward_delete_char(&mut self) -> GenericResult<()> {
-        if self.cursor_position_in_buffer.col > 0 && self.last_input_string.len() > 0 {
+        if self.cursor_position_in_buffer.col > 0 && !self.last_input_string.is_empty() {
+            self.cursor_position_in_buffer.col -= 1;
+            self.retreat_cursor_over(self.cursor_position_in_buffer.col);
             self.buffer.delete_char(
                 self.cursor_position_in_buffer.row,
-                self.cursor_position_in_buffer.col - 1,
+                self.cursor_position_in_buffer.col,
             )?;
             self.last_input_string.pop();
-            self.cursor_position_in_buffer.col -= 1;
-            let char = self.buffer.get_char(
-                self.cursor_position_in_buffer.row,
-                self.cursor_position_in_buffer.col,
-            );
-            if let Some(char) = char {
-                let char_width = crate::util::get_char_width(char);
-                self.cursor_position_on_screen.col -= char_width;
-                if self.cursor_position_on_screen.col >= self.terminal_size.width {
-                    self.cursor_position_on_screen.col = self.terminal_size.width - 1;
-                    if self.cursor_position_on_screen.row > 0 {
-                        self.cursor_position_on_screen.row -= 1;
-                    } else if self.window_position_in_buffer.row > 0 {
-                        self.window_position_in_buffer.row -= 1;
-                    }
-                }
-            }
-        } else if self.cursor_position_in_buffer.col == 0 && self.last_input_string.len() > 0 {
+        } else if self.cursor_position_in_buffer.col == 0 && !self.last_input_string.is_empty() {
             self.last_input_string.pop();
             if self.cursor_position_in_buffer.row > 0 {
-                let rest_of_line = self.buffer.lines[self.cursor_position_in_buffer.row].clone();
-                self.buffer.lines.remove(self.cursor_position_in_buffer.row);
                 let mut previous_line = crate::command::commands::move_cursor::PreviousLine {};
                 previous_line.execute(self)?;
                 let mut move_end_of_line = crate::command::commands::move_cursor::MoveEndOfLine {};
                 move_end_of_line.execute(self)?;
-                self.buffer.lines[self.cursor_position_in_buffer.row] += &rest_of_line;
+                self.buffer.join_lines(self.cursor_position_in_buffer.row);
                 let mut forward_char = crate::command::commands::move_cursor::ForwardChar {};
                 forward_char.execute(self)?;
             }
@@ -450,18 +1529,10 @@ impl Editor {
     }
 
     pub fn append_new_line(&mut self) -> GenericResult<()> {
-        let rest_of_line = self.buffer.lines[self.cursor_position_in_buffer.row]
-            .chars()
-            .skip(self.cursor_position_in_buffer.col)
-            .collect::<String>();
-        let new_line = self.buffer.lines[self.cursor_position_in_buffer.row]
-            .chars()
-            .take(self.cursor_position_in_buffer.col)
-            .collect::<String>();
-        self.buffer.lines[self.cursor_position_in_buffer.row] = new_line;
-        self.buffer
-            .lines
-            .insert(self.cursor_position_in_buffer.row + 1, rest_of_line);
+        self.buffer.split_line(
+            self.cursor_position_in_buffer.row,
+            self.cursor_position_in_buffer.col,
+        );
         self.cursor_position_in_buffer.row += 1;
         self.cursor_position_in_buffer.col = 0;
         if self.cursor_position_on_screen.row < self.content_height() - 1 {
@@ -474,7 +1545,7 @@ impl Editor {
         Ok(())
     }
 
-    pub fn get_line_number_from(&mut self, line_address: &LineAddressType) -> usize {
+    pub fn get_line_number_from(&mut self, line_address: &LineAddressType) -> GenericResult<usize> {
         let line_number: isize = match line_address {
             crate::data::LineAddressType::Absolute(SimpleLineAddressType::LineNumber(n)) => {
                 let input = *n as isize;
@@ -489,30 +1560,37 @@ impl Editor {
             },
             crate::data::LineAddressType::Absolute(SimpleLineAddressType::FirstLine) => 0,
             crate::data::LineAddressType::Absolute(SimpleLineAddressType::LastLine) => {
-                self.buffer.lines.len().saturating_sub(1) as isize
+                self.buffer.len_lines().saturating_sub(1) as isize
             },
             crate::data::LineAddressType::Absolute(SimpleLineAddressType::AllLines) => {
-                self.buffer.lines.len().saturating_sub(1) as isize
+                self.buffer.len_lines().saturating_sub(1) as isize
             },
-            crate::data::LineAddressType::Absolute(SimpleLineAddressType::Pattern(_)) => {
-                // TODO: Implement
-                unimplemented!()
+            crate::data::LineAddressType::Absolute(SimpleLineAddressType::Pattern(pattern)) => {
+                self.find_line(&pattern.pattern)? as isize
             },
             // Relative line addressing disabled (variant removed).
         };
 
-        line_number as usize
+        Ok(line_number as usize)
+    }
+
+    // Searches for `pattern` from the line after the cursor, wrapping around
+    // the end of the buffer, like `/pattern/` addresses.
+    fn find_line(&self, pattern: &str) -> GenericResult<usize> {
+        let re = Regex::new(pattern).map_err(|e| e.to_string())?;
+        let len = self.buffer.len_lines();
+        let start = self.cursor_position_in_buffer.row + 1;
+        (0..len)
+            .map(|offset| (start + offset) % len)
+            .find(|&row| re.is_match(&self.buffer.line(row).to_string()))
+            .ok_or_else(|| format!("E486: Pattern not found: {}", pattern).into())
     }
 }
 
-impl Drop for Editor {
-    fn drop(&mut self) {
-        info!("Drop Editor");
-        let mut stdout = std::io::stdout();
-        terminal::disable_raw_mode().unwrap();
-        stdout.execute(terminal::Clear(ClearType::All)).unwrap();
-        stdout.execute(terminal::LeaveAlternateScreen).unwrap();
-        stdout.flush().unwrap();
+fn is_writable(file_path: &PathBuf) -> bool {
+    match fs::metadata(file_path) {
+        Ok(metadata) if metadata.permissions().readonly() => false,
+        _ => fs::OpenOptions::new().write(true).open(file_path).is_ok(),
     }
 }
 
@@ -525,39 +1603,241 @@ mod tests {
     #[test]
     fn test_editor_get_line_number_from_absolute() {
         let mut editor = Editor::new();
-        editor.buffer.lines = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        editor.buffer = Buffer::from_lines(&["a", "b", "c"]);
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(0))),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(0))).unwrap(),
             0
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(1))),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(1))).unwrap(),
             0
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(2))),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(2))).unwrap(),
             1
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(3))),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LineNumber(3))).unwrap(),
             2
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::CurrentLine)),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::CurrentLine)).unwrap(),
             0
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::FirstLine)),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::FirstLine)).unwrap(),
             0
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LastLine)),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::LastLine)).unwrap(),
             2
         );
         assert_eq!(
-            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::AllLines)),
+            editor.get_line_number_from(&LineAddressType::Absolute(SimpleLineAddressType::AllLines)).unwrap(),
             2
         );
     }
 
+    #[test]
+    fn test_editor_get_line_number_from_pattern() {
+        let mut editor = Editor::new();
+        editor.buffer = Buffer::from_lines(&["foo", "bar", "foo"]);
+        let address = |pattern: &str| {
+            LineAddressType::Absolute(SimpleLineAddressType::Pattern(crate::data::Pattern {
+                pattern: pattern.to_string(),
+            }))
+        };
+        // the search starts after the cursor line and wraps around
+        assert_eq!(editor.get_line_number_from(&address("foo")).unwrap(), 2);
+        editor.cursor_position_in_buffer.row = 2;
+        assert_eq!(editor.get_line_number_from(&address("fo+")).unwrap(), 0);
+        assert_eq!(
+            editor.get_line_number_from(&address("baz")).unwrap_err().to_string(),
+            "E486: Pattern not found: baz"
+        );
+    }
+
+    #[test]
+    fn test_editor_open_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        let mut editor = Editor::new();
+        editor.open_file(&path).unwrap();
+        assert!(editor.buffer.is_empty());
+        assert!(editor.status_line.ends_with("[New File]"));
+
+        editor.buffer.insert(0, 0, "hello").unwrap();
+        editor.save_file(false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_editor_open_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut editor = Editor::new();
+        let result = editor.open_file(dir.path());
+        assert!(result.unwrap_err().to_string().ends_with("is a directory"));
+    }
+
+    #[test]
+    fn test_editor_recover_from_swap_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "abc\n").unwrap();
+
+        let mut crashed = Editor::new();
+        crashed.open_file(&path).unwrap();
+        crashed.buffer.insert(0, 0, "unsaved ").unwrap();
+        crashed.is_dirty = true;
+        crashed.record_key_for_swap();
+        crashed.update_swap_file();
+        let swap_path = dir.path().join(".a.txt.swp");
+        assert!(swap_path.exists());
+
+        let mut editor = Editor::new();
+        editor.open_file(&path).unwrap();
+        assert!(editor.status_line.starts_with("E325: ATTENTION"));
+        assert_eq!(editor.buffer, ["abc"][..]);
+        editor.recover().unwrap();
+        assert_eq!(editor.buffer, ["unsaved abc"][..]);
+        assert!(editor.is_dirty);
+
+        let swap_contents = fs::read_to_string(&swap_path).unwrap();
+        editor.update_swap_file();
+        let own_swap_path = dir.path().join(".a.txt.swo");
+        assert!(fs::read_to_string(&own_swap_path).unwrap().ends_with("unsaved abc\n"));
+
+        editor.save_file(false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "unsaved abc\n");
+        assert!(!own_swap_path.exists());
+        assert_eq!(fs::read_to_string(&swap_path).unwrap(), swap_contents);
+    }
+
+    #[test]
+    fn test_editor_swap_file_of_read_only_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ro.txt");
+        fs::write(&path, "abc\n").unwrap();
+        fs::write(dir.path().join(".ro.txt.swp"), "").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(&path).unwrap();
+        assert!(editor.status_line.contains("[readonly]"));
+        assert!(editor.status_line.contains("E325: ATTENTION"));
+    }
+
+    #[test]
+    fn test_editor_detects_external_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "abc\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(&path).unwrap();
+        assert!(!editor.check_file_changed());
+
+        fs::write(&path, "changed elsewhere\n").unwrap();
+        assert!(editor.check_file_changed());
+        assert!(editor.status_line.starts_with("W11:"));
+        // The same change is reported only once.
+        assert!(!editor.check_file_changed());
+
+        editor.buffer.insert(0, 0, "x").unwrap();
+        editor.is_dirty = true;
+        assert!(editor.save_file(false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "changed elsewhere\n");
+
+        editor.reload_file().unwrap();
+        assert_eq!(editor.buffer, ["changed elsewhere"][..]);
+        assert!(!editor.is_dirty);
+        editor.buffer.insert(0, 0, "x").unwrap();
+        editor.save_file(false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xchanged elsewhere\n");
+
+        fs::write(&path, "again\n").unwrap();
+        editor.save_file(true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xchanged elsewhere\n");
+    }
+
+    #[test]
+    fn test_editor_read_only_file_needs_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ro.txt");
+        fs::write(&path, "abc\n").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(&path).unwrap();
+        assert!(editor.buffer.read_only);
+        assert!(editor.status_line.ends_with("[readonly]"));
+
+        editor.buffer.insert(0, 0, "x").unwrap();
+        assert!(editor.save_file(false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "abc\n");
+        editor.save_file(true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xabc\n");
+    }
+
+    #[test]
+    fn test_editor_keeps_hidden_buffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "a\n").unwrap();
+        fs::write(&b, "b\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(&a).unwrap();
+        editor.buffer.insert(0, 0, "x").unwrap();
+        editor.is_dirty = true;
+        editor.edit_file(&b).unwrap();
+        assert_eq!(editor.buffer, ["b"][..]);
+        assert_eq!(editor.buffer_list_lines().len(), 2);
+        assert!(editor.buffer_list_lines()[0].contains("#h +"));
+        assert_eq!(editor.modified_hidden_buffer(), Some(a.to_string_lossy().to_string()));
+
+        editor.switch_to_alternate_buffer().unwrap();
+        assert_eq!(editor.buffer, ["xa"][..]);
+        assert!(editor.is_dirty);
+        assert!(editor.is_current_file(&a));
+
+        assert!(editor.delete_buffer(Some(1), false).is_err());
+        editor.delete_buffer(Some(1), true).unwrap();
+        assert_eq!(editor.buffer, ["b"][..]);
+        assert_eq!(editor.buffer_list_lines().len(), 1);
+        assert!(editor.switch_to_alternate_buffer().is_err());
+        assert!(editor.switch_to_buffer_number(1).is_err());
+    }
+
+    #[test]
+    fn test_editor_windows_share_buffer() {
+        let mut editor = Editor::new();
+        editor.resize_terminal(80, 24);
+        editor.buffer = Buffer::from_lines(&["a", "b", "c"]);
+        editor.split_window(false).unwrap();
+        assert_eq!(editor.content_height(), 10);
+        GoToLineCommand {
+            line_address: LineAddressType::Absolute(SimpleLineAddressType::LineNumber(3)),
+        }
+        .execute(&mut editor)
+        .unwrap();
+
+        editor.move_to_window(Direction::Down);
+        assert_eq!(editor.cursor_position_in_buffer.row, 0);
+        assert_eq!(editor.content_height(), 11);
+        editor.buffer.remove_line(2);
+
+        // The cursor of the other window is kept inside the shorter buffer.
+        editor.move_to_next_window();
+        assert_eq!(editor.cursor_position_in_buffer.row, 1);
+        assert_eq!(editor.cursor_position_on_screen.row, 1);
+        editor.close_window().unwrap();
+        assert_eq!(editor.content_height(), 23);
+        assert!(editor.close_window().is_err());
+    }
 }
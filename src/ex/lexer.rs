@@ -85,14 +85,34 @@ impl Lexer {
                     lexeme: ch.to_string(),
                 }],
                 '/' => vec![self.read_pattern()],
-                '!' | '#' | '=' | '.' | '-' | '+' | '*' | '%' | '$' | '^' => vec![Token {
+                '!' | '#' | '=' | '.' | '-' | '+' | '*' | '%' | '$' | '^' | '|' => vec![Token {
                     token_type: TokenType::Symbol,
                     lexeme: ch.to_string(),
                 }],
                 '0'..='9' => vec![self.read_number()],
-                's' => self.read_substitution_command(),
-                'r' | 'w' => self.file_command(),
-                _ if ch.is_alphabetic() => vec![self.read_command()],
+                's' if self.peek_char() == Some('/') => self.read_substitution_command(),
+                'r' if !self.peek_char().is_some_and(char::is_alphabetic) => self.file_command(),
+                'w' if !self.peek_char().is_some_and(|c| c.is_alphabetic() && c != 'q') => {
+                    self.file_command()
+                }
+                _ if ch.is_alphabetic() => {
+                    let command = self.read_command();
+                    if command.lexeme == "set" || command.lexeme == "se" {
+                        let mut tokens = vec![command];
+                        tokens.append(&mut self.read_arguments(TokenType::Option));
+                        tokens
+                    } else if takes_map_arguments(&command.lexeme) {
+                        let mut tokens = vec![command];
+                        tokens.append(&mut self.read_map_arguments());
+                        tokens
+                    } else if takes_file_arguments(&command.lexeme) {
+                        let mut tokens = vec![command];
+                        tokens.append(&mut self.read_arguments(TokenType::Filename));
+                        tokens
+                    } else {
+                        vec![command]
+                    }
+                }
                 _ => vec![Token {
                     token_type: TokenType::Illegal,
                     lexeme: ch.to_string(),
@@ -293,7 +313,12 @@ impl Lexer {
                     file_command_status = FileCommandState::Command;
                 }
                 FileCommandState::Command => {
-                    if c.is_whitespace() {
+                    if c == '!' && tokens.len() == 1 {
+                        tokens.push(Token {
+                            token_type: TokenType::Symbol,
+                            lexeme: c.to_string(),
+                        });
+                    } else if c.is_whitespace() {
                         file_command_status = FileCommandState::Filename;
                     } else {
                         self.rewind_char();
@@ -318,6 +343,85 @@ impl Lexer {
         tokens
     }
 
+    // Reads an optional `!` right after the command name and then the
+    // whitespace-separated arguments of the command, like the options of `:set`.
+    // A backslash keeps the whitespace after it in the argument.
+    fn read_arguments(&mut self, token_type: TokenType) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.read_char();
+        if self.current_char == Some('!') {
+            tokens.push(Token {
+                token_type: TokenType::Symbol,
+                lexeme: "!".to_string(),
+            });
+            self.read_char();
+        }
+        loop {
+            self.skip_whitespace();
+            let mut lexeme = String::new();
+            while let Some(c) = self.current_char {
+                if c == '\\' && self.peek_char().is_some_and(char::is_whitespace) {
+                    self.read_char();
+                } else if c.is_whitespace() {
+                    break;
+                }
+                lexeme.push(self.current_char.unwrap());
+                self.read_char();
+            }
+            if lexeme.is_empty() {
+                break;
+            }
+            tokens.push(Token {
+                token_type: token_type.clone(),
+                lexeme,
+            });
+        }
+        tokens
+    }
+
+    // Reads an optional `!` right after the command name, the keys to map and
+    // the rest of the line up to a `|`, which is what they are mapped to.
+    fn read_map_arguments(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.read_char();
+        if self.current_char == Some('!') {
+            tokens.push(Token {
+                token_type: TokenType::Symbol,
+                lexeme: "!".to_string(),
+            });
+            self.read_char();
+        }
+        self.skip_whitespace();
+        let lhs = self.read_map_keys(char::is_whitespace);
+        self.skip_whitespace();
+        let rhs = self.read_map_keys(|_| false);
+        for lexeme in [lhs, rhs] {
+            if !lexeme.is_empty() {
+                tokens.push(Token {
+                    token_type: TokenType::Keys,
+                    lexeme,
+                });
+            }
+        }
+        // The `|` is read as the next token.
+        self.rewind_char();
+        tokens
+    }
+
+    // Reads keys up to `is_end` or a `|`, `\|` being a `|` of the keys like
+    // `<Bar>`.
+    fn read_map_keys(&mut self, is_end: fn(char) -> bool) -> String {
+        let mut keys = String::new();
+        while let Some(c) = self.current_char.filter(|&c| c != '|' && !is_end(c)) {
+            if c == '\\' && self.peek_char() == Some('|') {
+                self.read_char();
+            }
+            keys.push(self.current_char.unwrap());
+            self.read_char();
+        }
+        keys
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.current_char {
             if c.is_whitespace() {
@@ -329,6 +433,45 @@ impl Lexer {
     }
 }
 
+// Commands whose arguments are file names, like `:n a.txt b.txt` and `:e a.txt`.
+fn takes_file_arguments(command: &str) -> bool {
+    "next".starts_with(command)
+        || "edit".starts_with(command)
+        || "Next".starts_with(command)
+        || (command.len() >= 2
+            && ["wnext", "split", "vsplit", "last"]
+                .iter()
+                .any(|name| name.starts_with(command)))
+        || (command.len() >= 3
+            && ["rewind", "first"]
+                .iter()
+                .any(|name| name.starts_with(command)))
+        || (command.len() >= 4 && "previous".starts_with(command))
+        || (command.len() >= 4 && "colorscheme".starts_with(command))
+}
+
+// `:map` and the commands like it, with the fewest letters each is known by.
+pub const MAP_COMMANDS: [(&str, usize); 12] = [
+    ("map", 3),
+    ("nmap", 2),
+    ("imap", 2),
+    ("cmap", 2),
+    ("noremap", 2),
+    ("nnoremap", 2),
+    ("inoremap", 3),
+    ("cnoremap", 3),
+    ("unmap", 3),
+    ("nunmap", 3),
+    ("iunmap", 2),
+    ("cunmap", 2),
+];
+
+fn takes_map_arguments(command: &str) -> bool {
+    MAP_COMMANDS
+        .iter()
+        .any(|(name, min_len)| command.len() >= *min_len && name.starts_with(command))
+}
+
 pub fn tokenize(input: &str) -> Vec<Token> {
     let mut lexer = Lexer::new(input.to_string());
     let mut tokens = Vec::new();
@@ -609,4 +752,86 @@ mod tests {
         assert_eq!(tokens[4].lexeme, "p");
         assert_eq!(tokens[5].token_type, TokenType::EndOfInput);
     }
+
+    #[test]
+    fn test_tokenize_set_options() {
+        let input = ":set fileformat=dos fenc=shift_jis";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].token_type, TokenType::Command);
+        assert_eq!(tokens[1].lexeme, "set");
+        assert_eq!(tokens[2].token_type, TokenType::Option);
+        assert_eq!(tokens[2].lexeme, "fileformat=dos");
+        assert_eq!(tokens[3].token_type, TokenType::Option);
+        assert_eq!(tokens[3].lexeme, "fenc=shift_jis");
+        assert_eq!(tokens[4].token_type, TokenType::EndOfInput);
+    }
+
+    #[test]
+    fn test_tokenize_escaped_space() {
+        let input = ":set stl=%f\\ %m ru";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.len(), 5, "tokens: {:?}", tokens);
+        assert_eq!(tokens[2].lexeme, "stl=%f %m");
+        assert_eq!(tokens[3].lexeme, "ru");
+    }
+
+    #[test]
+    fn test_tokenize_next_with_files() {
+        let input = ":n! a.txt  b.txt";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[1].token_type, TokenType::Command);
+        assert_eq!(tokens[1].lexeme, "n");
+        assert_eq!(tokens[2].token_type, TokenType::Symbol);
+        assert_eq!(tokens[2].lexeme, "!");
+        assert_eq!(tokens[3].token_type, TokenType::Filename);
+        assert_eq!(tokens[3].lexeme, "a.txt");
+        assert_eq!(tokens[4].token_type, TokenType::Filename);
+        assert_eq!(tokens[4].lexeme, "b.txt");
+        assert_eq!(tokens[5].token_type, TokenType::EndOfInput);
+    }
+
+    #[test]
+    fn test_tokenize_recover() {
+        let input = ":recover!";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].token_type, TokenType::Command);
+        assert_eq!(tokens[1].lexeme, "recover");
+        assert_eq!(tokens[2].token_type, TokenType::Symbol);
+        assert_eq!(tokens[2].lexeme, "!");
+        assert_eq!(tokens[3].token_type, TokenType::EndOfInput);
+    }
+
+    #[test]
+    fn test_tokenize_force_write_file() {
+        let input = ":w! file.txt";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].token_type, TokenType::Command);
+        assert_eq!(tokens[1].lexeme, "w");
+        assert_eq!(tokens[2].token_type, TokenType::Symbol);
+        assert_eq!(tokens[2].lexeme, "!");
+        assert_eq!(tokens[3].token_type, TokenType::Filename);
+        assert_eq!(tokens[3].lexeme, "file.txt");
+        assert_eq!(tokens[4].token_type, TokenType::EndOfInput);
+    }
+
+    #[test]
+    fn test_tokenize_map() {
+        let tokens = tokenize(":map! ,w  :w<CR> \\| x<Bar>y | x ");
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, [":", "map", "!", ",w", ":w<CR> | x<Bar>y ", "|", "x", ""]);
+        assert_eq!(tokens[3].token_type, TokenType::Keys);
+        assert_eq!(tokens[4].token_type, TokenType::Keys);
+        assert_eq!(tokens[5].token_type, TokenType::Symbol);
+
+        let tokens = tokenize("map a|");
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["map", "a", "|", ""]);
+
+        let tokens = tokenize("nn");
+        assert_eq!(tokens.len(), 2);
+    }
 }
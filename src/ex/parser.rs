@@ -1,9 +1,17 @@
 use std::ops::BitOr;
 
 use crate::command::base::Command;
+use crate::command::commands::args::{self, ArgumentTarget};
+use crate::command::commands::buffers;
 use crate::command::commands::delete;
+use crate::command::commands::edit;
 use crate::command::commands::go_to_line;
+use crate::command::commands::map;
+use crate::command::commands::set;
+use crate::command::commands::colorscheme;
 use crate::command::commands::substitute;
+use crate::command::commands::window;
+use crate::command::commands::write;
 use crate::data::LineAddressType;
 use crate::data::LineRange;
 use crate::data::Pattern;
@@ -12,9 +20,11 @@ use crate::data::Token;
 use crate::data::TokenType;
 use crate::ex::lexer;
 use crate::generic_error::GenericError;
+use crate::mapping::MapModes;
 
 use crate::command::commands::exit;
 use crate::command::commands::print;
+use crate::command::commands::recover;
 
 enum MyOption<T> {
     Some(T),
@@ -54,7 +64,7 @@ pub struct Parser {
 impl Parser {
     pub fn new(input: &str) -> Self {
         let tokens = lexer::tokenize(input);
-        println!("tokens {:?}", tokens);
+        log::info!("tokens {:?}", tokens);
         Parser {
             original_tokens: tokens.clone(),
             tokens,
@@ -83,14 +93,14 @@ impl Parser {
     }
 
     fn pop(&mut self) -> MyOption<Token> {
-        if self.stack.len() > 0 {
+        if !self.stack.is_empty() {
             return MyOption::Some(self.stack.pop().unwrap());
         }
         MyOption::None
     }
 
     fn get_symbol(&mut self) {
-        if self.tokens.len() > 0 {
+        if !self.tokens.is_empty() {
             self.token_opt = MyOption::Some(self.tokens.remove(0));
         } else {
             self.token_opt = MyOption::Some(Token {
@@ -140,8 +150,25 @@ impl Parser {
         false
     }
 
+    // Accepts a command name abbreviated to at least `min_len` characters, like `:rec[over]`.
+    fn accept_command(&mut self, name: &str, min_len: usize) -> bool {
+        if let MyOption::Some(token) = &self.token_opt {
+            if token.token_type == TokenType::Command
+                && token.lexeme.len() >= min_len
+                && name.starts_with(&token.lexeme)
+            {
+                self.push(token.clone());
+                self.get_symbol();
+                return true;
+            }
+        }
+        false
+    }
+
     fn complex_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
-        let line_range = if let MyOption::Some(range) = self.line_range()? {
+        let line_range_opt = self.line_range()?;
+        let has_line_range = line_range_opt.is_some();
+        let line_range = if let MyOption::Some(range) = line_range_opt {
             range
         } else {
             LineRange {
@@ -150,6 +177,7 @@ impl Parser {
             }
         };
         let command_opt = self.display_command(&line_range)?
+            | self.line_number_command(&line_range, has_line_range)?
             | self.substitute_command(&line_range)?
             | self.delete_command(&line_range)?;
         if let MyOption::Some(command) = command_opt {
@@ -159,23 +187,69 @@ impl Parser {
     }
 
     fn delete_command(&mut self, line_range: &LineRange) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        // "d" [register]
         if self.accept(TokenType::Command, "d") {
+            let register = self.register()?;
             let delete_command = delete::DeleteLines {
                 line_range: line_range.clone(),
                 editor_cursor_data: None,
                 text: None,
+                register,
             };
             return Ok(MyOption::Some(Box::new(delete_command)));
         }
         Ok(MyOption::None)
     }
 
+    // The register of `:d a` and `:d _`, a letter or `_`.
+    fn register(&mut self) -> Result<Option<char>, GenericError> {
+        if !(self.accept_type(TokenType::Command) || self.accept(TokenType::Illegal, "_")) {
+            return Ok(None);
+        }
+        let MyOption::Some(token) = self.pop() else {
+            return Ok(None);
+        };
+        let mut chars = token.lexeme.chars();
+        match (chars.next(), chars.next()) {
+            (Some(name), None) => Ok(Some(name)),
+            _ => Err(format!("E488: Trailing characters: {}", token.lexeme).into()),
+        }
+    }
+
     fn display_command(&mut self, line_range: &LineRange) -> Result<MyOption<Box<dyn Command>>, GenericError> {
-        if self.accept(TokenType::Command, "p") {
-            let print_command = print::PrintCommand {
-                line_range: line_range.clone()
+        // "p[rint]" | "nu[mber]" | "#" | "l[ist]"
+        let (number, list) = if self.accept_command("print", 1) {
+            (false, false)
+        } else if self.accept_command("number", 2) || self.accept(TokenType::Symbol, "#") {
+            (true, false)
+        } else if self.accept_command("list", 1) {
+            (false, true)
+        } else {
+            return Ok(MyOption::None);
+        };
+        self.pop();
+        let print_command = print::PrintCommand {
+            line_range: line_range.clone(),
+            number,
+            list,
+        };
+        Ok(MyOption::Some(Box::new(print_command)))
+    }
+
+    fn line_number_command(
+        &mut self,
+        line_range: &LineRange,
+        has_line_range: bool,
+    ) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        // [line_range] "=", the last line without one
+        if self.accept(TokenType::Symbol, "=") {
+            self.pop();
+            let line_address = if has_line_range {
+                line_range.end.clone()
+            } else {
+                LineAddressType::Absolute(SimpleLineAddressType::LastLine)
             };
-            return Ok(MyOption::Some(Box::new(print_command)));
+            return Ok(MyOption::Some(Box::new(print::LineNumberCommand { line_address })));
         }
         Ok(MyOption::None)
     }
@@ -279,9 +353,9 @@ impl Parser {
     }
 
     fn pattern(&mut self) -> Result<MyOption<Pattern>, GenericError> {
-        if self.accept_type(TokenType::Pattern) {
-            if let MyOption::Some(token) = &self.token_opt {
-                let pattern = token.lexeme.clone();
+        if self.accept_type(TokenType::AddressPattern) {
+            if let MyOption::Some(token) = self.pop() {
+                let pattern = token.lexeme;
                 return Ok(MyOption::Some(Pattern { pattern }));
             }
         }
@@ -344,8 +418,12 @@ impl Parser {
 
     fn simple_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
         let command_opt =
-            self.q_command()? | self.wq_command()? | self.q_exclamation_command()?
-            | self.go_to_line_command()?;
+            self.q_command()? | self.w_command()?
+            | self.go_to_line_command()? | self.set_command()?
+            | self.recover_command()? | self.edit_command()?
+            | self.argument_command()? | self.buffer_command()?
+            | self.window_command()? | self.colorscheme_command()?
+            | self.map_command()?;
         if let MyOption::Some(command) = command_opt {
             return Ok(MyOption::Some(command));
         }
@@ -367,33 +445,237 @@ impl Parser {
         Ok(MyOption::None)
     }
 
-    fn q_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
-        if self.accept(TokenType::Command, "q") {
+    fn set_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept(TokenType::Command, "set") || self.accept(TokenType::Command, "se") {
             self.pop();
-            return Ok(MyOption::Some(Box::new(exit::ExitCommand {})));
+            let mut arguments = Vec::new();
+            while self.accept_type(TokenType::Option) {
+                if let MyOption::Some(token) = self.pop() {
+                    arguments.push(token.lexeme);
+                }
+            }
+            return Ok(MyOption::Some(Box::new(set::SetCommand { arguments })));
         }
+        Ok(MyOption::None)
+    }
 
+    fn colorscheme_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("colorscheme", 4) {
+            self.pop();
+            let mut name = None;
+            if self.accept_type(TokenType::Filename) {
+                if let MyOption::Some(token) = self.pop() {
+                    name = Some(token.lexeme);
+                }
+            }
+            return Ok(MyOption::Some(Box::new(colorscheme::ColorschemeCommand { name })));
+        }
         Ok(MyOption::None)
     }
 
-    fn q_exclamation_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+    // `:map`, `:noremap`, `:unmap` and their variants for each mode.
+    fn map_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        let Some(&(name, _)) = lexer::MAP_COMMANDS
+            .iter()
+            .find(|(name, min_len)| self.accept_command(name, *min_len))
+        else {
+            return Ok(MyOption::None);
+        };
+        self.pop();
+        let bang = self.bang();
+        let modes = match name.chars().next() {
+            Some('n') if name != "noremap" => MapModes::Normal,
+            Some('i') => MapModes::Insert,
+            Some('c') => MapModes::CommandLine,
+            _ if bang => MapModes::InsertAndCommandLine,
+            _ => MapModes::All,
+        };
+        let mut keys = Vec::new();
+        while self.accept_type(TokenType::Keys) {
+            if let MyOption::Some(token) = self.pop() {
+                keys.push(token.lexeme);
+            }
+        }
+        // Another command after a `|` is not run.
+        if self.accept(TokenType::Symbol, "|") {
+            if let MyOption::Some(token) = &self.token_opt {
+                if token.token_type != TokenType::EndOfInput {
+                    return Err(format!("E488: Trailing characters: {}", token.lexeme).into());
+                }
+            }
+        }
+        let mut keys = keys.into_iter();
+        let lhs = keys.next();
+        if name.ends_with("unmap") {
+            let lhs = lhs.ok_or("E474: Invalid argument")?;
+            return Ok(MyOption::Some(Box::new(map::UnmapCommand { modes, lhs })));
+        }
+        Ok(MyOption::Some(Box::new(map::MapCommand {
+            modes,
+            lhs,
+            rhs: keys.next(),
+            noremap: name.contains("noremap"),
+        })))
+    }
+
+    fn recover_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("recover", 3) {
+            self.pop();
+            let force = self.bang();
+            return Ok(MyOption::Some(Box::new(recover::RecoverCommand { force })));
+        }
+        Ok(MyOption::None)
+    }
+
+    fn edit_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("edit", 1) {
+            self.pop();
+            let force = self.bang();
+            let mut file_name = None;
+            if self.accept_type(TokenType::Filename) {
+                if let MyOption::Some(token) = self.pop() {
+                    file_name = Some(token.lexeme);
+                }
+            }
+            return Ok(MyOption::Some(Box::new(edit::EditCommand { force, file_name })));
+        }
+        Ok(MyOption::None)
+    }
+
+    fn buffer_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("ls", 2)
+            || self.accept_command("buffers", 7)
+            || self.accept_command("files", 5)
+        {
+            self.pop();
+            return Ok(MyOption::Some(Box::new(buffers::ListBuffersCommand {})));
+        }
+        let delete = self.accept_command("bdelete", 2);
+        if !delete && !self.accept_command("buffer", 1) {
+            return Ok(MyOption::None);
+        }
+        self.pop();
+        let force = self.bang();
+        let mut number = None;
+        if self.accept_type(TokenType::Number) {
+            if let MyOption::Some(token) = self.pop() {
+                number = Some(token.lexeme.parse()?);
+            }
+        }
+        if delete {
+            return Ok(MyOption::Some(Box::new(buffers::DeleteBufferCommand { number, force })));
+        }
+        match number {
+            Some(number) => Ok(MyOption::Some(Box::new(buffers::BufferCommand { number }))),
+            None => Err("E94: No matching buffer".into()),
+        }
+    }
+
+    fn argument_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("args", 2) {
+            self.pop();
+            return Ok(MyOption::Some(Box::new(args::ShowArgumentsCommand {})));
+        }
+        let write = self.accept_command("wnext", 2);
+        let next = !write && self.accept_command("next", 1);
+        let target = if write || next {
+            ArgumentTarget::Next
+        } else if self.accept_command("Next", 1) || self.accept_command("previous", 4) {
+            ArgumentTarget::Previous
+        } else if self.accept_command("rewind", 3) || self.accept_command("first", 3) {
+            ArgumentTarget::First
+        } else if self.accept_command("last", 2) {
+            ArgumentTarget::Last
+        } else {
+            return Ok(MyOption::None);
+        };
+        self.pop();
+        let force = self.bang();
+        let mut file_names = Vec::new();
+        while self.accept_type(TokenType::Filename) {
+            if let MyOption::Some(token) = self.pop() {
+                file_names.push(token.lexeme);
+            }
+        }
+        // Only `:n` takes files, which replace the argument list.
+        let target = match file_names.first() {
+            None => target,
+            Some(_) if next => ArgumentTarget::NewList(file_names),
+            Some(file_name) => {
+                return Err(format!("E488: Trailing characters: {}", file_name).into());
+            }
+        };
+        Ok(MyOption::Some(Box::new(args::EditArgumentCommand {
+            target,
+            force,
+            write,
+        })))
+    }
+
+    fn window_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+        if self.accept_command("close", 3) {
+            self.pop();
+            self.bang();
+            return Ok(MyOption::Some(Box::new(window::CloseCommand {})));
+        }
+        if self.accept_command("only", 2) {
+            self.pop();
+            self.bang();
+            return Ok(MyOption::Some(Box::new(window::OnlyCommand {})));
+        }
+        let vertical = self.accept_command("vsplit", 2);
+        if !vertical && !self.accept_command("split", 2) {
+            return Ok(MyOption::None);
+        }
+        self.pop();
+        let mut file_name = None;
+        if self.accept_type(TokenType::Filename) {
+            if let MyOption::Some(token) = self.pop() {
+                file_name = Some(token.lexeme);
+            }
+        }
+        Ok(MyOption::Some(Box::new(window::SplitCommand {
+            vertical,
+            file_name,
+        })))
+    }
+
+    fn bang(&mut self) -> bool {
+        if self.accept(TokenType::Symbol, "!") {
+            self.pop();
+            return true;
+        }
+        false
+    }
+
+    fn q_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
         if self.accept(TokenType::Command, "q") {
             self.pop();
-            if self.accept(TokenType::Command, "!") {
-                self.pop();
+            if self.bang() {
                 return Ok(MyOption::Some(Box::new(exit::ExitWithoutSaveCommand {})));
             }
+            return Ok(MyOption::Some(Box::new(exit::ExitCommand {})));
         }
+
         Ok(MyOption::None)
     }
 
-    fn wq_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
+    fn w_command(&mut self) -> Result<MyOption<Box<dyn Command>>, GenericError> {
         if self.accept(TokenType::Command, "w") {
             self.pop();
             if self.accept(TokenType::Command, "q") {
                 self.pop();
-                return Ok(MyOption::Some(Box::new(exit::ExitWithSaveCommand {})));
+                let force = self.bang();
+                return Ok(MyOption::Some(Box::new(exit::ExitWithSaveCommand { force })));
+            }
+            let force = self.bang();
+            let mut file_name = None;
+            if self.accept_type(TokenType::Filename) {
+                if let MyOption::Some(token) = self.pop() {
+                    file_name = Some(token.lexeme);
+                }
             }
+            return Ok(MyOption::Some(Box::new(write::WriteCommand { force, file_name })));
         }
         Ok(MyOption::None)
     }
@@ -419,6 +701,132 @@ mod tests {
         assert!(command.is::<exit::ExitWithSaveCommand>());
     }
 
+    #[test]
+    fn test_parse_q_exclamation_command() {
+        let input = "q!";
+        let mut parser = Parser::new(input);
+        let command = parser.parse().unwrap();
+        assert!(command.is::<exit::ExitWithoutSaveCommand>());
+    }
+
+    #[test]
+    fn test_parse_recover_command() {
+        let mut parser = Parser::new("rec");
+        let command = parser.parse().unwrap();
+        assert!(!command.downcast_ref::<recover::RecoverCommand>().unwrap().force);
+
+        let mut parser = Parser::new("recover!");
+        let command = parser.parse().unwrap();
+        assert!(command.downcast_ref::<recover::RecoverCommand>().unwrap().force);
+
+        assert!(Parser::new("re").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_edit_command() {
+        let mut parser = Parser::new("e!");
+        let command = parser.parse().unwrap();
+        assert!(command.downcast_ref::<edit::EditCommand>().unwrap().force);
+
+        let mut parser = Parser::new("edit");
+        let command = parser.parse().unwrap();
+        assert!(!command.downcast_ref::<edit::EditCommand>().unwrap().force);
+
+        let mut parser = Parser::new("e! #");
+        let command = parser.parse().unwrap();
+        let command = command.downcast_ref::<edit::EditCommand>().unwrap();
+        assert!(command.force);
+        assert_eq!(command.file_name.as_deref(), Some("#"));
+    }
+
+    #[test]
+    fn test_parse_window_commands() {
+        let command = Parser::new("vs b.txt").parse().unwrap();
+        let command = command.downcast_ref::<window::SplitCommand>().unwrap();
+        assert!(command.vertical);
+        assert_eq!(command.file_name.as_deref(), Some("b.txt"));
+
+        let command = Parser::new("sp").parse().unwrap();
+        let command = command.downcast_ref::<window::SplitCommand>().unwrap();
+        assert!(!command.vertical);
+        assert_eq!(command.file_name, None);
+
+        assert!(Parser::new("clo").parse().unwrap().is::<window::CloseCommand>());
+        assert!(Parser::new("only").parse().unwrap().is::<window::OnlyCommand>());
+    }
+
+    #[test]
+    fn test_parse_colorscheme_command() {
+        let command = Parser::new("colo light").parse().unwrap();
+        let command = command.downcast_ref::<colorscheme::ColorschemeCommand>().unwrap();
+        assert_eq!(command.name.as_deref(), Some("light"));
+
+        let command = Parser::new("colorscheme").parse().unwrap();
+        let command = command.downcast_ref::<colorscheme::ColorschemeCommand>().unwrap();
+        assert_eq!(command.name, None);
+    }
+
+    #[test]
+    fn test_parse_buffer_commands() {
+        assert!(Parser::new("ls").parse().unwrap().is::<buffers::ListBuffersCommand>());
+        assert!(Parser::new("buffers").parse().unwrap().is::<buffers::ListBuffersCommand>());
+
+        let command = Parser::new("b 2").parse().unwrap();
+        assert_eq!(command.downcast_ref::<buffers::BufferCommand>().unwrap().number, 2);
+
+        let command = Parser::new("bd! 3").parse().unwrap();
+        let command = command.downcast_ref::<buffers::DeleteBufferCommand>().unwrap();
+        assert_eq!((command.number, command.force), (Some(3), true));
+    }
+
+    #[test]
+    fn test_parse_argument_commands() {
+        let parse = |input: &str| {
+            let command = Parser::new(input).parse().unwrap();
+            let command = command.downcast_ref::<args::EditArgumentCommand>().unwrap();
+            (command.target.clone(), command.force, command.write)
+        };
+        assert_eq!(parse("n"), (ArgumentTarget::Next, false, false));
+        assert_eq!(parse("N!"), (ArgumentTarget::Previous, true, false));
+        assert_eq!(parse("prev"), (ArgumentTarget::Previous, false, false));
+        assert_eq!(parse("rew"), (ArgumentTarget::First, false, false));
+        assert_eq!(parse("last"), (ArgumentTarget::Last, false, false));
+        assert_eq!(parse("wn"), (ArgumentTarget::Next, false, true));
+        assert_eq!(
+            parse("next a.txt b.txt"),
+            (
+                ArgumentTarget::NewList(vec!["a.txt".to_string(), "b.txt".to_string()]),
+                false,
+                false
+            )
+        );
+
+        for input in ["N a.txt", "prev a.txt", "rew a.txt", "last a.txt", "wn a.txt"] {
+            assert_eq!(
+                Parser::new(input).parse().err().unwrap().to_string(),
+                "E488: Trailing characters: a.txt"
+            );
+        }
+
+        let command = Parser::new("args").parse().unwrap();
+        assert!(command.is::<args::ShowArgumentsCommand>());
+    }
+
+    #[test]
+    fn test_parse_write_command() {
+        let mut parser = Parser::new("w");
+        let command = parser.parse().unwrap();
+        let write_command = command.downcast_ref::<write::WriteCommand>().unwrap();
+        assert!(!write_command.force);
+        assert_eq!(write_command.file_name, None);
+
+        let mut parser = Parser::new("w! other.txt");
+        let command = parser.parse().unwrap();
+        let write_command = command.downcast_ref::<write::WriteCommand>().unwrap();
+        assert!(write_command.force);
+        assert_eq!(write_command.file_name, Some("other.txt".to_string()));
+    }
+
     #[test]
     fn test_parse_go_to_line_command() {
         let input = "1";
@@ -427,6 +835,15 @@ mod tests {
         assert!(command.is::<go_to_line::GoToLineCommand>());
     }
 
+    #[test]
+    fn test_parse_set_command() {
+        let input = "set ff=dos nobomb";
+        let mut parser = Parser::new(input);
+        let command = parser.parse().unwrap();
+        let set_command = command.downcast_ref::<set::SetCommand>().unwrap();
+        assert_eq!(set_command.arguments, vec!["ff=dos", "nobomb"]);
+    }
+
     #[test]
     fn test_parse_print_command() {
         let input = "p";
@@ -451,6 +868,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_number_and_list_commands() {
+        for (input, number, list) in [("1,10#", true, false), ("nu", true, false), ("l", false, true)] {
+            let mut parser = Parser::new(input);
+            let command = parser.parse().unwrap();
+            let print_command = command.downcast_ref::<print::PrintCommand>().unwrap();
+            assert_eq!((print_command.number, print_command.list), (number, list), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_line_number_command() {
+        let pattern = SimpleLineAddressType::Pattern(Pattern {
+            pattern: "fo+".to_string(),
+        });
+        for (input, address) in [
+            ("=", SimpleLineAddressType::LastLine),
+            (".=", SimpleLineAddressType::CurrentLine),
+            ("/fo+/=", pattern),
+        ] {
+            let mut parser = Parser::new(input);
+            let command = parser.parse().unwrap();
+            let line_number_command = command.downcast_ref::<print::LineNumberCommand>().unwrap();
+            assert_eq!(line_number_command.line_address, LineAddressType::Absolute(address));
+        }
+    }
+
     #[test]
     fn test_parse_delete_line_with_address() {
         let input = "1,3d";
@@ -467,6 +911,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_delete_into_register() {
+        for (input, register) in [("d", None), ("2d a", Some('a')), ("d A", Some('A')), ("d _", Some('_'))] {
+            let mut parser = Parser::new(input);
+            let command = parser.parse().unwrap();
+            let delete_command = command.downcast_ref::<delete::DeleteLines>().unwrap();
+            assert_eq!(delete_command.register, register);
+        }
+        assert!(Parser::new("d ab").parse().is_err());
+    }
+
     #[test]
     fn test_parse_substitute_ignore_case() {
         let input = "1,5s/^abc/cba/i";
@@ -526,4 +981,35 @@ mod tests {
         assert!(!sub.global);
         assert!(!sub.ignore_case);
     }
+
+    #[test]
+    fn test_parse_map_commands() {
+        let command = Parser::new("inoremap jk <Esc>").parse().unwrap();
+        let command = command.downcast_ref::<map::MapCommand>().unwrap();
+        assert_eq!(command.modes, MapModes::Insert);
+        assert_eq!(command.lhs.as_deref(), Some("jk"));
+        assert_eq!(command.rhs.as_deref(), Some("<Esc>"));
+        assert!(command.noremap);
+
+        let command = Parser::new("map!").parse().unwrap();
+        let command = command.downcast_ref::<map::MapCommand>().unwrap();
+        assert_eq!(command.modes, MapModes::InsertAndCommandLine);
+        assert_eq!(command.lhs, None);
+        assert!(!command.noremap);
+
+        let command = Parser::new("nun Q").parse().unwrap();
+        let command = command.downcast_ref::<map::UnmapCommand>().unwrap();
+        assert_eq!(command.modes, MapModes::Normal);
+        assert_eq!(command.lhs, "Q");
+        assert!(Parser::new("unmap").parse().is_err());
+
+        // the keys end at a `|` unless it is `\|` or `<Bar>`
+        let command = Parser::new(r"map Q a\|b<Bar>c|").parse().unwrap();
+        let command = command.downcast_ref::<map::MapCommand>().unwrap();
+        assert_eq!(command.rhs.as_deref(), Some("a|b<Bar>c"));
+        assert_eq!(
+            Parser::new("map Q dd | x").parse().err().unwrap().to_string(),
+            "E488: Trailing characters: x"
+        );
+    }
 }
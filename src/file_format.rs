@@ -0,0 +1,302 @@
+use encoding_rs::{Encoding, EUC_JP, SHIFT_JIS};
+
+use crate::generic_error::GenericResult;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileEncoding {
+    Utf8,
+    Latin1,
+    ShiftJis,
+    EucJp,
+}
+
+impl FileEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileEncoding::Utf8 => "utf-8",
+            FileEncoding::Latin1 => "latin1",
+            FileEncoding::ShiftJis => "sjis",
+            FileEncoding::EucJp => "euc-jp",
+        }
+    }
+
+    // An empty name selects the default encoding, like vim's `:set fileencoding=`.
+    pub fn from_name(name: &str) -> Option<FileEncoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "" | "utf-8" | "utf8" => Some(FileEncoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(FileEncoding::Latin1),
+            "sjis" | "shift_jis" | "shift-jis" | "cp932" => Some(FileEncoding::ShiftJis),
+            "euc-jp" | "eucjp" => Some(FileEncoding::EucJp),
+            _ => None,
+        }
+    }
+
+    fn detect(bytes: &[u8]) -> FileEncoding {
+        if std::str::from_utf8(bytes).is_ok() {
+            return FileEncoding::Utf8;
+        }
+        // Text that decodes cleanly in only one Japanese encoding is in it.
+        // When it decodes in both, the one giving more kana wins, EUC-JP on a
+        // tie, as its kanji also read as half-width katakana in Shift_JIS.
+        match (decode_strict(SHIFT_JIS, bytes), decode_strict(EUC_JP, bytes)) {
+            (Some(shift_jis), Some(euc_jp)) => {
+                if kana_count(&shift_jis) > kana_count(&euc_jp) {
+                    FileEncoding::ShiftJis
+                } else {
+                    FileEncoding::EucJp
+                }
+            }
+            (Some(_), None) => FileEncoding::ShiftJis,
+            (None, Some(_)) => FileEncoding::EucJp,
+            (None, None) => FileEncoding::Latin1,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> GenericResult<String> {
+        match self {
+            FileEncoding::Utf8 => Ok(std::str::from_utf8(bytes)?.to_string()),
+            FileEncoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+            FileEncoding::ShiftJis => {
+                decode_strict(SHIFT_JIS, bytes).ok_or_else(|| "Invalid Shift_JIS text".into())
+            }
+            FileEncoding::EucJp => {
+                decode_strict(EUC_JP, bytes).ok_or_else(|| "Invalid EUC-JP text".into())
+            }
+        }
+    }
+
+    fn encode(&self, text: &str) -> GenericResult<Vec<u8>> {
+        let conversion_failed = || format!("E513: write error, conversion to {} failed", self.name());
+        match self {
+            FileEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            FileEncoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| conversion_failed().into()))
+                .collect(),
+            FileEncoding::ShiftJis | FileEncoding::EucJp => {
+                let encoding = if *self == FileEncoding::ShiftJis { SHIFT_JIS } else { EUC_JP };
+                let (bytes, _, had_errors) = encoding.encode(text);
+                if had_errors {
+                    return Err(conversion_failed().into());
+                }
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    Unix,
+    Dos,
+}
+
+impl LineEnding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Unix => "unix",
+            LineEnding::Dos => "dos",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LineEnding> {
+        match name {
+            "unix" => Some(LineEnding::Unix),
+            "dos" => Some(LineEnding::Dos),
+            _ => None,
+        }
+    }
+}
+
+// How the text of a buffer is stored on disk. `decode` detects it when a file
+// is read and `encode` reproduces it when the buffer is written back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileFormat {
+    pub encoding: FileEncoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    pub end_of_line: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat {
+            encoding: FileEncoding::Utf8,
+            bom: false,
+            line_ending: LineEnding::Unix,
+            end_of_line: true,
+        }
+    }
+}
+
+impl FileFormat {
+    /// Decodes file contents into text whose lines are all terminated by '\n'.
+    pub fn decode(bytes: &[u8]) -> GenericResult<(FileFormat, String)> {
+        let mut file_format = FileFormat::default();
+        let bytes = match bytes.strip_prefix(UTF8_BOM) {
+            Some(rest) => {
+                file_format.bom = true;
+                rest
+            }
+            None => bytes,
+        };
+        file_format.encoding = if file_format.bom {
+            FileEncoding::Utf8
+        } else {
+            FileEncoding::detect(bytes)
+        };
+        let mut text = file_format.encoding.decode(bytes)?;
+
+        let num_of_lf = text.matches('\n').count();
+        if num_of_lf > 0 && text.matches("\r\n").count() == num_of_lf {
+            file_format.line_ending = LineEnding::Dos;
+            text = text.replace("\r\n", "\n");
+        }
+        if !text.is_empty() && !text.ends_with('\n') {
+            file_format.end_of_line = false;
+            text.push('\n');
+        }
+        Ok((file_format, text))
+    }
+
+    /// Encodes text whose lines are all terminated by '\n' into file contents.
+    pub fn encode(&self, text: &str) -> GenericResult<Vec<u8>> {
+        let text = if self.end_of_line {
+            text
+        } else {
+            text.strip_suffix('\n').unwrap_or(text)
+        };
+        let mut bytes = Vec::with_capacity(text.len() + UTF8_BOM.len());
+        if self.bom && self.encoding == FileEncoding::Utf8 {
+            bytes.extend_from_slice(UTF8_BOM);
+        }
+        match self.line_ending {
+            LineEnding::Unix => bytes.extend(self.encoding.encode(text)?),
+            LineEnding::Dos => bytes.extend(self.encoding.encode(&text.replace('\n', "\r\n"))?),
+        }
+        Ok(bytes)
+    }
+}
+
+fn decode_strict(encoding: &'static Encoding, bytes: &[u8]) -> Option<String> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+}
+
+fn kana_count(text: &str) -> usize {
+    text.chars().filter(|c| ('\u{3040}'..='\u{30ff}').contains(c)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> (FileFormat, String) {
+        let (file_format, text) = FileFormat::decode(bytes).unwrap();
+        assert_eq!(file_format.encode(&text).unwrap(), bytes);
+        (file_format, text)
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let (file_format, text) = round_trip("abc\nあいう\n".as_bytes());
+        assert_eq!(file_format, FileFormat::default());
+        assert_eq!(text, "abc\nあいう\n");
+    }
+
+    #[test]
+    fn test_decode_empty() {
+        let (file_format, text) = round_trip(b"");
+        assert_eq!(file_format, FileFormat::default());
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn test_decode_bom_crlf_without_final_newline() {
+        let (file_format, text) = round_trip(b"\xEF\xBB\xBFabc\r\ndef");
+        assert!(file_format.bom);
+        assert_eq!(file_format.encoding, FileEncoding::Utf8);
+        assert_eq!(file_format.line_ending, LineEnding::Dos);
+        assert!(!file_format.end_of_line);
+        assert_eq!(text, "abc\ndef\n");
+    }
+
+    #[test]
+    fn test_decode_mixed_line_endings_keeps_cr() {
+        let (file_format, text) = round_trip(b"abc\r\ndef\n");
+        assert_eq!(file_format.line_ending, LineEnding::Unix);
+        assert_eq!(text, "abc\r\ndef\n");
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        let (file_format, text) = round_trip(b"caf\xe9\n");
+        assert_eq!(file_format.encoding, FileEncoding::Latin1);
+        assert_eq!(text, "café\n");
+    }
+
+    #[test]
+    fn test_decode_latin1_accented_pairs() {
+        // "déjà élève, où être" in Latin-1, whose pairs of accented letters
+        // are also valid kanji in Shift_JIS and EUC-JP
+        let bytes = b"d\xe9j\xe0 \xe9l\xe8ve, o\xf9 \xeatre\n";
+        assert!(decode_strict(EUC_JP, b"\xe9\xe8").is_some());
+        let (file_format, text) = round_trip(bytes);
+        assert_eq!(file_format.encoding, FileEncoding::Latin1);
+        assert_eq!(text, "déjà élève, où être\n");
+    }
+
+    #[test]
+    fn test_decode_shift_jis() {
+        // "日本語のテキスト" in Shift_JIS
+        let bytes = b"\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67\n";
+        let (file_format, text) = round_trip(bytes);
+        assert_eq!(file_format.encoding, FileEncoding::ShiftJis);
+        assert_eq!(text, "日本語のテキスト\n");
+    }
+
+    #[test]
+    fn test_decode_kanji_only() {
+        // "漢字" in Shift_JIS, whose second bytes are not EUC-JP
+        let bytes = b"\x8a\xbf\x8e\x9a\n";
+        assert!(decode_strict(EUC_JP, bytes).is_none());
+        let (file_format, text) = round_trip(bytes);
+        assert_eq!(file_format.encoding, FileEncoding::ShiftJis);
+        assert_eq!(text, "漢字\n");
+
+        // "漢字" in EUC-JP, which Shift_JIS cannot decode
+        let bytes = b"\xb4\xc1\xbb\xfa\n";
+        assert!(decode_strict(SHIFT_JIS, bytes).is_none());
+        let (file_format, text) = round_trip(bytes);
+        assert_eq!(file_format.encoding, FileEncoding::EucJp);
+        assert_eq!(text, "漢字\n");
+    }
+
+    #[test]
+    fn test_decode_euc_jp() {
+        // "あいうえお" in EUC-JP
+        let bytes = b"\xa4\xa2\xa4\xa4\xa4\xa6\xa4\xa8\xa4\xaa\n";
+        let (file_format, text) = round_trip(bytes);
+        assert_eq!(file_format.encoding, FileEncoding::EucJp);
+        assert_eq!(text, "あいうえお\n");
+    }
+
+    #[test]
+    fn test_encode_converts_format() {
+        let file_format = FileFormat {
+            encoding: FileEncoding::ShiftJis,
+            line_ending: LineEnding::Dos,
+            ..FileFormat::default()
+        };
+        assert_eq!(file_format.encode("あ\n").unwrap(), b"\x82\xa0\r\n");
+
+        let file_format = FileFormat {
+            encoding: FileEncoding::Latin1,
+            ..FileFormat::default()
+        };
+        assert!(file_format.encode("あ\n").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+// What is remembered about a file when it is read or written, to notice
+// when something else changes it on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileStamp {
+    Missing,
+    Present {
+        modified: Option<SystemTime>,
+        len: u64,
+        inode: u64,
+    },
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> FileStamp {
+        match fs::metadata(path) {
+            Ok(metadata) => FileStamp::Present {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+                inode: inode(&metadata),
+            },
+            Err(_) => FileStamp::Missing,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stamp_changes_with_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        assert_eq!(FileStamp::of(&path), FileStamp::Missing);
+
+        fs::write(&path, "abc\n").unwrap();
+        let stamp = FileStamp::of(&path);
+        assert_ne!(stamp, FileStamp::Missing);
+        assert_eq!(FileStamp::of(&path), stamp);
+
+        fs::write(&path, "abcdef\n").unwrap();
+        assert_ne!(FileStamp::of(&path), stamp);
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::generic_error::GenericResult;
+
+// Same limit as Linux's MAXSYMLINKS.
+const MAX_SYMLINKS: usize = 40;
+
+enum WriteFailure {
+    Open(io::Error),
+    Write(io::Error),
+    Fsync(io::Error),
+}
+
+impl WriteFailure {
+    fn io_error(&self) -> &io::Error {
+        match self {
+            WriteFailure::Open(e) | WriteFailure::Write(e) | WriteFailure::Fsync(e) => e,
+        }
+    }
+
+    fn message(&self, path: &Path) -> String {
+        let message = match self {
+            WriteFailure::Open(_) => "E212: Can't open file for writing",
+            WriteFailure::Write(_) => "E514: write error (file system full?)",
+            WriteFailure::Fsync(_) => "E667: Fsync failed",
+        };
+        format!("\"{}\" {}", path.to_string_lossy(), message)
+    }
+}
+
+/// Writes `bytes` to `path` so that a crash never leaves a half-written file.
+///
+/// The data goes to a temporary file in the target's directory, which is
+/// synced and then renamed over the target. Symlinks are followed, and the
+/// mode and owner of an existing file are carried over. Files with several
+/// hard links, and files whose directory doesn't allow the rename, are
+/// overwritten in place instead.
+pub fn write_file(path: &Path, bytes: &[u8]) -> GenericResult<()> {
+    let target = resolve_symlinks(path);
+    let metadata = fs::metadata(&target).ok();
+    let can_replace = metadata
+        .as_ref()
+        .is_none_or(|metadata| metadata.is_file() && hard_link_count(metadata) <= 1);
+
+    let result = if can_replace {
+        match write_by_rename(&target, bytes, metadata.as_ref()) {
+            Err(WriteFailure::Open(e)) => {
+                info!("Cannot replace {:?} ({}), writing in place", target, e);
+                write_in_place(&target, bytes)
+            }
+            result => result,
+        }
+    } else {
+        write_in_place(&target, bytes)
+    };
+    result.map_err(|failure| {
+        info!("Writing {:?} failed: {}", target, failure.io_error());
+        failure.message(path).into()
+    })
+}
+
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match fs::read_link(&path) {
+            // `join` keeps an absolute link target as it is.
+            Ok(link) => path = path.parent().unwrap_or(Path::new("")).join(link),
+            Err(_) => break,
+        }
+    }
+    path
+}
+
+// Only failures to set up the temporary file are reported as `Open`, so the
+// caller can fall back to writing in place without losing the original
+// when the disk is full.
+fn write_by_rename(
+    target: &Path,
+    bytes: &[u8],
+    metadata: Option<&Metadata>,
+) -> Result<(), WriteFailure> {
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(&format!(".{}.", file_name))
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(WriteFailure::Open)?;
+
+    if let Some(metadata) = metadata {
+        fs::set_permissions(temp_file.path(), metadata.permissions())
+            .map_err(WriteFailure::Open)?;
+        copy_owner(temp_file.as_file(), metadata).map_err(WriteFailure::Open)?;
+    }
+    temp_file.write_all(bytes).map_err(WriteFailure::Write)?;
+    temp_file
+        .as_file()
+        .sync_all()
+        .map_err(WriteFailure::Fsync)?;
+    temp_file
+        .persist(target)
+        .map_err(|e| WriteFailure::Open(e.error))?;
+
+    // Make the rename itself durable; not every platform can sync a directory.
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn write_in_place(target: &Path, bytes: &[u8]) -> Result<(), WriteFailure> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(target)
+        .map_err(WriteFailure::Open)?;
+    file.write_all(bytes).map_err(WriteFailure::Write)?;
+    file.sync_all().map_err(WriteFailure::Fsync)
+}
+
+#[cfg(unix)]
+fn hard_link_count(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+#[cfg(not(unix))]
+fn hard_link_count(_metadata: &Metadata) -> u64 {
+    1
+}
+
+#[cfg(unix)]
+fn copy_owner(file: &File, metadata: &Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let current = file.metadata()?;
+    if current.uid() == metadata.uid() && current.gid() == metadata.gid() {
+        return Ok(());
+    }
+    std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_file: &File, _metadata: &Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+
+    use super::*;
+
+    #[test]
+    fn test_write_file_keeps_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+
+        write_file(&path, b"new\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o750
+        );
+        // No temporary file is left behind.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_file_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&target, "old\n").unwrap();
+        symlink("target.txt", &link).unwrap();
+
+        write_file(&link, b"new\n").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new\n");
+    }
+
+    #[test]
+    fn test_write_file_keeps_hard_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        let other = dir.path().join("b.txt");
+        fs::write(&path, "old\n").unwrap();
+        fs::hard_link(&path, &other).unwrap();
+
+        write_file(&path, b"new\n").unwrap();
+        assert_eq!(fs::read(&other).unwrap(), b"new\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().ino(),
+            fs::metadata(&other).unwrap().ino()
+        );
+    }
+
+    #[test]
+    fn test_write_file_reports_vi_style_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("a.txt");
+        let error = write_file(&path, b"new\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "\"{}\" E212: Can't open file for writing",
+                path.to_string_lossy()
+            )
+        );
+    }
+}
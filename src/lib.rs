@@ -1,4 +1,9 @@
 pub mod buffer;
+pub mod buffer_list;
+pub mod file_format;
+pub mod file_stamp;
+pub mod file_write;
+pub mod swap_file;
 pub mod editor;
 pub mod command {
     pub mod base;
@@ -6,13 +11,24 @@ pub mod command {
     pub mod region;
     pub mod commands; // use commands/mod.rs for submodules
     pub mod compose;
-    pub mod key_codes;
+    pub mod keymap;
 }
 pub mod util;
+pub mod window;
+pub mod options;
 pub mod generic_error;
 pub mod data;
 pub mod ex {
     pub mod lexer;
     pub mod parser;
 }
+pub mod backend;
+pub mod mapping;
+pub mod pager;
+pub mod register;
 pub mod render;
+pub mod screen;
+pub mod status_line;
+pub mod syntax;
+pub mod theme;
+pub mod main_loop;
@@ -1,12 +1,4 @@
-mod data;
-mod buffer;
-mod command;
-mod editor;
-mod main_loop;
-mod render;
-mod generic_error;
-mod util;
-mod ex;
+use evi::{editor, main_loop};
 
 use log::{error, info};
 
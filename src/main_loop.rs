@@ -1,154 +1,350 @@
-use crossterm::{
-    event::{self, Event, KeyEvent, KeyModifiers},
-    terminal::{self, ClearType},
-    ExecutableCommand,
-};
+use crossterm::event::{self, Event, KeyEvent, KeyModifiers};
 use std::io::stdout;
-use std::io::Write;
+use std::time::Duration;
 
 use log::{error, info};
 
+use crate::backend::{Backend, CrosstermBackend};
+use crate::command::base::CommandData;
 use crate::command::compose::{compose, InputState, KeyData};
 use crate::editor::Editor;
+use crate::command::keymap::Lookup;
 use crate::generic_error::GenericResult;
+use crate::mapping::{Mapping, TypeaheadKey};
+use crate::pager::PagerAction;
+
+// Like vim's 'updatetime': the swap file is written, and the file on disk is
+// checked for changes, when no key is typed for this long.
+const UPDATE_TIME: Duration = Duration::from_secs(4);
+// Like vim's 'maxmapdepth': how many mappings may be put in place of keys
+// before the next key typed is handled.
+const MAX_MAP_DEPTH: usize = 1000;
 
 pub fn main_loop(editor: &mut Editor) -> GenericResult<()> {
-    let mut stdout = stdout();
-    let mut event_keys: Vec<KeyEvent> = Vec::new();
+    let mut backend = CrosstermBackend::new(stdout());
+    backend.enter()?;
+    // the terminal is restored when the backend is dropped
+    run(editor, &mut backend)
+}
 
-    terminal::enable_raw_mode()?;
+/// Renders the editor and handles the events of `backend` until the editor
+/// exits or the events run out.
+pub fn run(editor: &mut Editor, backend: &mut impl Backend) -> GenericResult<()> {
+    let mut event_keys: Vec<KeyEvent> = Vec::new();
 
-    let terminal_size = terminal::size()?;
+    editor.color_depth = backend.color_depth();
+    let terminal_size = backend.size()?;
     editor.resize_terminal(terminal_size.0, terminal_size.1);
 
-    loop {
-        editor.render(&mut stdout)?;
-        let result = event::read();
-        match result {
-            Ok(Event::Key(key_event)) => {
-                if editor.is_command_mode() {
-                    info!("Key event: {:?}", key_event);
-                    if event_keys.len() == 0 && key_event.code == event::KeyCode::Char(':') {
-                        // ex command begin
-                        editor.set_ex_command_mode();
-                        editor.status_line = ":".to_string();
-                    } else {
-                        event_keys.push(key_event);
-                        let input_state = compose(&event_keys);
-                        match input_state {
-                            InputState::CommandCompleted(command_data) => {
-                                info!("Command completed: {:?}", command_data);
-                                editor.execute_command(command_data)?;
-                                event_keys.clear();
-                            }
-                            InputState::CommandInvalid(key_codes) => {
-                                //　TODO: error message
-                                error!("Invalid command: {:?}", key_codes);
-                                event_keys.clear();
-                            }
-                            _ => {
-                                info!("Input state: {:?}", input_state);
-                            }
-                        }
-                    }
-                } else if editor.is_ex_command_mode() {
-                    let key_data: KeyData = key_event.into();
-                    match key_data {
-                        KeyData {
-                            key_code: event::KeyCode::Enter,
-                            ..
-                        } => {
-                            let command_data = editor.get_ex_command_data();
-                            editor.execute_ex_command(command_data)?;
-                            editor.set_command_mode();
-                        }
-                        KeyData {
-                            key_code: event::KeyCode::Esc,
-                            ..
-                        } => {
-                            editor.set_command_mode();
-                            editor.status_line = "".to_string();
-                        }
-                        KeyData {
-                            key_code: event::KeyCode::Backspace,
-                            ..
-                        }
-                        | KeyData {
-                            key_code: event::KeyCode::Char('\u{8}'),
-                            ..
-                        }
-                        | KeyData {
-                            key_code: event::KeyCode::Char('\u{7f}'),
-                            ..
-                        }
-                        | KeyData {
-                            key_code: event::KeyCode::Char('h'),
-                            modifiers: KeyModifiers::CONTROL,
-                            ..
-                        } => {
-                            editor.delete_last_ex_command_char();
-                        }
-                        _ => {
-                            editor.append_ex_command(key_data);
-                        }
-                    }
-                } else if editor.is_insert_mode() {
-                    let key_data: KeyData = key_event.into();
-                    match key_data {
-                        KeyData {
-                            key_code: event::KeyCode::Enter,
-                            ..
-                        } => {
-                            editor.append_new_line()?;
-                        }
-                        KeyData {
-                            key_code: event::KeyCode::Esc,
-                            ..
-                        } => {
-                            editor.set_command_mode();
-                            editor.status_line = "".to_string();
-                        }
-                        KeyData {
-                            key_code: event::KeyCode::Backspace,
-                            ..
-                        }
-                        | KeyData {
-                            key_code: event::KeyCode::Char('h'),
-                            modifiers: KeyModifiers::CONTROL,
-                        } => {
-                            editor.backward_delete_char()?;
-                        }
-                        KeyData {
-                            key_code: event::KeyCode::Char('l'),
-                            modifiers: KeyModifiers::CONTROL,
-                            ..
-                        } => {
-                            editor.render(&mut stdout)?;
-                        }
-                        _ => {
-                            if let crossterm::event::KeyCode::Char(c) = key_event.code {
-                                editor.insert_char(c)?;
-                            }
-                        }
-                    }
+    'events: loop {
+        editor.render(backend)?;
+        loop {
+            // Keys that may be the start of a longer mapping or command wait
+            // for the rest of it.
+            let waiting = editor.options.timeout
+                && (!editor.typeahead.is_empty()
+                    || matches!(compose(&event_keys), InputState::Ambiguous(_)));
+            let wait = if waiting {
+                Duration::from_millis(editor.options.timeout_len)
+            } else {
+                UPDATE_TIME
+            };
+            if backend.poll_event(wait)? {
+                break;
+            }
+            if waiting {
+                handle_timeout(editor, &mut event_keys)?;
+                if editor.should_exit {
+                    break 'events;
                 }
+                editor.render(backend)?;
+                continue;
             }
-            Ok(Event::Resize(width, height)) => {
-                editor.resize_terminal(width, height);
+            editor.update_swap_file();
+            if editor.check_file_changed() {
+                editor.render(backend)?;
             }
-            _ => {
-                info!("Other event: {:?}", result)
+        }
+        let Some(event) = backend.read_event()? else {
+            return Ok(());
+        };
+        handle_event(editor, &mut event_keys, event)?;
+        if editor.should_exit {
+            break;
+        }
+    }
+    editor.remove_swap_files();
+
+    Ok(())
+}
+
+// `event_keys` are the keys of the command being typed.
+fn handle_event(
+    editor: &mut Editor,
+    event_keys: &mut Vec<KeyEvent>,
+    event: Event,
+) -> GenericResult<()> {
+    match event {
+        Event::Key(key_event) => {
+            editor.record_key(key_event.into());
+            editor.typeahead.push_back(TypeaheadKey {
+                key: key_event,
+                remap: true,
+            });
+            handle_typeahead(editor, event_keys, false)?;
+            editor.record_key_for_swap();
+        }
+        Event::FocusGained => {
+            editor.check_file_changed();
+        }
+        Event::Resize(width, height) => {
+            editor.resize_terminal(width, height);
+        }
+        _ => {
+            info!("Other event: {:?}", event)
+        }
+    }
+    Ok(())
+}
+
+// No key came for 'timeoutlen' after keys that may be the start of a longer
+// mapping or command: they mean what they are by themselves.
+fn handle_timeout(editor: &mut Editor, event_keys: &mut Vec<KeyEvent>) -> GenericResult<()> {
+    if !editor.typeahead.is_empty() {
+        return handle_typeahead(editor, event_keys, true);
+    }
+    if let InputState::Ambiguous(command_data) = compose(event_keys) {
+        execute_command(editor, event_keys, command_data);
+    }
+    Ok(())
+}
+
+// Handles the keys of the typeahead, putting what mappings are mapped to in
+// place of their keys. Keys that may be the start of a longer mapping are left
+// to wait for more keys, unless the wait has `timed_out`.
+fn handle_typeahead(
+    editor: &mut Editor,
+    event_keys: &mut Vec<KeyEvent>,
+    timed_out: bool,
+) -> GenericResult<()> {
+    let mut depth = 0;
+    while let Some(first) = editor.typeahead.front().copied() {
+        if let Some(mode) = editor.map_mode().filter(|_| first.remap) {
+            let keys: Vec<KeyData> = editor
+                .typeahead
+                .iter()
+                .take_while(|key| key.remap)
+                .map(|key| key.key.into())
+                .collect();
+            // Typed keys may go on with a longer mapping, the keys of another
+            // mapping may not.
+            let more_may_come = !timed_out && keys.len() == editor.typeahead.len();
+            let mapping = match editor.mappings.lookup(mode, &keys) {
+                Lookup::Found(mapping, _) => Some(mapping),
+                Lookup::Prefix | Lookup::Ambiguous(_) if more_may_come => break,
+                Lookup::Ambiguous(mapping) => Some(mapping),
+                Lookup::Prefix | Lookup::NotFound => None,
+            };
+            if let Some(mapping) = mapping {
+                depth += 1;
+                if depth > MAX_MAP_DEPTH {
+                    editor.typeahead.clear();
+                    editor.status_line = "E223: Recursive mapping".to_string();
+                    break;
+                }
+                put_mapping(editor, &mapping);
+                continue;
             }
         }
+        editor.typeahead.pop_front();
+        handle_key(editor, event_keys, first.key)?;
         if editor.should_exit {
             break;
         }
     }
+    let waiting = editor.typeahead.iter().map(|key| &key.key);
+    editor.pending_keys = pending_keys(event_keys.iter().chain(waiting));
+    Ok(())
+}
 
-    terminal::disable_raw_mode()?;
-    stdout.execute(terminal::Clear(ClearType::All))?;
-    stdout.execute(terminal::LeaveAlternateScreen)?;
-    stdout.flush()?;
+// Puts the keys `mapping` is mapped to in place of its own.
+fn put_mapping(editor: &mut Editor, mapping: &Mapping) {
+    editor.typeahead.drain(..mapping.lhs.len());
+    // Like vim, when the keys start with the mapped keys, the first one is
+    // not mapped again, so that `:map j jzz` is not endless.
+    let starts_with_lhs = mapping.rhs.starts_with(&mapping.lhs);
+    for (i, key) in mapping.rhs.iter().enumerate().rev() {
+        editor.typeahead.push_front(TypeaheadKey {
+            key: KeyEvent::new(key.key_code, key.modifiers),
+            remap: !mapping.noremap && (i > 0 || !starts_with_lhs),
+        });
+    }
+}
 
+// Handles a key after mappings, in the pager or in the current mode.
+fn handle_key(
+    editor: &mut Editor,
+    event_keys: &mut Vec<KeyEvent>,
+    key_event: KeyEvent,
+) -> GenericResult<()> {
+    let page = editor.pager_height();
+    if let Some(pager) = &mut editor.pager {
+        match pager.handle_key(key_event.into(), page) {
+            PagerAction::Stay => return Ok(()),
+            PagerAction::Close => {
+                editor.pager = None;
+                return Ok(());
+            }
+            PagerAction::CloseAndHandle => editor.pager = None,
+        }
+    }
+    if editor.is_command_mode() {
+        info!("Key event: {:?}", key_event);
+        if event_keys.is_empty() && key_event.code == event::KeyCode::Char(':') {
+            // ex command begin
+            editor.set_ex_command_mode();
+            editor.status_line = ":".to_string();
+        } else if event_keys.is_empty()
+            && key_event.code == event::KeyCode::Char('q')
+            && !key_event.modifiers.contains(KeyModifiers::CONTROL)
+            && editor.recording.is_some()
+        {
+            editor.stop_recording();
+        } else {
+            compose_key(editor, event_keys, key_event);
+        }
+    } else if editor.is_ex_command_mode() {
+        let key_data: KeyData = key_event.into();
+        match key_data {
+            KeyData {
+                key_code: event::KeyCode::Enter,
+                ..
+            } => {
+                let command_data = editor.get_ex_command_data();
+                editor.set_command_mode();
+                editor.execute_ex_command(command_data)?;
+            }
+            KeyData {
+                key_code: event::KeyCode::Esc,
+                ..
+            } => {
+                editor.set_command_mode();
+                editor.status_line = "".to_string();
+            }
+            KeyData {
+                key_code: event::KeyCode::Backspace,
+                ..
+            }
+            | KeyData {
+                key_code: event::KeyCode::Char('\u{8}'),
+                ..
+            }
+            | KeyData {
+                key_code: event::KeyCode::Char('\u{7f}'),
+                ..
+            }
+            | KeyData {
+                key_code: event::KeyCode::Char('h'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                editor.delete_last_ex_command_char();
+            }
+            _ => {
+                editor.append_ex_command(key_data);
+            }
+        }
+    } else if editor.is_insert_mode() {
+        let key_data: KeyData = key_event.into();
+        match key_data {
+            KeyData {
+                key_code: event::KeyCode::Enter,
+                ..
+            } => {
+                editor.append_new_line()?;
+            }
+            KeyData {
+                key_code: event::KeyCode::Esc,
+                ..
+            } => {
+                editor.set_command_mode();
+                editor.status_line = "".to_string();
+            }
+            KeyData {
+                key_code: event::KeyCode::Backspace,
+                ..
+            }
+            | KeyData {
+                key_code: event::KeyCode::Char('h'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                editor.backward_delete_char()?;
+            }
+            KeyData {
+                key_code: event::KeyCode::Char('l'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                editor.renderer.invalidate();
+            }
+            _ => {
+                if let crossterm::event::KeyCode::Char(c) = key_event.code {
+                    editor.insert_char(c)?;
+                }
+            }
+        }
+    }
     Ok(())
 }
+
+// Adds `key_event` to the keys of the command being typed, and executes the
+// command when it is complete.
+fn compose_key(editor: &mut Editor, event_keys: &mut Vec<KeyEvent>, key_event: KeyEvent) {
+    event_keys.push(key_event);
+    let input_state = compose(event_keys);
+    match input_state {
+        InputState::CommandCompleted(command_data) => {
+            info!("Command completed: {:?}", command_data);
+            execute_command(editor, event_keys, command_data);
+        }
+        InputState::CommandInvalid(key_codes) => {
+            event_keys.pop();
+            if let InputState::Ambiguous(command_data) = compose(event_keys) {
+                // The shorter command was meant, and the key begins another.
+                execute_command(editor, event_keys, command_data);
+                compose_key(editor, event_keys, key_event);
+                return;
+            }
+            //　TODO: error message
+            error!("Invalid command: {:?}", key_codes);
+            event_keys.clear();
+            editor.pending_keys.clear();
+        }
+        _ => {
+            info!("Input state: {:?}", input_state);
+        }
+    }
+}
+
+fn execute_command(editor: &mut Editor, event_keys: &mut Vec<KeyEvent>, command_data: CommandData) {
+    if let Err(e) = editor.execute_command(command_data) {
+        error!("Command failed: {}", e);
+        editor.status_line = e.to_string();
+    }
+    event_keys.clear();
+    editor.pending_keys.clear();
+}
+
+// The keys of a command being typed as text for 'showcmd', control keys as
+// control characters.
+fn pending_keys<'a>(event_keys: impl Iterator<Item = &'a KeyEvent>) -> String {
+    event_keys
+        .filter_map(|key| match key.code {
+            event::KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(((c as u8) & 0x1f) as char)
+            }
+            event::KeyCode::Char(c) => Some(c),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,200 @@
+use crossterm::event::KeyEvent;
+
+use crate::command::compose::KeyData;
+use crate::command::keymap::{key_names, parse_keys, Keymap, Lookup};
+use crate::generic_error::GenericResult;
+
+// The modes mappings are made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapMode {
+    Normal,
+    Insert,
+    CommandLine,
+}
+
+// The modes of `:map`, `:nmap`, `:map!`, `:imap` and `:cmap`, and the letter
+// their mappings are listed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapModes {
+    // `:map`; in vim also visual and operator-pending mode.
+    All,
+    Normal,
+    // `:map!`
+    InsertAndCommandLine,
+    Insert,
+    CommandLine,
+}
+
+impl MapModes {
+    pub fn modes(self) -> &'static [MapMode] {
+        match self {
+            MapModes::All | MapModes::Normal => &[MapMode::Normal],
+            MapModes::InsertAndCommandLine => &[MapMode::Insert, MapMode::CommandLine],
+            MapModes::Insert => &[MapMode::Insert],
+            MapModes::CommandLine => &[MapMode::CommandLine],
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            MapModes::All => ' ',
+            MapModes::Normal => 'n',
+            MapModes::InsertAndCommandLine => '!',
+            MapModes::Insert => 'i',
+            MapModes::CommandLine => 'c',
+        }
+    }
+}
+
+// A mapping: typing `lhs` is typing `rhs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub lhs: Vec<KeyData>,
+    pub rhs: Vec<KeyData>,
+    // Made with `:noremap` and the like: `rhs` is not mapped again.
+    pub noremap: bool,
+    // The modes of the command that made it.
+    pub modes: MapModes,
+}
+
+impl Mapping {
+    // A line of `:map`, like `n  ,w          * :w<CR>`, with `*` when the keys
+    // are not mapped again.
+    fn listing(&self) -> String {
+        format!(
+            "{}  {:<12} {} {}",
+            self.modes.letter(),
+            key_names(&self.lhs),
+            if self.noremap { '*' } else { ' ' },
+            key_names(&self.rhs)
+        )
+    }
+}
+
+// A key to be handled, and whether mappings apply to it: they do not to the
+// keys a `:noremap` mapping is typing.
+#[derive(Clone, Copy, Debug)]
+pub struct TypeaheadKey {
+    pub key: KeyEvent,
+    pub remap: bool,
+}
+
+// The mappings of each mode.
+#[derive(Default)]
+pub struct Mappings {
+    normal: Keymap<Mapping>,
+    insert: Keymap<Mapping>,
+    command_line: Keymap<Mapping>,
+}
+
+impl Mappings {
+    fn keymap(&self, mode: MapMode) -> &Keymap<Mapping> {
+        match mode {
+            MapMode::Normal => &self.normal,
+            MapMode::Insert => &self.insert,
+            MapMode::CommandLine => &self.command_line,
+        }
+    }
+
+    fn keymap_mut(&mut self, mode: MapMode) -> &mut Keymap<Mapping> {
+        match mode {
+            MapMode::Normal => &mut self.normal,
+            MapMode::Insert => &mut self.insert,
+            MapMode::CommandLine => &mut self.command_line,
+        }
+    }
+
+    /// Maps `lhs` to `rhs`, both in the notation of `parse_keys`.
+    pub fn add(&mut self, modes: MapModes, lhs: &str, rhs: &str, noremap: bool) {
+        let mapping = Mapping {
+            lhs: parse_keys(lhs),
+            rhs: parse_keys(rhs),
+            noremap,
+            modes,
+        };
+        for mode in modes.modes() {
+            self.keymap_mut(*mode).add_keys(&mapping.lhs, mapping.clone());
+        }
+    }
+
+    /// Removes the mapping of `lhs` in `modes`.
+    pub fn remove(&mut self, modes: MapModes, lhs: &str) -> GenericResult<()> {
+        let keys = parse_keys(lhs);
+        let mut removed = false;
+        for mode in modes.modes() {
+            removed |= self.keymap_mut(*mode).remove(&keys).is_some();
+        }
+        if removed {
+            Ok(())
+        } else {
+            Err("E31: No such mapping".into())
+        }
+    }
+
+    pub fn lookup(&self, mode: MapMode, keys: &[KeyData]) -> Lookup<Mapping> {
+        self.keymap(mode).lookup(keys)
+    }
+
+    /// Returns the lines `:map` shows for the mappings in `modes` whose keys
+    /// start with `prefix`.
+    pub fn list(&self, modes: MapModes, prefix: &str) -> Vec<String> {
+        let prefix = parse_keys(prefix);
+        let mut mappings: Vec<&Mapping> = modes
+            .modes()
+            .iter()
+            .flat_map(|mode| self.keymap(*mode).bindings())
+            .filter(|mapping| mapping.lhs.starts_with(&prefix))
+            .collect();
+        mappings.sort_by_key(|mapping| (key_names(&mapping.lhs), mapping.modes.letter()));
+        // `:map!` mappings are in two keymaps.
+        mappings.dedup_by_key(|mapping| (key_names(&mapping.lhs), mapping.modes.letter()));
+        mappings.iter().map(|mapping| mapping.listing()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mappings() {
+        let mut mappings = Mappings::default();
+        mappings.add(MapModes::All, ",w", ":w<cr>", true);
+        mappings.add(MapModes::InsertAndCommandLine, "jk", "<Esc>", false);
+        mappings.add(MapModes::Normal, "Q", "gq", false);
+
+        assert!(matches!(
+            mappings.lookup(MapMode::Normal, &parse_keys(",")),
+            Lookup::Prefix
+        ));
+        assert!(matches!(
+            mappings.lookup(MapMode::Insert, &parse_keys("jk")),
+            Lookup::Found(_, 2)
+        ));
+        assert!(matches!(
+            mappings.lookup(MapMode::Normal, &parse_keys("jk")),
+            Lookup::NotFound
+        ));
+
+        assert_eq!(
+            mappings.list(MapModes::All, ""),
+            [
+                "   ,w           * :w<CR>",
+                "n  Q              gq",
+            ]
+        );
+        assert_eq!(mappings.list(MapModes::All, "Q"), ["n  Q              gq"]);
+        assert_eq!(mappings.list(MapModes::InsertAndCommandLine, ""), ["!  jk             <Esc>"]);
+
+        mappings.remove(MapModes::Insert, "jk").unwrap();
+        assert!(matches!(
+            mappings.lookup(MapMode::Insert, &parse_keys("jk")),
+            Lookup::NotFound
+        ));
+        assert_eq!(mappings.list(MapModes::CommandLine, "").len(), 1);
+        assert_eq!(
+            mappings.remove(MapModes::Insert, "jk").unwrap_err().to_string(),
+            "E31: No such mapping"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+
+// The options that are global, not local to a buffer or a window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    // Like vim's 'sidescroll': the fewest columns to scroll sideways when the
+    // cursor leaves the window; 0 puts the cursor in the middle.
+    pub side_scroll: usize,
+    // Like vim's 'sidescrolloff': the columns kept left and right of the
+    // cursor when lines do not wrap.
+    pub side_scroll_off: usize,
+    // Like vim's 'tabstop': tabs reach the next multiple of this column.
+    pub tab_stop: usize,
+    pub list_chars: ListChars,
+    // Like vim's 'showmode': `-- INSERT --` on the command line.
+    pub show_mode: bool,
+    // Like vim's 'showcmd': the keys of a command being typed on the command
+    // line.
+    pub show_cmd: bool,
+    // Like vim's 'ruler': the cursor position in the status line, or on the
+    // command line when the window has none.
+    pub ruler: bool,
+    // Like vim's 'statusline': the format of the status lines, the file name
+    // and the modified flag when empty.
+    pub status_line: String,
+    // Like vim's 'timeout' and 'timeoutlen': keys that are a mapping or a
+    // command, and also the start of a longer one, wait this many
+    // milliseconds for the rest of the longer one, or forever without
+    // 'timeout'.
+    pub timeout: bool,
+    pub timeout_len: u64,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            side_scroll: 0,
+            side_scroll_off: 0,
+            tab_stop: 8,
+            list_chars: ListChars::default(),
+            show_mode: true,
+            show_cmd: false,
+            ruler: false,
+            status_line: String::new(),
+            timeout: true,
+            timeout_len: 1000,
+        }
+    }
+}
+
+// Like vim's 'listchars': how `:set list` shows tabs, trailing spaces, the
+// ends of lines and non-breaking spaces. `extends` and `precedes` mark lines
+// that continue off the window when lines do not wrap, with or without `list`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListChars {
+    pub eol: Option<char>,
+    // The first character of a tab, the one repeated after it and the last
+    // one, if given.
+    pub tab: Option<(char, char, Option<char>)>,
+    pub trail: Option<char>,
+    pub nbsp: Option<char>,
+    pub extends: Option<char>,
+    pub precedes: Option<char>,
+}
+
+impl Default for ListChars {
+    fn default() -> ListChars {
+        ListChars::parse("eol:$,extends:>,precedes:<").expect("the default listchars is valid")
+    }
+}
+
+impl ListChars {
+    /// Parses a comma separated list of `name:chars`, like
+    /// `tab:>-,trail:-,eol:$`.
+    pub fn parse(value: &str) -> Option<ListChars> {
+        let mut list_chars = ListChars {
+            eol: None,
+            tab: None,
+            trail: None,
+            nbsp: None,
+            extends: None,
+            precedes: None,
+        };
+        for item in value.split(',').filter(|item| !item.is_empty()) {
+            let (name, chars) = item.split_once(':')?;
+            let chars: Vec<char> = chars.chars().collect();
+            let one = || match chars[..] {
+                [c] => Some(c),
+                _ => None,
+            };
+            match name {
+                "eol" => list_chars.eol = Some(one()?),
+                "trail" => list_chars.trail = Some(one()?),
+                "nbsp" => list_chars.nbsp = Some(one()?),
+                "extends" => list_chars.extends = Some(one()?),
+                "precedes" => list_chars.precedes = Some(one()?),
+                "tab" => match chars[..] {
+                    [first, fill] => list_chars.tab = Some((first, fill, None)),
+                    [first, fill, last] => list_chars.tab = Some((first, fill, Some(last))),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        Some(list_chars)
+    }
+}
+
+impl fmt::Display for ListChars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut items = Vec::new();
+        if let Some(c) = self.eol {
+            items.push(format!("eol:{}", c));
+        }
+        if let Some((first, fill, last)) = self.tab {
+            items.push(format!(
+                "tab:{}{}{}",
+                first,
+                fill,
+                last.map_or(String::new(), String::from)
+            ));
+        }
+        for (name, c) in [
+            ("trail", self.trail),
+            ("nbsp", self.nbsp),
+            ("extends", self.extends),
+            ("precedes", self.precedes),
+        ] {
+            if let Some(c) = c {
+                items.push(format!("{}:{}", name, c));
+            }
+        }
+        write!(f, "{}", items.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_chars() {
+        let list_chars = ListChars::parse("tab:>-,trail:~,eol:$").unwrap();
+        assert_eq!(list_chars.tab, Some(('>', '-', None)));
+        assert_eq!(list_chars.trail, Some('~'));
+        assert_eq!(list_chars.extends, None);
+        assert_eq!(list_chars.to_string(), "eol:$,tab:>-,trail:~");
+        assert_eq!(
+            ListChars::parse("tab:<->,nbsp:+").unwrap().tab,
+            Some(('<', '-', Some('>')))
+        );
+
+        assert_eq!(ListChars::parse("tab:>"), None);
+        assert_eq!(ListChars::parse("eol:ab"), None);
+        assert_eq!(ListChars::parse("space:."), None);
+        assert_eq!(
+            ListChars::default().to_string(),
+            "eol:$,extends:>,precedes:<"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::command::compose::KeyData;
+use crate::util::get_char_width_at;
+
+// Output of ex commands that is longer than the command line, like the lines
+// of `:1,20p`. It scrolls up from the bottom of the screen a page at a time,
+// with `-- More --` at the bottom while there is more to show.
+#[derive(Debug)]
+pub struct Pager {
+    // The output as screen rows: long lines are wrapped and tabs expanded.
+    rows: Vec<String>,
+    // How many rows have been shown; the last page of them is on the screen.
+    shown: usize,
+}
+
+// What a key typed to the pager does.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PagerAction {
+    // The pager stays, maybe scrolled.
+    Stay,
+    // The pager goes away.
+    Close,
+    // The pager goes away and the key is handled as usual, like `:` at
+    // "Press ENTER or type command to continue".
+    CloseAndHandle,
+}
+
+impl Pager {
+    /// Makes a pager showing the first `page` rows of `lines`, wrapped at
+    /// `width` columns.
+    pub fn new(lines: &[String], width: usize, tab_stop: usize, page: usize) -> Pager {
+        let mut rows = Vec::new();
+        for line in lines {
+            let mut row = String::new();
+            let mut col = 0;
+            for c in line.chars() {
+                let mut char_width = get_char_width_at(c, col, tab_stop) as usize;
+                if col + char_width > width && col > 0 {
+                    rows.push(std::mem::take(&mut row));
+                    col = 0;
+                    char_width = get_char_width_at(c, col, tab_stop) as usize;
+                }
+                if c == '\t' {
+                    row.extend(std::iter::repeat_n(' ', char_width));
+                } else {
+                    row.push(c);
+                }
+                col += char_width;
+            }
+            rows.push(row);
+        }
+        let shown = rows.len().min(page.max(1));
+        Pager { rows, shown }
+    }
+
+    /// Returns the rows on a screen with `page` rows for them.
+    pub fn visible_rows(&self, page: usize) -> &[String] {
+        &self.rows[self.shown.saturating_sub(page.max(1))..self.shown]
+    }
+
+    /// Returns whether all the rows have been shown.
+    pub fn is_at_end(&self) -> bool {
+        self.shown == self.rows.len()
+    }
+
+    /// Returns the prompt at the bottom of the screen.
+    pub fn prompt(&self) -> &'static str {
+        if self.is_at_end() {
+            "Press ENTER or type command to continue"
+        } else {
+            "-- More --"
+        }
+    }
+
+    /// Scrolls for `key`, with `page` rows on the screen: Space and `f` show
+    /// the next page, Enter and `j` the next line, `d` the next half page, `b`,
+    /// `k` and `u` go back and `G` to the end. `q` and Esc close the pager.
+    pub fn handle_key(&mut self, key: KeyData, page: usize) -> PagerAction {
+        let page = page.max(1);
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.key_code {
+                KeyCode::Char('c') => PagerAction::Close,
+                _ => PagerAction::Stay,
+            };
+        }
+        let at_end = self.is_at_end();
+        match key.key_code {
+            KeyCode::Char('q') | KeyCode::Esc => return PagerAction::Close,
+            KeyCode::Enter | KeyCode::Char(' ') if at_end => return PagerAction::Close,
+            KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => {
+                self.scroll_down(page)
+            }
+            KeyCode::Enter | KeyCode::Char('j') | KeyCode::Down => self.scroll_down(1),
+            KeyCode::Char('d') => self.scroll_down(page.div_ceil(2)),
+            KeyCode::Char('G') => self.shown = self.rows.len(),
+            KeyCode::Char('b') | KeyCode::PageUp => self.scroll_up(page, page),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_up(1, page),
+            KeyCode::Char('u') => self.scroll_up(page.div_ceil(2), page),
+            KeyCode::Char(':') => return PagerAction::CloseAndHandle,
+            _ if at_end => return PagerAction::CloseAndHandle,
+            _ => {}
+        }
+        PagerAction::Stay
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        self.shown = (self.shown + count).min(self.rows.len());
+    }
+
+    // The first page stays on the screen.
+    fn scroll_up(&mut self, count: usize, page: usize) {
+        self.shown = self
+            .shown
+            .saturating_sub(count)
+            .max(page.min(self.rows.len()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyData {
+        KeyData {
+            key_code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_pager_pages() {
+        let lines: Vec<String> = (1..=7).map(|n| n.to_string()).collect();
+        let mut pager = Pager::new(&lines, 10, 8, 3);
+        assert_eq!(pager.visible_rows(3), ["1", "2", "3"]);
+        assert_eq!(pager.prompt(), "-- More --");
+
+        assert_eq!(pager.handle_key(key('j'), 3), PagerAction::Stay);
+        assert_eq!(pager.visible_rows(3), ["2", "3", "4"]);
+        assert_eq!(pager.handle_key(key(' '), 3), PagerAction::Stay);
+        assert_eq!(pager.visible_rows(3), ["5", "6", "7"]);
+        assert!(pager.is_at_end());
+        assert_eq!(pager.prompt(), "Press ENTER or type command to continue");
+
+        assert_eq!(pager.handle_key(key('b'), 3), PagerAction::Stay);
+        assert_eq!(pager.visible_rows(3), ["2", "3", "4"]);
+        assert_eq!(pager.handle_key(key('b'), 3), PagerAction::Stay);
+        assert_eq!(pager.visible_rows(3), ["1", "2", "3"]);
+
+        // `:` at the prompt starts a command line, other keys do only at the end
+        assert_eq!(pager.handle_key(key('x'), 3), PagerAction::Stay);
+        assert_eq!(pager.handle_key(key('G'), 3), PagerAction::Stay);
+        assert_eq!(pager.handle_key(key('x'), 3), PagerAction::CloseAndHandle);
+        assert_eq!(pager.handle_key(key('q'), 3), PagerAction::Close);
+    }
+
+    #[test]
+    fn test_pager_wraps_rows() {
+        let lines = vec!["abcdef".to_string(), "\tx".to_string(), "".to_string()];
+        let pager = Pager::new(&lines, 4, 2, 10);
+        assert_eq!(pager.visible_rows(10), ["abcd", "ef", "  x", ""]);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use crate::generic_error::GenericResult;
+
+// The text of a register and whether it is whole lines, which `p` puts below
+// the cursor line, or characters, which it puts after the cursor.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+impl Register {
+    pub fn new(text: &str, linewise: bool) -> Self {
+        Register {
+            text: text.to_string(),
+            linewise,
+        }
+    }
+
+    // Like vim, appending lines to characters or characters to lines makes
+    // lines of both.
+    fn append(&mut self, other: &Register) {
+        if self.linewise || other.linewise {
+            if !self.linewise && !self.text.is_empty() {
+                self.text.push('\n');
+            }
+            self.text.push_str(&other.text);
+            if !self.text.ends_with('\n') {
+                self.text.push('\n');
+            }
+            self.linewise = true;
+        } else {
+            self.text.push_str(&other.text);
+        }
+    }
+}
+
+// Whether text can be stored in the register `name` with `"x`: the unnamed
+// register `"`, the named ones, `A` to `Z` appending to `a` to `z`, the
+// numbered ones, the small delete register `-` and the black hole `_`.
+fn is_writable(name: char) -> bool {
+    name.is_ascii_alphanumeric() || matches!(name, '"' | '-' | '_')
+}
+
+/// Fails unless text can be stored in the register named with `"x`, which
+/// commands check before changing the buffer.
+pub fn check_writable(name: Option<char>) -> GenericResult<()> {
+    match name {
+        Some(name) if !is_writable(name) => {
+            Err(format!("E354: Invalid register name: '{}'", name).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// The registers deleted and yanked text goes to, by name.
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    /// Returns the register `name`, `A` to `Z` being `a` to `z`.
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    /// Stores deleted text, unless there is none. Without `"x` it goes to `"1`, shifting the
+    /// numbered registers, when it is lines or more than one line, and to
+    /// `"-` when it is less.
+    pub fn delete(&mut self, name: Option<char>, register: Register) -> GenericResult<()> {
+        if register.text.is_empty() {
+            return Ok(());
+        }
+        let many_lines = register.linewise || register.text.contains('\n');
+        match name {
+            Some('_') => return Ok(()),
+            Some(name) if name != '"' => {
+                self.store(name, &register)?;
+                if many_lines {
+                    self.shift_numbered(register);
+                }
+            }
+            _ => {
+                if many_lines {
+                    self.shift_numbered(register.clone());
+                } else {
+                    self.registers.insert('-', register.clone());
+                }
+                self.registers.insert('"', register);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores yanked text, unless there is none, without `"x` in `"0`.
+    pub fn yank(&mut self, name: Option<char>, register: Register) -> GenericResult<()> {
+        if register.text.is_empty() {
+            return Ok(());
+        }
+        match name {
+            Some('_') => Ok(()),
+            Some(name) if name != '"' => self.store(name, &register),
+            _ => {
+                self.registers.insert('0', register.clone());
+                self.registers.insert('"', register);
+                Ok(())
+            }
+        }
+    }
+
+    /// Stores the keys of a macro recorded with `q`, as characters; unlike a
+    /// yank, it leaves the unnamed register as it was.
+    pub fn record(&mut self, name: char, text: &str) {
+        let register = Register::new(text, false);
+        let lower = name.to_ascii_lowercase();
+        if name.is_ascii_uppercase() {
+            self.registers.entry(lower).or_default().append(&register);
+        } else {
+            self.registers.insert(lower, register);
+        }
+    }
+
+    // Stores `register` in the register `name` and in the unnamed one, which
+    // gets all of `"a` when `"A` is appended to.
+    fn store(&mut self, name: char, register: &Register) -> GenericResult<()> {
+        check_writable(Some(name))?;
+        let lower = name.to_ascii_lowercase();
+        if name.is_ascii_uppercase() {
+            self.registers.entry(lower).or_default().append(register);
+        } else {
+            self.registers.insert(lower, register.clone());
+        }
+        let stored = self.registers[&lower].clone();
+        self.registers.insert('"', stored);
+        Ok(())
+    }
+
+    // `"1` moves to `"2` and so on, the text of `"9` being dropped.
+    fn shift_numbered(&mut self, register: Register) {
+        for n in (1..9).rev() {
+            let from = char::from_digit(n, 10).unwrap();
+            let to = char::from_digit(n + 1, 10).unwrap();
+            if let Some(shifted) = self.registers.remove(&from) {
+                self.registers.insert(to, shifted);
+            }
+        }
+        self.registers.insert('1', register);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(registers: &Registers, name: char) -> Option<(&str, bool)> {
+        registers
+            .get(name)
+            .map(|register| (register.text.as_str(), register.linewise))
+    }
+
+    #[test]
+    fn test_delete_and_yank() {
+        let mut registers = Registers::default();
+        registers.delete(None, Register::new("one\n", true)).unwrap();
+        registers.delete(None, Register::new("two\n", true)).unwrap();
+        registers.delete(None, Register::new("w", false)).unwrap();
+        assert_eq!(text(&registers, '1'), Some(("two\n", true)));
+        assert_eq!(text(&registers, '2'), Some(("one\n", true)));
+        assert_eq!(text(&registers, '-'), Some(("w", false)));
+        assert_eq!(text(&registers, '"'), Some(("w", false)));
+
+        registers.yank(None, Register::new("y", false)).unwrap();
+        assert_eq!(text(&registers, '0'), Some(("y", false)));
+        assert_eq!(text(&registers, '"'), Some(("y", false)));
+        assert_eq!(text(&registers, '1'), Some(("two\n", true)));
+
+        // `"_` keeps everything as it was
+        registers.delete(Some('_'), Register::new("gone\n", true)).unwrap();
+        assert_eq!(text(&registers, '"'), Some(("y", false)));
+        assert_eq!(text(&registers, '1'), Some(("two\n", true)));
+
+        for n in 0..10 {
+            registers.delete(None, Register::new(&format!("{}\n", n), true)).unwrap();
+        }
+        assert_eq!(text(&registers, '1'), Some(("9\n", true)));
+        assert_eq!(text(&registers, '9'), Some(("1\n", true)));
+    }
+
+    #[test]
+    fn test_named_registers() {
+        let mut registers = Registers::default();
+        registers.yank(Some('a'), Register::new("one", false)).unwrap();
+        registers.yank(Some('A'), Register::new("two", false)).unwrap();
+        assert_eq!(text(&registers, 'a'), Some(("onetwo", false)));
+        assert_eq!(text(&registers, '"'), Some(("onetwo", false)));
+        assert_eq!(text(&registers, '0'), None);
+
+        registers.delete(Some('A'), Register::new("three\n", true)).unwrap();
+        assert_eq!(text(&registers, 'a'), Some(("onetwo\nthree\n", true)));
+        assert_eq!(text(&registers, '1'), Some(("three\n", true)));
+        registers.yank(Some('A'), Register::new("four", false)).unwrap();
+        assert_eq!(text(&registers, 'A'), Some(("onetwo\nthree\nfour\n", true)));
+
+        registers.delete(Some('b'), Register::new("x", false)).unwrap();
+        assert_eq!(text(&registers, 'b'), Some(("x", false)));
+        assert_eq!(text(&registers, '-'), None);
+
+        assert_eq!(
+            registers.yank(Some('.'), Register::new("x", false)).unwrap_err().to_string(),
+            "E354: Invalid register name: '.'"
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let mut registers = Registers::default();
+        registers.yank(None, Register::new("y", false)).unwrap();
+        registers.record('q', "ix\x1b");
+        registers.record('Q', "j");
+        assert_eq!(text(&registers, 'q'), Some(("ix\x1bj", false)));
+        assert_eq!(text(&registers, '"'), Some(("y", false)));
+    }
+}
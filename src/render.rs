@@ -1,74 +1,616 @@
-use std::io::Write;
-
-use crossterm::{
-    cursor,
-    style::{self},
-    terminal, QueueableCommand,
-};
 use log::info;
 
 use crate::{
-    editor::{Editor, TerminalSize},
+    backend::Backend,
+    buffer::Buffer,
+    editor::Editor,
     generic_error::GenericResult,
-    util::get_char_width,
+    options::ListChars,
+    pager::Pager,
+    status_line::{show_cmd_text, RULER_WIDTH, SHOW_CMD_WIDTH},
+    screen::{Screen, Style},
+    theme::{ColorDepth, Group, Theme},
+    util::{get_char_width_at, get_line_width},
+    window::{Rect, WindowOptions},
 };
 
-pub fn render(editor: &mut Editor, stdout: &mut std::io::Stdout) -> GenericResult<()> {
+pub fn render(editor: &mut Editor, backend: &mut impl Backend) -> GenericResult<()> {
     info!("render");
-    let mut stdout = stdout.lock();
-    stdout.queue(terminal::Clear(terminal::ClearType::All))?;
-    stdout.queue(cursor::MoveTo(0, 0))?;
+    let frame = draw(editor);
+    editor.ring_bell = false;
+    editor.renderer.flush(backend, frame)
+}
 
-    let mut cursor_position_on_writing = TerminalSize {
-        width: 0,
-        height: 0,
+// A drawn screen and what the renderer needs to know to show it.
+pub struct Frame {
+    pub screen: Screen,
+    // The terminal position of the cursor, (column, row).
+    pub cursor: (u16, u16),
+    // The rows of the full width windows, which the terminal can scroll.
+    pub scroll_regions: Vec<(u16, u16)>,
+    // The command line is written whole every time, so a message that is
+    // shown again is printed again.
+    pub message_row: Option<u16>,
+    pub bell: bool,
+}
+
+// Draws the windows and the command line into a new screen.
+fn draw(editor: &Editor) -> Frame {
+    let mut screen = Screen::new(editor.terminal_size.width, editor.terminal_size.height);
+    let mut scroll_regions = Vec::new();
+    let colors = Colors {
+        theme: &editor.theme,
+        depth: editor.color_depth,
     };
-    let start_row: usize = editor.window_position_in_buffer.row;
-    let lines = &editor.buffer.lines;
-    for line in &lines[start_row..] {
-        for c in line.chars() {
-            // check if c is double width character
-            let char_width = get_char_width(c);
-            stdout.queue(style::Print(c))?;
-            cursor_position_on_writing.width += char_width as u16;
-            if cursor_position_on_writing.width >= editor.terminal_size.width {
-                cursor_position_on_writing.width = 0;
-                cursor_position_on_writing.height += 1;
-                stdout.queue(cursor::MoveTo(0, cursor_position_on_writing.height))?;
-            }
-            if cursor_position_on_writing.height >= editor.content_height() {
-                break;
+
+    let windows = editor.windows();
+    let current_id = windows.current_id();
+    for (id, rect) in windows.rects() {
+        let text_height = windows.text_height(id);
+        let window = windows.window(id);
+        let (window_position, cursor_row) = if id == current_id {
+            (
+                editor.window_position_in_buffer,
+                editor.cursor_position_in_buffer.row,
+            )
+        } else {
+            (
+                window.cursor_data.window_position_in_buffer,
+                window.cursor_data.cursor_position_in_buffer.row,
+            )
+        };
+        let (buffer, _, _) = editor.window_buffer(id);
+        let view = View {
+            start_row: window_position.row,
+            left_col: window_position.col,
+            cursor_row,
+            options: window.options,
+            number_width: editor.number_width(id),
+            tab_stop: editor.options.tab_stop,
+            list_chars: &editor.options.list_chars,
+        };
+        draw_text(&mut screen, buffer, &view, &colors, rect, text_height);
+        if rect.col == 0 && rect.width == screen.width() {
+            scroll_regions.push((rect.row, rect.row + text_height));
+        }
+
+        // draw the status line of the window
+        if windows.has_status_lines() {
+            let status = status_line_text(editor, id, rect.width);
+            let style = if id == current_id {
+                colors.style(Group::StatusLine)
+            } else {
+                colors.style(Group::StatusLineNC)
+            };
+            let row = rect.row + text_height;
+            let width = screen.put_str_within(row, rect.col, rect.width, &status, style);
+            let rest = rect.width.saturating_sub(width);
+            screen.fill(row, rect.col + width, rest, ' ', style);
+        }
+
+        // draw the separator on the right of the window
+        if rect.col + rect.width < screen.width() {
+            for row in rect.row..rect.row + rect.height {
+                screen.put_char(
+                    row,
+                    rect.col + rect.width,
+                    '|',
+                    colors.style(Group::VertSplit),
+                );
             }
         }
-        cursor_position_on_writing.width = 0;
-        cursor_position_on_writing.height += 1;
-        stdout.queue(cursor::MoveTo(0, cursor_position_on_writing.height))?;
     }
 
-    // render status line
-    cursor_position_on_writing.width = 0;
-    cursor_position_on_writing.height = editor.content_height();
-    stdout.queue(cursor::MoveTo(0, cursor_position_on_writing.height))?;
-    for c in editor.status_line.chars() {
-        stdout.queue(style::Print(c))?;
-        let char_width = get_char_width(c);
-        cursor_position_on_writing.width += char_width;
+    // draw the command line: the message or the mode, then the ruler and the
+    // keys of the command being typed on the right
+    let status_row = screen.height().saturating_sub(1);
+    let message_width = match editor.mode_message() {
+        Some(mode) if editor.status_line.is_empty() => {
+            screen.put_str(status_row, 0, &mode, colors.style(Group::ModeMsg))
+        }
+        _ => screen.put_str(
+            status_row,
+            0,
+            &editor.status_line,
+            colors.style(message_group(&editor.status_line)),
+        ),
+    };
+    if !editor.is_ex_command_mode() {
+        let normal = colors.style(Group::Normal);
+        let mut right_col = screen.width();
+        if editor.options.ruler && !windows.has_status_lines() {
+            right_col = right_col.saturating_sub(RULER_WIDTH);
+            if message_width < right_col {
+                let ruler = editor.window_status(current_id).ruler(RULER_WIDTH as usize);
+                screen.put_str(status_row, right_col, &ruler, normal);
+            }
+        }
+        let show_cmd_col = right_col.saturating_sub(SHOW_CMD_WIDTH);
+        if editor.options.show_cmd && message_width < show_cmd_col {
+            let keys = show_cmd_text(&editor.pending_keys);
+            screen.put_str(status_row, show_cmd_col, &keys, normal);
+        }
     }
-    for _ in cursor_position_on_writing.width..editor.terminal_size.width {
-        stdout.queue(style::Print(" "))?;
+
+    if let Some(pager) = &editor.pager {
+        let cursor = draw_pager(&mut screen, pager, editor.pager_height(), &colors);
+        return Frame {
+            screen,
+            cursor,
+            scroll_regions,
+            message_row: Some(status_row),
+            bell: editor.ring_bell,
+        };
     }
 
     // カーソル位置をコンテンツ領域内に制限（ステータス行を超えないように）
+    let rect = windows.rect(current_id);
+    let cursor_col = editor
+        .cursor_position_on_screen
+        .col
+        .saturating_sub(editor.window_position_in_buffer.col as u16);
     let cursor_row = std::cmp::min(
-        editor.cursor_position_on_screen.row as u16,
-        editor.content_height() - 1,
+        editor.cursor_position_on_screen.row,
+        editor.content_height().saturating_sub(1),
     );
-    
-    stdout.queue(cursor::MoveTo(
-        editor.cursor_position_on_screen.col as u16,
-        cursor_row,
-    ))?;
-    stdout.flush()?;
+    Frame {
+        screen,
+        cursor: (
+            rect.col + editor.number_width(current_id) + cursor_col,
+            rect.row + cursor_row,
+        ),
+        scroll_regions,
+        message_row: Some(status_row),
+        bell: editor.ring_bell,
+    }
+}
+
+// The status line of window `id`: the 'statusline' format, or the file name
+// and the modified flag with the ruler on the right.
+fn status_line_text(editor: &Editor, id: usize, width: u16) -> String {
+    let status = editor.window_status(id);
+    if !editor.options.status_line.is_empty() {
+        return status.format(&editor.options.status_line, width as usize);
+    }
+    let mut text = status.name.clone();
+    if status.modified {
+        text.push_str(" [+]");
+    }
+    if editor.options.ruler {
+        // Like vim, the ruler keeps to the right half of a narrow window.
+        let ruler_col = width.saturating_sub(RULER_WIDTH).max(width.div_ceil(2)) as usize;
+        let name: String = text.chars().take(ruler_col.saturating_sub(1)).collect();
+        let ruler = status.ruler(width as usize - ruler_col);
+        text = format!("{:<width$}{}", name, ruler, width = ruler_col);
+    }
+    text
+}
+
+// Draws the rows of the pager at the bottom of the screen, over the windows,
+// and its prompt below them. Returns the position of the cursor, after the
+// prompt.
+fn draw_pager(screen: &mut Screen, pager: &Pager, page: usize, colors: &Colors) -> (u16, u16) {
+    let normal = colors.style(Group::Normal);
+    let rows = pager.visible_rows(page);
+    let prompt_row = screen.height().saturating_sub(1);
+    let top = prompt_row.saturating_sub(rows.len() as u16);
+    for (row, text) in (top..).zip(rows) {
+        screen.fill(row, 0, screen.width(), ' ', normal);
+        screen.put_str(row, 0, text, normal);
+    }
+    let group = if pager.is_at_end() {
+        Group::Question
+    } else {
+        Group::MoreMsg
+    };
+    screen.fill(prompt_row, 0, screen.width(), ' ', normal);
+    let width = screen.put_str(prompt_row, 0, pager.prompt(), colors.style(group));
+    (width.min(screen.width().saturating_sub(1)), prompt_row)
+}
+
+// The highlight group of a message on the command line.
+fn message_group(message: &str) -> Group {
+    let digits = message
+        .strip_prefix('E')
+        .map(|rest| rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len());
+    match digits {
+        Some(n) if n > 0 && message[1 + n..].starts_with(':') => Group::ErrorMsg,
+        _ if message.starts_with("-- ") && message.ends_with(" --") => Group::ModeMsg,
+        _ => Group::Normal,
+    }
+}
+
+// The theme the text is drawn with.
+struct Colors<'a> {
+    theme: &'a Theme,
+    depth: ColorDepth,
+}
+
+impl Colors<'_> {
+    fn style(&self, group: Group) -> Style {
+        self.theme.style(group, self.depth)
+    }
+}
+
+// Where a window is in its buffer and how it shows the lines.
+struct View<'a> {
+    start_row: usize,
+    // The first screen column shown when lines do not wrap.
+    left_col: usize,
+    cursor_row: usize,
+    options: WindowOptions,
+    number_width: u16,
+    tab_stop: usize,
+    list_chars: &'a ListChars,
+}
+
+// Draws the lines of `buffer` from `view.start_row` into the top `height` lines
+// of `rect`, wrapping long lines at the width of the window and highlighting
+// them. Line numbers are drawn on the first screen line of each buffer line.
+// Lines that do not wrap are shown from `view.left_col`, with markers where
+// they continue off the window.
+fn draw_text(
+    screen: &mut Screen,
+    buffer: &Buffer,
+    view: &View,
+    colors: &Colors,
+    rect: Rect,
+    height: u16,
+) {
+    let mut writer = LineWriter {
+        screen,
+        top: rect.row,
+        text_col: rect.col + view.number_width,
+        text_width: rect.width - view.number_width,
+        height,
+        wrap: view.options.wrap,
+        left_col: view.left_col,
+        row: 0,
+        col: 0,
+    };
+    let list = view.options.list;
+    let list_chars = view.list_chars;
+    for (row, line) in (view.start_row..).zip(buffer.lines_at(view.start_row)) {
+        if writer.row >= height {
+            break;
+        }
+        if view.number_width > 0 {
+            let number = view
+                .options
+                .line_number(row, view.cursor_row, view.number_width);
+            let group = if row == view.cursor_row && view.options.relative_number {
+                Group::CursorLineNr
+            } else {
+                Group::LineNr
+            };
+            writer.screen.put_str(
+                rect.row + writer.row,
+                rect.col,
+                &number,
+                colors.style(group),
+            );
+        }
+        let spans = buffer.highlight_line(row);
+        let mut spans = spans.iter().peekable();
+        let mut byte = 0;
+        // The spaces from here to the end of the line are trailing.
+        let trail_start = line.len_chars()
+            - line
+                .chars_at(line.len_chars())
+                .reversed()
+                .take_while(|&c| c == ' ')
+                .count();
+        let special = colors.style(Group::SpecialKey);
+        // The column of `c` in its line.
+        let mut line_col = 0;
+        'chars: for (i, c) in line.chars().enumerate() {
+            while spans.next_if(|span| span.end <= byte).is_some() {}
+            let style = match spans.peek() {
+                Some(span) if span.start <= byte => colors.style(Group::from(span.kind)),
+                _ => colors.style(Group::Normal),
+            };
+            byte += c.len_utf8();
+            // check if c is double width character
+            let char_width = get_char_width_at(c, line_col, view.tab_stop);
+            line_col += char_width as usize;
+            let fits = match c {
+                '\t' => {
+                    // A tab is made of single cells.
+                    for cell in 0..char_width {
+                        let (ch, style) = match list_chars.tab.filter(|_| list) {
+                            Some((_, _, Some(last))) if cell + 1 == char_width => (last, special),
+                            Some((first, _, _)) if cell == 0 => (first, special),
+                            Some((_, fill, _)) => (fill, special),
+                            None => (' ', style),
+                        };
+                        if !writer.put(ch, 1, style) {
+                            break 'chars;
+                        }
+                    }
+                    true
+                }
+                ' ' if list && i >= trail_start && list_chars.trail.is_some() => {
+                    writer.put(list_chars.trail.unwrap(), 1, special)
+                }
+                '\u{a0}' | '\u{202f}' if list && list_chars.nbsp.is_some() => {
+                    writer.put(list_chars.nbsp.unwrap(), 1, special)
+                }
+                _ => writer.put(c, char_width, style),
+            };
+            if !fits {
+                break;
+            }
+        }
+        if let Some(eol) = list_chars.eol.filter(|_| list) {
+            writer.put(eol, 1, colors.style(Group::NonText));
+        }
+        if !view.options.wrap && writer.row < height {
+            let screen_row = rect.row + writer.row;
+            let text_col = writer.text_col;
+            let non_text = colors.style(Group::NonText);
+            if let Some(precedes) = list_chars.precedes {
+                if view.left_col > 0 && line_col > 0 {
+                    writer
+                        .screen
+                        .put_char(screen_row, text_col, precedes, non_text);
+                }
+            }
+            if let Some(extends) = list_chars.extends {
+                let line_width = get_line_width(line.chars(), view.tab_stop);
+                if line_width > view.left_col + writer.text_width as usize {
+                    let col = text_col + writer.text_width - 1;
+                    writer.screen.put_char(screen_row, col, extends, non_text);
+                }
+            }
+        }
+        writer.next_line();
+    }
+}
+
+// Puts the cells of buffer lines into the text area of a window one after
+// another, wrapping them at its right edge or, when lines do not wrap,
+// showing only the columns from `left_col`.
+struct LineWriter<'a> {
+    screen: &'a mut Screen,
+    top: u16,
+    text_col: u16,
+    text_width: u16,
+    height: u16,
+    wrap: bool,
+    left_col: usize,
+    // The screen line in the window.
+    row: u16,
+    // The column of the next cell: in the screen line when wrapping, in the
+    // buffer line otherwise.
+    col: usize,
+}
+
+impl LineWriter<'_> {
+    // Puts a cell `width` columns wide and returns whether there is room
+    // for more cells in this buffer line.
+    fn put(&mut self, c: char, width: u16, style: Style) -> bool {
+        if self.row >= self.height {
+            return false;
+        }
+        let left = self.col;
+        self.col += width as usize;
+        if self.wrap {
+            self.screen
+                .put_char(self.top + self.row, self.text_col + left as u16, c, style);
+            if self.col >= self.text_width as usize {
+                self.col = 0;
+                self.row += 1;
+            }
+            return self.row < self.height;
+        }
+        // A double width character cut by the left edge is left out.
+        if left < self.left_col {
+            return true;
+        }
+        if self.col > self.left_col + self.text_width as usize {
+            return false;
+        }
+        let col = self.text_col + (left - self.left_col) as u16;
+        self.screen.put_char(self.top + self.row, col, c, style);
+        true
+    }
+
+    fn next_line(&mut self) {
+        self.col = 0;
+        self.row += 1;
+    }
+}
+
+// Keeps what the terminal shows (the front screen) and updates it to a newly
+// drawn screen by drawing only the cells that changed.
+#[derive(Default)]
+pub struct Renderer {
+    front: Option<Screen>,
+}
+
+impl Renderer {
+    /// Makes the next flush repaint the whole terminal.
+    pub fn invalidate(&mut self) {
+        self.front = None;
+    }
+
+    pub fn flush(&mut self, backend: &mut impl Backend, frame: Frame) -> GenericResult<()> {
+        let Frame {
+            screen,
+            cursor: (cursor_col, cursor_row),
+            scroll_regions,
+            message_row,
+            bell,
+        } = frame;
+        let mut front = match self.front.take() {
+            Some(front) if front.width() == screen.width() && front.height() == screen.height() => {
+                front
+            }
+            _ => {
+                backend.clear()?;
+                Screen::new(screen.width(), screen.height())
+            }
+        };
+        for (top, bottom) in scroll_regions {
+            if let Some(count) = find_scroll(&front, &screen, top, bottom) {
+                backend.scroll(top, bottom, count)?;
+                front.scroll(top, bottom, count);
+            }
+        }
+        draw_changes(backend, &front, &screen, message_row)?;
+        if bell {
+            backend.bell()?;
+        }
+        backend.move_cursor(cursor_col, cursor_row)?;
+        backend.flush()?;
+        self.front = Some(screen);
+        Ok(())
+    }
+}
 
+// Returns by how many rows `top..bottom` of `front` should be scrolled up
+// (down if negative) to look most like `back`, if scrolling saves writing
+// at least half of the rows.
+fn find_scroll(front: &Screen, back: &Screen, top: u16, bottom: u16) -> Option<i32> {
+    let rows = bottom.saturating_sub(top) as i32;
+    let matching_rows = |count: i32| {
+        (top as i32..bottom as i32)
+            .filter(|&row| {
+                let from = row + count;
+                (top as i32..bottom as i32).contains(&from)
+                    && back.row(row as u16) == front.row(from as u16)
+            })
+            .count() as i32
+    };
+    let unchanged = matching_rows(0);
+    (1..rows)
+        .flat_map(|count| [count, -count])
+        .map(|count| (count, matching_rows(count)))
+        .filter(|&(_, matching)| matching - unchanged >= rows / 2 && matching > 0)
+        .max_by_key(|&(count, matching)| (matching, -count.abs()))
+        .map(|(count, _)| count)
+}
+
+// The length of a cursor movement sequence, roughly.
+const MAX_GAP: u16 = 6;
+
+// Draws the runs of cells that differ between `front` and `back`.
+fn draw_changes(
+    backend: &mut impl Backend,
+    front: &Screen,
+    back: &Screen,
+    forced_row: Option<u16>,
+) -> GenericResult<()> {
+    for row in 0..back.height() {
+        let (front_row, back_row) = (front.row(row), back.row(row));
+        let changed =
+            |col: u16| Some(row) == forced_row || front_row[col as usize] != back_row[col as usize];
+        let mut col = 0;
+        while col < back.width() {
+            if !changed(col) {
+                col += 1;
+                continue;
+            }
+            // Double width characters are drawn whole.
+            let mut start = col;
+            if back_row[start as usize].is_continuation() && start > 0 {
+                start -= 1;
+            }
+            let mut end = start;
+            // Short unchanged gaps are drawn too, as that is cheaper than
+            // moving the cursor over them.
+            let changed_soon = |col: u16| (col..(col + MAX_GAP).min(back.width())).any(changed);
+            while end < back.width()
+                && (changed_soon(end) || back_row[end as usize].is_continuation())
+            {
+                end += 1;
+            }
+            backend.draw(row, start, &back_row[start as usize..end as usize])?;
+            col = end;
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::CrosstermBackend;
+
+    fn frame(screen: Screen, scroll_regions: &[(u16, u16)]) -> Frame {
+        Frame {
+            screen,
+            cursor: (0, 0),
+            scroll_regions: scroll_regions.to_vec(),
+            message_row: None,
+            bell: false,
+        }
+    }
+
+    fn screen_with_rows(rows: &[&str]) -> Screen {
+        let mut screen = Screen::new(10, rows.len() as u16);
+        for (row, s) in rows.iter().enumerate() {
+            screen.put_str(row as u16, 0, s, Style::default());
+        }
+        screen
+    }
+
+    #[test]
+    fn test_flush_writes_only_changes() {
+        let mut renderer = Renderer::default();
+        let mut backend = CrosstermBackend::new(Vec::new());
+        renderer
+            .flush(&mut backend, frame(screen_with_rows(&["abc", "def"]), &[]))
+            .unwrap();
+        let output = String::from_utf8(backend.get_ref().clone()).unwrap();
+        assert!(output.contains("abc") && output.contains("def"));
+
+        let mut backend = CrosstermBackend::new(Vec::new());
+        renderer
+            .flush(&mut backend, frame(screen_with_rows(&["abc", "dxf"]), &[]))
+            .unwrap();
+        let output = String::from_utf8(backend.get_ref().clone()).unwrap();
+        assert!(output.contains('x'));
+        assert!(!output.contains("abc") && !output.contains('d') && !output.contains("\x1b[2J"));
+
+        // Ctrl-L repaints everything
+        renderer.invalidate();
+        let mut backend = CrosstermBackend::new(Vec::new());
+        renderer
+            .flush(&mut backend, frame(screen_with_rows(&["abc", "dxf"]), &[]))
+            .unwrap();
+        let output = String::from_utf8(backend.get_ref().clone()).unwrap();
+        assert!(output.contains("\x1b[2J") && output.contains("abc") && output.contains("dxf"));
+    }
+
+    #[test]
+    fn test_message_group() {
+        assert_eq!(
+            message_group("E37: No write since last change"),
+            Group::ErrorMsg
+        );
+        assert_eq!(message_group("-- INSERT --"), Group::ModeMsg);
+        assert_eq!(message_group("Every line"), Group::Normal);
+        assert_eq!(message_group("E: x"), Group::Normal);
+    }
+
+    #[test]
+    fn test_find_scroll() {
+        let front = screen_with_rows(&["1", "2", "3", "4", "5", "status"]);
+        let back = screen_with_rows(&["3", "4", "5", "6", "7", "status"]);
+        assert_eq!(find_scroll(&front, &back, 0, 5), Some(2));
+        assert_eq!(find_scroll(&back, &front, 0, 5), Some(-2));
+        assert_eq!(find_scroll(&front, &front, 0, 5), None);
+
+        let mut renderer = Renderer::default();
+        let mut backend = CrosstermBackend::new(Vec::new());
+        renderer.flush(&mut backend, frame(front, &[])).unwrap();
+        let mut backend = CrosstermBackend::new(Vec::new());
+        renderer
+            .flush(&mut backend, frame(back, &[(0, 5)]))
+            .unwrap();
+        let output = String::from_utf8(backend.get_ref().clone()).unwrap();
+        assert!(output.contains("\x1b[1;5r"));
+        assert!(output.contains('6') && output.contains('7') && !output.contains('3'));
+    }
+}
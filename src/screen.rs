@@ -0,0 +1,224 @@
+use crossterm::style::Color;
+
+use crate::util::get_char_width;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub const REVERSE: Style = Style {
+        fg: None,
+        bg: None,
+        bold: false,
+        reverse: true,
+        underline: false,
+    };
+}
+
+// One column of the terminal. A double width character takes two cells; the
+// second one is a continuation cell with width 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub ch: char,
+    pub width: u8,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            width: 1,
+            style: Style::default(),
+        }
+    }
+}
+
+impl Cell {
+    fn continuation(style: Style) -> Cell {
+        Cell {
+            ch: ' ',
+            width: 0,
+            style,
+        }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.width == 0
+    }
+}
+
+// A grid of cells, the contents of the terminal as evi wants it to look.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Screen {
+        Screen {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn row(&self, row: u16) -> &[Cell] {
+        let start = row as usize * self.width as usize;
+        &self.cells[start..start + self.width as usize]
+    }
+
+    pub fn cell(&self, row: u16, col: u16) -> &Cell {
+        &self.row(row)[col as usize]
+    }
+
+    /// Returns the text of a row, without the continuation cells.
+    pub fn row_text(&self, row: u16) -> String {
+        self.row(row)
+            .iter()
+            .filter(|cell| !cell.is_continuation())
+            .map(|cell| cell.ch)
+            .collect()
+    }
+
+    fn index(&self, row: u16, col: u16) -> usize {
+        row as usize * self.width as usize + col as usize
+    }
+
+    // Blanks the other half of a double width character before `col` is overwritten.
+    fn break_wide_char(&mut self, row: u16, col: u16) {
+        let index = self.index(row, col);
+        if self.cells[index].is_continuation() && col > 0 {
+            self.cells[index - 1] = Cell::default();
+        }
+        if self.cells[index].width == 2 && col + 1 < self.width {
+            self.cells[index + 1] = Cell::default();
+        }
+    }
+
+    /// Puts `ch` at `row`, `col` and returns the number of columns it takes.
+    /// Characters without width and those that do not fit are not drawn.
+    pub fn put_char(&mut self, row: u16, col: u16, ch: char, style: Style) -> u16 {
+        let width = get_char_width(ch);
+        if row >= self.height || width == 0 || col + width > self.width {
+            return 0;
+        }
+        self.break_wide_char(row, col);
+        if width == 2 {
+            self.break_wide_char(row, col + 1);
+        }
+        let index = self.index(row, col);
+        self.cells[index] = Cell {
+            ch,
+            width: width as u8,
+            style,
+        };
+        if width == 2 {
+            self.cells[index + 1] = Cell::continuation(style);
+        }
+        width
+    }
+
+    /// Puts `s` from `row`, `col` up to the right edge and returns the number
+    /// of columns used.
+    pub fn put_str(&mut self, row: u16, col: u16, s: &str, style: Style) -> u16 {
+        self.put_str_within(row, col, self.width.saturating_sub(col), s, style)
+    }
+
+    /// Like `put_str`, but uses at most `max_width` columns, as in a window.
+    pub fn put_str_within(
+        &mut self,
+        row: u16,
+        col: u16,
+        max_width: u16,
+        s: &str,
+        style: Style,
+    ) -> u16 {
+        let max_width = max_width.min(self.width.saturating_sub(col));
+        let mut used = 0;
+        for ch in s.chars() {
+            let width = get_char_width(ch);
+            if used + width > max_width {
+                break;
+            }
+            used += self.put_char(row, col + used, ch, style);
+        }
+        used
+    }
+
+    /// Fills `len` columns from `row`, `col` with `ch`.
+    pub fn fill(&mut self, row: u16, col: u16, len: u16, ch: char, style: Style) {
+        for col in col..(col + len).min(self.width) {
+            self.put_char(row, col, ch, style);
+        }
+    }
+
+    /// Moves the rows `top..bottom` up by `count` rows (down if negative),
+    /// like the terminal does in a scroll region. Rows scrolled in are blank.
+    pub fn scroll(&mut self, top: u16, bottom: u16, count: i32) {
+        let width = self.width as usize;
+        let (top, bottom) = (top as usize * width, bottom as usize * width);
+        let shift = count.unsigned_abs() as usize * width;
+        let region = &mut self.cells[top..bottom];
+        if shift >= region.len() {
+            region.fill(Cell::default());
+        } else if count > 0 {
+            region.rotate_left(shift);
+            let len = region.len();
+            region[len - shift..].fill(Cell::default());
+        } else {
+            region.rotate_right(shift);
+            region[..shift].fill(Cell::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_wide_chars() {
+        let mut screen = Screen::new(5, 2);
+        assert_eq!(screen.put_str(0, 0, "aあい", Style::default()), 5);
+        assert_eq!(screen.row_text(0), "aあい");
+        assert!(screen.cell(0, 2).is_continuation());
+
+        // Overwriting half of a double width character blanks the other half.
+        screen.put_char(0, 2, 'x', Style::default());
+        assert_eq!(screen.row_text(0), "a xい");
+        // A double width character does not fit in the last column.
+        assert_eq!(screen.put_str(1, 0, "abcdあ", Style::default()), 4);
+        assert_eq!(screen.row_text(1), "abcd ");
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut screen = Screen::new(2, 4);
+        for (row, s) in ["a", "b", "c", "d"].iter().enumerate() {
+            screen.put_str(row as u16, 0, s, Style::default());
+        }
+        screen.scroll(0, 3, 1);
+        let rows: Vec<String> = (0..4).map(|row| screen.row_text(row)).collect();
+        assert_eq!(rows, ["b ", "c ", "  ", "d "]);
+        screen.scroll(0, 4, -2);
+        let rows: Vec<String> = (0..4).map(|row| screen.row_text(row)).collect();
+        assert_eq!(rows, ["  ", "  ", "b ", "c "]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::util::{get_char_width, get_line_width};
+
+// The columns of the ruler, like vim's: the cursor position on the left and
+// where the window is in the buffer on the right.
+pub const RULER_WIDTH: u16 = 18;
+// The columns of the keys shown by 'showcmd'.
+pub const SHOW_CMD_WIDTH: u16 = 11;
+
+// What the status line and the ruler of a window can show.
+pub struct WindowStatus {
+    pub name: String,
+    pub buffer_number: usize,
+    pub modified: bool,
+    pub read_only: bool,
+    // The cursor line and the cursor.
+    pub line: String,
+    pub row: usize,
+    pub col: usize,
+    pub tab_stop: usize,
+    pub line_count: usize,
+    // The first and the last line shown in the window.
+    pub top_row: usize,
+    pub bottom_row: usize,
+}
+
+impl WindowStatus {
+    /// The byte column of the cursor counted from 1, 0 in an empty line.
+    fn byte_col(&self) -> usize {
+        if self.line.is_empty() {
+            return 0;
+        }
+        let before: usize = self.line.chars().take(self.col).map(char::len_utf8).sum();
+        before + 1
+    }
+
+    /// The screen column of the cursor counted from 1, where the character
+    /// under the cursor starts.
+    fn virtual_col(&self) -> usize {
+        get_line_width(self.line.chars().take(self.col), self.tab_stop) + 1
+    }
+
+    /// Like vim's `%P`: `All` when every line is shown, `Top` or `Bot` when
+    /// the first or the last one is, otherwise the percentage of the lines
+    /// above the window.
+    fn relative_position(&self) -> String {
+        let above = self.top_row;
+        let below = self.line_count.saturating_sub(self.bottom_row + 1);
+        match (above, below) {
+            (0, 0) => "All".to_string(),
+            (_, 0) => "Bot".to_string(),
+            (0, _) => "Top".to_string(),
+            _ => format!("{}%", above * 100 / (above + below)),
+        }
+    }
+
+    /// Returns the ruler for `width` columns, `line,column` and the relative
+    /// position, like `12,5-9         Top`. The last column is left blank.
+    pub fn ruler(&self, width: usize) -> String {
+        let position = format!("{},{}{}", self.row + 1, self.byte_col(), self.virtual_suffix());
+        let relative_position = format!("{:>3}", self.relative_position());
+        let used = position.len() + relative_position.len();
+        let padding = width.saturating_sub(used + 1).max(1);
+        format!("{}{}{}", position, " ".repeat(padding), relative_position)
+    }
+
+    // Like vim's `%V`: `-` and the screen column when it is not the byte column.
+    fn virtual_suffix(&self) -> String {
+        let virtual_col = self.virtual_col();
+        if virtual_col == self.byte_col() {
+            String::new()
+        } else {
+            format!("-{}", virtual_col)
+        }
+    }
+
+    /// Formats the status line `width` columns wide with a format like vim's
+    /// 'statusline': `%f` is the file name, `%m` `[+]` when modified, `%M`
+    /// `,+` when modified, `%r` `[RO]` when read only, `%n` the buffer
+    /// number, `%l` the line, `%L` the number of lines, `%c` the byte column,
+    /// `%v` the screen column, `%V` `-` and the screen column when it is not
+    /// the byte column, `%p` the percentage of the line in the buffer, `%P`
+    /// the relative position of the window and `%%` a `%`. The text after
+    /// `%=` is aligned right.
+    pub fn format(&self, format: &str, width: usize) -> String {
+        // The left part and, after `%=`, the right part.
+        let mut parts = vec![String::new()];
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c == '%' && chars.clone().next() == Some('=') {
+                chars.next();
+                if parts.len() == 1 {
+                    parts.push(String::new());
+                }
+                continue;
+            }
+            let part = parts.last_mut().unwrap();
+            if c != '%' {
+                part.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('f') => part.push_str(&self.name),
+                Some('m') if self.modified => part.push_str("[+]"),
+                Some('M') if self.modified => part.push_str(",+"),
+                Some('r') if self.read_only => part.push_str("[RO]"),
+                Some('m' | 'M' | 'r') => {}
+                Some('n') => part.push_str(&self.buffer_number.to_string()),
+                Some('l') => part.push_str(&(self.row + 1).to_string()),
+                Some('L') => part.push_str(&self.line_count.to_string()),
+                Some('c') => part.push_str(&self.byte_col().to_string()),
+                Some('v') => part.push_str(&self.virtual_col().to_string()),
+                Some('V') => part.push_str(&self.virtual_suffix()),
+                Some('p') => {
+                    let percent = (self.row + 1) * 100 / self.line_count.max(1);
+                    part.push_str(&percent.to_string());
+                }
+                Some('P') => part.push_str(&self.relative_position()),
+                Some(c) => part.push(c),
+                None => part.push('%'),
+            }
+        }
+        let [left, right] = &parts[..] else {
+            return parts.concat();
+        };
+        let used = text_width(left) + text_width(right);
+        format!("{}{}{}", left, " ".repeat(width.saturating_sub(used)), right)
+    }
+}
+
+/// Returns the keys typed so far for 'showcmd', the last ones when they are
+/// too many, with control keys as `^X`.
+pub fn show_cmd_text(keys: &str) -> String {
+    let mut text = String::new();
+    for c in keys.chars() {
+        if c.is_ascii_control() {
+            text.push('^');
+            text.push(((c as u8) ^ 0x40) as char);
+        } else {
+            text.push(c);
+        }
+    }
+    let max = SHOW_CMD_WIDTH as usize - 1;
+    let skip = text.chars().count().saturating_sub(max);
+    text.chars().skip(skip).collect()
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().map(|c| get_char_width(c) as usize).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(line: &str, col: usize) -> WindowStatus {
+        WindowStatus {
+            name: "a.txt".to_string(),
+            buffer_number: 1,
+            modified: true,
+            read_only: false,
+            line: line.to_string(),
+            row: 9,
+            col,
+            tab_stop: 8,
+            line_count: 40,
+            top_row: 5,
+            bottom_row: 24,
+        }
+    }
+
+    #[test]
+    fn test_ruler() {
+        assert_eq!(status("abc", 1).ruler(18), "10,2          25%");
+        // after a tab, and after a wide character of three bytes
+        assert_eq!(status("\tx", 1).ruler(18), "10,2-9        25%");
+        assert_eq!(status("あい", 1).ruler(18), "10,4-3        25%");
+        assert_eq!(status("", 0).ruler(18), "10,0-1        25%");
+
+        let mut all = status("abc", 0);
+        all.top_row = 0;
+        all.bottom_row = 39;
+        assert_eq!(all.ruler(18), "10,1          All");
+        all.top_row = 1;
+        assert_eq!(all.ruler(18), "10,1          Bot");
+        assert_eq!(all.ruler(10), "10,1  Bot");
+        assert_eq!(all.ruler(5), "10,1 Bot");
+    }
+
+    #[test]
+    fn test_format() {
+        let status = status("\tx", 1);
+        assert_eq!(status.format("%f%m %n", 20), "a.txt[+] 1");
+        assert_eq!(status.format("%l/%L %c%V %v%%", 20), "10/40 2-9 9%");
+        assert_eq!(status.format("%f%=%p%%", 12), "a.txt    25%");
+        assert_eq!(status.format("%f%M%=%P", 3), "a.txt,+25%");
+    }
+
+    #[test]
+    fn test_show_cmd_text() {
+        assert_eq!(show_cmd_text("2d"), "2d");
+        assert_eq!(show_cmd_text("\x17"), "^W");
+        assert_eq!(show_cmd_text("123456789012"), "3456789012");
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::buffer::Buffer;
+use crate::file_format::{FileEncoding, FileFormat, LineEnding};
+use crate::file_write::write_file;
+use crate::generic_error::GenericResult;
+
+const MAGIC: &str = "evi swap file";
+
+// Like vim, a second editing session on the same file uses `.swo`, then
+// `.swn`, and so on, so the swap file of a crashed session is not overwritten.
+const SUFFIXES: [&str; 6] = ["swp", "swo", "swn", "swm", "swl", "swk"];
+
+// A swap file holds a snapshot of a modified buffer next to the edited file:
+// a few `key=value` header lines, an empty line, and then the text of the
+// buffer with every line terminated by '\n'.
+pub struct SwapFile {
+    pub path: PathBuf,
+}
+
+pub struct Recovered {
+    pub buffer: Buffer,
+    pub cursor_row: usize,
+}
+
+impl SwapFile {
+    /// Returns the swap files that exist for `file_path`.
+    pub fn existing(file_path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+        candidates(file_path).filter(|path| path.exists())
+    }
+
+    /// Picks the first swap file name that is not in use.
+    pub fn create_for(file_path: &Path) -> Option<SwapFile> {
+        candidates(file_path)
+            .find(|path| !path.exists())
+            .map(|path| SwapFile { path })
+    }
+
+    pub fn write(&self, file_path: &Path, buffer: &Buffer, cursor_row: usize) -> GenericResult<()> {
+        let file_format = &buffer.file_format;
+        let mut contents = format!(
+            "{}\nfile={}\npid={}\nfileformat={}\nfileencoding={}\nbomb={}\nendofline={}\ncursor={}\n\n",
+            MAGIC,
+            file_path.to_string_lossy(),
+            std::process::id(),
+            file_format.line_ending.name(),
+            file_format.encoding.name(),
+            file_format.bom as u8,
+            file_format.end_of_line as u8,
+            cursor_row,
+        );
+        contents.push_str(&buffer.text());
+        write_file(&self.path, contents.as_bytes())
+    }
+
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub fn recover(swap_path: &Path) -> GenericResult<Recovered> {
+    let corrupted = || {
+        format!(
+            "E307: {} does not look like an evi swap file",
+            swap_path.to_string_lossy()
+        )
+    };
+    let contents = fs::read_to_string(swap_path)?;
+    let (header, text) = contents.split_once("\n\n").ok_or_else(corrupted)?;
+    let mut lines = header.lines();
+    if lines.next() != Some(MAGIC) {
+        return Err(corrupted().into());
+    }
+
+    let mut file_format = FileFormat::default();
+    let mut cursor_row = 0;
+    for line in lines {
+        let (key, value) = line.split_once('=').ok_or_else(corrupted)?;
+        match key {
+            "fileformat" => {
+                file_format.line_ending = LineEnding::from_name(value).ok_or_else(corrupted)?
+            }
+            "fileencoding" => {
+                file_format.encoding = FileEncoding::from_name(value).ok_or_else(corrupted)?
+            }
+            "bomb" => file_format.bom = value == "1",
+            "endofline" => file_format.end_of_line = value == "1",
+            "cursor" => cursor_row = value.parse()?,
+            _ => {}
+        }
+    }
+    Ok(Recovered {
+        buffer: Buffer::from_text(text, file_format),
+        cursor_row,
+    })
+}
+
+fn candidates(file_path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+    SUFFIXES
+        .iter()
+        .map(move |suffix| file_path.with_file_name(format!(".{}.{}", file_name, suffix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("a.txt");
+        let mut buffer = Buffer::from_lines(&["abc", "", "def"]);
+        buffer.file_format.line_ending = LineEnding::Dos;
+
+        let swap_file = SwapFile::create_for(&file_path).unwrap();
+        assert_eq!(swap_file.path, dir.path().join(".a.txt.swp"));
+        swap_file.write(&file_path, &buffer, 2).unwrap();
+        assert_eq!(SwapFile::existing(&file_path).next().as_ref(), Some(&swap_file.path));
+        // A second session must not reuse the swap file.
+        assert_eq!(
+            SwapFile::create_for(&file_path).unwrap().path,
+            dir.path().join(".a.txt.swo")
+        );
+
+        let recovered = recover(&swap_file.path).unwrap();
+        assert_eq!(recovered.buffer, ["abc", "", "def"][..]);
+        assert_eq!(recovered.buffer.file_format.line_ending, LineEnding::Dos);
+        assert_eq!(recovered.cursor_row, 2);
+
+        swap_file.remove();
+        assert_eq!(SwapFile::existing(&file_path).count(), 0);
+    }
+
+    #[test]
+    fn test_recover_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".a.txt.swp");
+        fs::write(&path, "not a swap file\n").unwrap();
+        assert!(recover(&path).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+use std::cell::{OnceCell, RefCell};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use regex::Regex;
+
+use crate::buffer::Buffer;
+use crate::generic_error::GenericResult;
+
+// The grammars that come with evi, in the same format as the user's own.
+const BUILTIN_GRAMMARS: &[&str] = &[
+    include_str!("../syntax/rust.syntax"),
+    include_str!("../syntax/python.syntax"),
+    include_str!("../syntax/sh.syntax"),
+    include_str!("../syntax/markdown.syntax"),
+    include_str!("../syntax/toml.syntax"),
+    include_str!("../syntax/yaml.syntax"),
+];
+
+// What a piece of text is, named like vim's highlight groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Comment,
+    Constant,
+    String,
+    Number,
+    Identifier,
+    Function,
+    Keyword,
+    Type,
+    PreProc,
+    Special,
+    Title,
+    Underlined,
+}
+
+impl TokenKind {
+    fn from_name(name: &str) -> Option<TokenKind> {
+        match name {
+            "Comment" => Some(TokenKind::Comment),
+            "Constant" => Some(TokenKind::Constant),
+            "String" => Some(TokenKind::String),
+            "Number" => Some(TokenKind::Number),
+            "Identifier" => Some(TokenKind::Identifier),
+            "Function" => Some(TokenKind::Function),
+            "Keyword" => Some(TokenKind::Keyword),
+            "Type" => Some(TokenKind::Type),
+            "PreProc" => Some(TokenKind::PreProc),
+            "Special" => Some(TokenKind::Special),
+            "Title" => Some(TokenKind::Title),
+            "Underlined" => Some(TokenKind::Underlined),
+            _ => None,
+        }
+    }
+}
+
+// A highlighted part of a line, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+// A pattern to highlight. When the pattern has a group, only the text of the
+// group is highlighted. A region goes on from the pattern until `end`, over
+// line ends; text matching `skip` (like an escaped quote) does not end it.
+#[derive(Debug)]
+struct Rule {
+    kind: TokenKind,
+    pattern: Regex,
+    end: Option<Regex>,
+    skip: Option<Regex>,
+}
+
+// The index of the region rule a line starts in, if any.
+type LineState = Option<usize>;
+
+/// A grammar file has one definition per line; `#` starts a comment line.
+///
+/// ```text
+/// name rust
+/// files *.rs
+/// firstline ^#!.*\brust-script
+/// keyword Keyword fn let mut
+/// match Number \b[0-9]+\b
+/// region String " " \\.
+/// ```
+///
+/// `files` lists extensions (`*.rs`) and file names (`Makefile`), and
+/// `firstline` matches the first line of files that have neither. A `match`
+/// pattern is the rest of the line; the patterns of `region` (start, end and
+/// an optional skip) are separated by spaces, so a space in them is `\s`.
+/// When patterns match at the same place, the one defined first wins.
+#[derive(Debug)]
+pub struct Grammar {
+    pub name: String,
+    extensions: Vec<String>,
+    file_names: Vec<String>,
+    first_line: Option<Regex>,
+    // The rule lines and their line numbers. They are compiled when the
+    // grammar is first used, so that unused grammars cost nothing.
+    definitions: Vec<(usize, String)>,
+    rules: OnceCell<Vec<Rule>>,
+}
+
+impl Grammar {
+    pub fn parse(text: &str) -> GenericResult<Grammar> {
+        let mut grammar = Grammar {
+            name: String::new(),
+            extensions: Vec::new(),
+            file_names: Vec::new(),
+            first_line: None,
+            definitions: Vec::new(),
+            rules: OnceCell::new(),
+        };
+        for (number, line) in text.lines().enumerate() {
+            grammar
+                .parse_line(number + 1, line.trim())
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        if grammar.name.is_empty() {
+            return Err("no name".into());
+        }
+        Ok(grammar)
+    }
+
+    fn parse_line(&mut self, number: usize, line: &str) -> GenericResult<()> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        match directive {
+            "name" => self.name = rest.to_string(),
+            "files" => {
+                for pattern in rest.split_whitespace() {
+                    match pattern.strip_prefix("*.") {
+                        Some(extension) => self.extensions.push(extension.to_string()),
+                        None => self.file_names.push(pattern.to_string()),
+                    }
+                }
+            }
+            "firstline" => self.first_line = Some(Regex::new(rest)?),
+            "keyword" | "match" | "region" => self.definitions.push((number, line.to_string())),
+            _ => return Err(format!("unknown definition {}", directive).into()),
+        }
+        Ok(())
+    }
+
+    fn parse_rule(line: &str) -> GenericResult<Rule> {
+        let mut words = line.splitn(3, char::is_whitespace);
+        let directive = words.next().unwrap_or_default();
+        let kind = words.next().unwrap_or_default();
+        let kind = TokenKind::from_name(kind).ok_or_else(|| format!("unknown kind {}", kind))?;
+        let rest = words.next().unwrap_or_default().trim_start();
+        let (pattern, end, skip) = match directive {
+            "keyword" => {
+                let words: Vec<String> = rest.split_whitespace().map(regex::escape).collect();
+                (format!(r"\b(?:{})\b", words.join("|")), None, None)
+            }
+            "region" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                [start, end] => (start.to_string(), Some(Regex::new(end)?), None),
+                [start, end, skip] => (
+                    start.to_string(),
+                    Some(Regex::new(end)?),
+                    Some(Regex::new(skip)?),
+                ),
+                _ => return Err("region needs a start and an end".into()),
+            },
+            _ => (rest.to_string(), None, None),
+        };
+        Ok(Rule {
+            kind,
+            pattern: Regex::new(&pattern)?,
+            end,
+            skip,
+        })
+    }
+
+    /// Compiles the rules, to find the errors in them.
+    pub fn compile(&self) -> GenericResult<()> {
+        if self.rules.get().is_none() {
+            let rules = self
+                .definitions
+                .iter()
+                .map(|(number, line)| {
+                    Grammar::parse_rule(line).map_err(|e| format!("line {}: {}", number, e))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let _ = self.rules.set(rules);
+        }
+        Ok(())
+    }
+
+    fn rules(&self) -> &[Rule] {
+        // The user's grammars are compiled when they are loaded and the built
+        // in ones are checked by the tests.
+        self.compile().expect("a grammar is valid");
+        self.rules.get().map_or(&[], Vec::as_slice)
+    }
+
+    fn matches_file(&self, path: &Path, first_line: &str) -> bool {
+        let extension = path.extension().and_then(|e| e.to_str());
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        extension.is_some_and(|extension| self.extensions.iter().any(|e| e == extension))
+            || file_name.is_some_and(|name| self.file_names.iter().any(|n| n == name))
+            || self
+                .first_line
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(first_line))
+    }
+
+    // Returns the end of the region of `rule` that goes on at `pos`.
+    fn region_end(&self, rule: &Rule, line: &str, mut pos: usize) -> Option<usize> {
+        let end = rule.end.as_ref()?;
+        loop {
+            let found = end.find_at(line, pos)?;
+            match rule.skip.as_ref().and_then(|skip| skip.find_at(line, pos)) {
+                Some(skipped) if skipped.start() < found.start() && skipped.end() > pos => {
+                    pos = skipped.end();
+                }
+                _ => return Some(found.end()),
+            }
+        }
+    }
+
+    // Returns where rule `index` next matches from `pos`: the whole match and
+    // the highlighted part.
+    fn find_rule(
+        &self,
+        index: usize,
+        line: &str,
+        pos: usize,
+    ) -> Option<((usize, usize), (usize, usize))> {
+        let captures = self.rules()[index].pattern.captures_at(line, pos)?;
+        let whole = captures.get(0)?;
+        let part = captures.get(1).unwrap_or(whole);
+        Some(((whole.start(), whole.end()), (part.start(), part.end())))
+    }
+
+    // Splits `line` into spans, starting in `state`, and returns the state the
+    // next line starts in.
+    fn tokenize(&self, line: &str, state: LineState) -> (Vec<Span>, LineState) {
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        if let Some(index) = state {
+            let rule = &self.rules()[index];
+            let end = self.region_end(rule, line, 0);
+            if end.unwrap_or(line.len()) > 0 {
+                spans.push(Span {
+                    start: 0,
+                    end: end.unwrap_or(line.len()),
+                    kind: rule.kind,
+                });
+            }
+            match end {
+                Some(end) => pos = end,
+                None => return (spans, state),
+            }
+        }
+        // The next match of every rule, found again once `pos` passes it. A
+        // rule that does not match any more never will.
+        let mut next: Vec<_> = (0..self.rules().len())
+            .map(|index| self.find_rule(index, line, pos))
+            .collect();
+        while pos < line.len() {
+            for (index, found) in next.iter_mut().enumerate() {
+                if matches!(found, Some(((start, _), _)) if *start < pos) {
+                    *found = self.find_rule(index, line, pos);
+                }
+            }
+            let Some((index, ((start, end), (part_start, part_end)))) = next
+                .iter()
+                .enumerate()
+                .filter_map(|(index, found)| found.map(|found| (index, found)))
+                .min_by_key(|&(index, ((start, _), _))| (start, index))
+            else {
+                break;
+            };
+            let rule = &self.rules()[index];
+            let new_pos = if rule.end.is_some() {
+                let region_end = self.region_end(rule, line, end);
+                spans.push(Span {
+                    start,
+                    end: region_end.unwrap_or(line.len()),
+                    kind: rule.kind,
+                });
+                match region_end {
+                    Some(region_end) => region_end,
+                    None => return (spans, Some(index)),
+                }
+            } else {
+                if part_start < part_end {
+                    spans.push(Span {
+                        start: part_start,
+                        end: part_end,
+                        kind: rule.kind,
+                    });
+                }
+                end
+            };
+            // An empty match must not stop the search.
+            pos = if new_pos > pos {
+                new_pos
+            } else {
+                pos + line[pos..].chars().next().map_or(1, char::len_utf8)
+            };
+        }
+        (spans, None)
+    }
+}
+
+// The grammars evi knows, the built in ones and the user's.
+#[derive(Default)]
+pub struct Grammars {
+    grammars: Vec<Rc<Grammar>>,
+}
+
+impl Grammars {
+    pub fn builtin() -> Grammars {
+        let mut grammars = Grammars::default();
+        for text in BUILTIN_GRAMMARS {
+            grammars.add(Grammar::parse(text).expect("a built in grammar is valid"));
+        }
+        grammars
+    }
+
+    /// Adds `grammar`, in place of the grammar with the same name if any.
+    pub fn add(&mut self, grammar: Grammar) {
+        self.grammars.retain(|g| g.name != grammar.name);
+        self.grammars.push(Rc::new(grammar));
+    }
+
+    /// Adds the grammars of the `*.syntax` files in `dir`.
+    pub fn load_dir(&mut self, dir: &Path) -> GenericResult<()> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Ok(());
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "syntax"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let grammar = fs::read_to_string(&path)
+                .map_err(|e| e.into())
+                .and_then(|text| Grammar::parse(&text))
+                .and_then(|grammar| grammar.compile().map(|_| grammar))
+                .map_err(|e| format!("Error in {}: {}", path.to_string_lossy(), e))?;
+            self.add(grammar);
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<Rc<Grammar>> {
+        self.grammars.iter().find(|g| g.name == name).cloned()
+    }
+
+    /// Returns the grammar for the file at `path` whose first line is `first_line`.
+    pub fn detect(&self, path: &Path, first_line: &str) -> Option<Rc<Grammar>> {
+        // User grammars come last and win.
+        self.grammars
+            .iter()
+            .rev()
+            .find(|g| g.matches_file(path, first_line))
+            .cloned()
+    }
+}
+
+// The highlighting of a buffer. The state at the start of each line is
+// kept, so that only the lines from the first changed one on are tokenized
+// again.
+#[derive(Clone, Debug)]
+pub struct Syntax {
+    grammar: Rc<Grammar>,
+    // The state at the start of lines `0..states.len()`.
+    states: RefCell<Vec<LineState>>,
+}
+
+impl Syntax {
+    pub fn new(grammar: Rc<Grammar>) -> Syntax {
+        Syntax {
+            grammar,
+            states: RefCell::new(vec![None]),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.grammar.name
+    }
+
+    /// Forgets the states after line `row`, which changed.
+    pub fn invalidate_from(&self, row: usize) {
+        self.states.borrow_mut().truncate(row + 1);
+    }
+
+    /// Returns the spans of line `row` of `buffer`.
+    pub fn highlight(&self, buffer: &Buffer, row: usize) -> Vec<Span> {
+        let line_text = |row: usize| {
+            buffer
+                .get_line(row)
+                .map(|line| line.to_string())
+                .unwrap_or_default()
+        };
+        let mut states = self.states.borrow_mut();
+        while states.len() <= row {
+            let previous = states.len() - 1;
+            let (_, state) = self
+                .grammar
+                .tokenize(&line_text(previous), states[previous]);
+            states.push(state);
+        }
+        self.grammar.tokenize(&line_text(row), states[row]).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(grammar: &Grammar, line: &'a str, state: LineState) -> Vec<(&'a str, TokenKind)> {
+        let (spans, _) = grammar.tokenize(line, state);
+        spans
+            .iter()
+            .map(|span| (&line[span.start..span.end], span.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let grammar = Grammar::parse(
+            r#"
+name test
+keyword Keyword fn let
+match Function \b([a-z_]+)\s*\(
+match Comment //.*
+region Comment /\* \*/
+region String " " \\.
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&grammar, r#"fn f() { let s = "a\"b"; } // c"#, None),
+            [
+                ("fn", TokenKind::Keyword),
+                ("f", TokenKind::Function),
+                ("let", TokenKind::Keyword),
+                (r#""a\"b""#, TokenKind::String),
+                ("// c", TokenKind::Comment),
+            ]
+        );
+
+        // A region goes on over line ends.
+        let (spans, state) = grammar.tokenize("let a /* b", None);
+        assert_eq!(spans.last().unwrap().kind, TokenKind::Comment);
+        assert!(state.is_some());
+        assert_eq!(
+            kinds(&grammar, "c */ let", state),
+            [("c */", TokenKind::Comment), ("let", TokenKind::Keyword)]
+        );
+        assert_eq!(grammar.tokenize("c */ let", state).1, None);
+    }
+
+    #[test]
+    fn test_highlight_follows_changes() {
+        let grammar = Grammar::parse("name test\nregion Comment /\\* \\*/").unwrap();
+        let syntax = Syntax::new(Rc::new(grammar));
+        let mut buffer = Buffer::from_lines(&["a", "b", "c"]);
+        assert!(syntax.highlight(&buffer, 2).is_empty());
+
+        buffer.set_line(0, "/* a");
+        syntax.invalidate_from(0);
+        assert_eq!(syntax.highlight(&buffer, 2)[0].kind, TokenKind::Comment);
+    }
+
+    #[test]
+    fn test_builtin_grammars() {
+        let grammars = Grammars::builtin();
+        let name = |path: &str, first_line: &str| {
+            grammars
+                .detect(Path::new(path), first_line)
+                .map(|grammar| grammar.name.clone())
+        };
+        assert_eq!(name("src/main.rs", "").as_deref(), Some("rust"));
+        assert_eq!(name("a.py", "").as_deref(), Some("python"));
+        assert_eq!(name("build", "#!/bin/bash").as_deref(), Some("sh"));
+        assert_eq!(name("README.md", "").as_deref(), Some("markdown"));
+        assert_eq!(name("Cargo.toml", "").as_deref(), Some("toml"));
+        assert_eq!(name("ci.yml", "").as_deref(), Some("yaml"));
+        assert_eq!(name("notes.txt", ""), None);
+        for grammar in &grammars.grammars {
+            grammar.compile().unwrap();
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use crossterm::style::Color;
+
+use crate::generic_error::GenericResult;
+use crate::screen::Style;
+use crate::syntax::TokenKind;
+
+// The color schemes that come with evi, in the same format as the user's own.
+const BUILTIN_THEMES: &[&str] = &[
+    include_str!("../colors/default.theme"),
+    include_str!("../colors/light.theme"),
+];
+
+// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses the color support of the terminal from `$COLORTERM` and `$TERM`.
+    pub fn detect() -> ColorDepth {
+        let var = |name| env::var(name).unwrap_or_default();
+        if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            ColorDepth::TrueColor
+        } else if var("TERM").contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+// What a theme gives colors to, named like vim's highlight groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Group {
+    Normal,
+    StatusLine,
+    StatusLineNC,
+    VertSplit,
+    ErrorMsg,
+    ModeMsg,
+    MoreMsg,
+    Question,
+    Search,
+    Visual,
+    LineNr,
+    CursorLineNr,
+    NonText,
+    SpecialKey,
+    Comment,
+    Constant,
+    String,
+    Number,
+    Identifier,
+    Function,
+    Keyword,
+    Type,
+    PreProc,
+    Special,
+    Title,
+    Underlined,
+}
+
+impl Group {
+    fn from_name(name: &str) -> Option<Group> {
+        let group = match name {
+            "Normal" => Group::Normal,
+            "StatusLine" => Group::StatusLine,
+            "StatusLineNC" => Group::StatusLineNC,
+            "VertSplit" => Group::VertSplit,
+            "ErrorMsg" => Group::ErrorMsg,
+            "ModeMsg" => Group::ModeMsg,
+            "MoreMsg" => Group::MoreMsg,
+            "Question" => Group::Question,
+            "Search" => Group::Search,
+            "Visual" => Group::Visual,
+            "LineNr" => Group::LineNr,
+            "CursorLineNr" => Group::CursorLineNr,
+            "NonText" => Group::NonText,
+            "SpecialKey" => Group::SpecialKey,
+            "Comment" => Group::Comment,
+            "Constant" => Group::Constant,
+            "String" => Group::String,
+            "Number" => Group::Number,
+            "Identifier" => Group::Identifier,
+            "Function" => Group::Function,
+            "Keyword" => Group::Keyword,
+            "Type" => Group::Type,
+            "PreProc" => Group::PreProc,
+            "Special" => Group::Special,
+            "Title" => Group::Title,
+            "Underlined" => Group::Underlined,
+            _ => return None,
+        };
+        Some(group)
+    }
+
+    // The group whose colors are used when a theme leaves this one out.
+    fn parent(&self) -> Option<Group> {
+        match self {
+            Group::String | Group::Number => Some(Group::Constant),
+            Group::Function => Some(Group::Identifier),
+            Group::CursorLineNr => Some(Group::LineNr),
+            _ => None,
+        }
+    }
+}
+
+impl From<TokenKind> for Group {
+    fn from(kind: TokenKind) -> Group {
+        match kind {
+            TokenKind::Comment => Group::Comment,
+            TokenKind::Constant => Group::Constant,
+            TokenKind::String => Group::String,
+            TokenKind::Number => Group::Number,
+            TokenKind::Identifier => Group::Identifier,
+            TokenKind::Function => Group::Function,
+            TokenKind::Keyword => Group::Keyword,
+            TokenKind::Type => Group::Type,
+            TokenKind::PreProc => Group::PreProc,
+            TokenKind::Special => Group::Special,
+            TokenKind::Title => Group::Title,
+            TokenKind::Underlined => Group::Underlined,
+        }
+    }
+}
+
+// The colors of a group for each color depth; a missing one is made from
+// the others.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct GroupColors {
+    fg16: Option<Color>,
+    bg16: Option<Color>,
+    fg256: Option<Color>,
+    bg256: Option<Color>,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    reverse: bool,
+    underline: bool,
+}
+
+impl GroupColors {
+    fn style(&self, depth: ColorDepth) -> Style {
+        Style {
+            fg: pick_color(depth, self.fg16, self.fg256, self.fg),
+            bg: pick_color(depth, self.bg16, self.bg256, self.bg),
+            bold: self.bold,
+            reverse: self.reverse,
+            underline: self.underline,
+        }
+    }
+}
+
+fn pick_color(
+    depth: ColorDepth,
+    ansi16: Option<Color>,
+    ansi256: Option<Color>,
+    rgb: Option<Color>,
+) -> Option<Color> {
+    match depth {
+        ColorDepth::TrueColor => rgb.or(ansi256).or(ansi16),
+        ColorDepth::Ansi256 => ansi256.or(rgb.map(to_ansi256)).or(ansi16),
+        ColorDepth::Ansi16 => ansi16.or(ansi256.or(rgb).map(to_ansi16)),
+    }
+}
+
+// The usual colors of the 16 terminal colors, in the order of their numbers.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// The levels of the 6x6x6 color cube of the 256 colors.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn rgb_of(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(value @ 0..=15) => ANSI16[value as usize].1,
+        Color::AnsiValue(value @ 16..=231) => {
+            let value = value - 16;
+            (
+                CUBE_LEVELS[value as usize / 36],
+                CUBE_LEVELS[value as usize / 6 % 6],
+                CUBE_LEVELS[value as usize % 6],
+            )
+        }
+        Color::AnsiValue(value) => {
+            let gray = 8 + 10 * (value - 232);
+            (gray, gray, gray)
+        }
+        color => ANSI16
+            .iter()
+            .find(|(c, _)| *c == color)
+            .map_or((0, 0, 0), |(_, rgb)| *rgb),
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn to_ansi256(color: Color) -> Color {
+    let rgb = rgb_of(color);
+    let level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap()
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u16 + rgb.1 as u16 + rgb.2 as u16) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as usize;
+    let value = [cube, gray]
+        .into_iter()
+        .min_by_key(|&value| distance(rgb, rgb_of(Color::AnsiValue(value as u8))))
+        .unwrap();
+    Color::AnsiValue(value as u8)
+}
+
+fn to_ansi16(color: Color) -> Color {
+    let rgb = rgb_of(color);
+    ANSI16
+        .iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .unwrap()
+        .0
+}
+
+fn parse_ansi16(name: &str) -> Option<Color> {
+    let color = match name.to_ascii_lowercase().as_str() {
+        "black" => Color::Black,
+        "darkred" => Color::DarkRed,
+        "darkgreen" => Color::DarkGreen,
+        "darkyellow" | "brown" => Color::DarkYellow,
+        "darkblue" => Color::DarkBlue,
+        "darkmagenta" => Color::DarkMagenta,
+        "darkcyan" => Color::DarkCyan,
+        "grey" | "gray" | "lightgrey" | "lightgray" => Color::Grey,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
+
+fn parse_rgb(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::Rgb {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+    })
+}
+
+/// A theme file gives the colors of one highlight group per line; `#` starts
+/// a comment line.
+///
+/// ```text
+/// name default
+/// StatusLine reverse bold
+/// Comment fg16=cyan fg256=14 fg=#80a0ff
+/// ErrorMsg fg16=white bg16=darkred bg256=1 bg=#ff0000
+/// ```
+///
+/// `fg16` and `bg16` are color names (`red`, `darkred`, `grey`, ...), `fg256`
+/// and `bg256` numbers from 0 to 255 and `fg` and `bg` true colors. Colors
+/// left out are made from the ones given for the other color depths.
+#[derive(Debug)]
+pub struct Theme {
+    pub name: String,
+    groups: HashMap<Group, GroupColors>,
+}
+
+impl Theme {
+    pub fn parse(text: &str) -> GenericResult<Theme> {
+        let mut theme = Theme {
+            name: String::new(),
+            groups: HashMap::new(),
+        };
+        for (number, line) in text.lines().enumerate() {
+            theme
+                .parse_line(line.trim())
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        if theme.name.is_empty() {
+            return Err("no name".into());
+        }
+        Ok(theme)
+    }
+
+    fn parse_line(&mut self, line: &str) -> GenericResult<()> {
+        let mut words = line.split_whitespace();
+        let Some(first) = words.next().filter(|word| !word.starts_with('#')) else {
+            return Ok(());
+        };
+        if first == "name" {
+            self.name = words.collect::<Vec<_>>().join(" ");
+            return Ok(());
+        }
+        let group = Group::from_name(first).ok_or_else(|| format!("unknown group {}", first))?;
+        let mut colors = GroupColors::default();
+        for word in words {
+            let invalid = || format!("invalid color {}", word);
+            let ansi256 =
+                |value: &str| value.parse().ok().map(Color::AnsiValue).ok_or_else(invalid);
+            match word.split_once('=') {
+                None if word == "bold" => colors.bold = true,
+                None if word == "reverse" => colors.reverse = true,
+                None if word == "underline" => colors.underline = true,
+                Some(("fg16", value)) => {
+                    colors.fg16 = Some(parse_ansi16(value).ok_or_else(invalid)?)
+                }
+                Some(("bg16", value)) => {
+                    colors.bg16 = Some(parse_ansi16(value).ok_or_else(invalid)?)
+                }
+                Some(("fg256", value)) => colors.fg256 = Some(ansi256(value)?),
+                Some(("bg256", value)) => colors.bg256 = Some(ansi256(value)?),
+                Some(("fg", value)) => colors.fg = Some(parse_rgb(value).ok_or_else(invalid)?),
+                Some(("bg", value)) => colors.bg = Some(parse_rgb(value).ok_or_else(invalid)?),
+                _ => return Err(format!("unknown attribute {}", word).into()),
+            }
+        }
+        self.groups.insert(group, colors);
+        Ok(())
+    }
+
+    /// Returns the style of `group` on a terminal with `depth` colors.
+    pub fn style(&self, group: Group, depth: ColorDepth) -> Style {
+        match (self.groups.get(&group), group.parent()) {
+            (Some(colors), _) => colors.style(depth),
+            (None, Some(parent)) => self.style(parent, depth),
+            (None, None) => Style::default(),
+        }
+    }
+}
+
+// The themes evi knows, the built in ones and the user's.
+#[derive(Default)]
+pub struct Themes {
+    themes: Vec<Rc<Theme>>,
+}
+
+impl Themes {
+    pub fn builtin() -> Themes {
+        let mut themes = Themes::default();
+        for text in BUILTIN_THEMES {
+            themes.add(Theme::parse(text).expect("a built in theme is valid"));
+        }
+        themes
+    }
+
+    /// Adds `theme`, in place of the theme with the same name if any.
+    pub fn add(&mut self, theme: Theme) {
+        self.themes.retain(|t| t.name != theme.name);
+        self.themes.push(Rc::new(theme));
+    }
+
+    /// Adds the themes of the `*.theme` files in `dir`.
+    pub fn load_dir(&mut self, dir: &Path) -> GenericResult<()> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Ok(());
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "theme"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let theme = fs::read_to_string(&path)
+                .map_err(|e| e.into())
+                .and_then(|text| Theme::parse(&text))
+                .map_err(|e| format!("Error in {}: {}", path.to_string_lossy(), e))?;
+            self.add(theme);
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<Rc<Theme>> {
+        self.themes.iter().find(|t| t.name == name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_styles() {
+        let theme = Theme::parse(
+            "name test\nStatusLine reverse bold\nComment fg16=cyan fg256=14 fg=#80a0ff\nLineNr fg=#ffff00",
+        )
+        .unwrap();
+        let comment = |depth| theme.style(Group::Comment, depth).fg;
+        assert_eq!(
+            comment(ColorDepth::TrueColor),
+            Some(Color::Rgb {
+                r: 0x80,
+                g: 0xa0,
+                b: 0xff
+            })
+        );
+        assert_eq!(comment(ColorDepth::Ansi256), Some(Color::AnsiValue(14)));
+        assert_eq!(comment(ColorDepth::Ansi16), Some(Color::Cyan));
+
+        // Colors given only as true colors are made for the other depths.
+        assert_eq!(
+            theme.style(Group::LineNr, ColorDepth::Ansi256).fg,
+            Some(Color::AnsiValue(226))
+        );
+        assert_eq!(
+            theme.style(Group::LineNr, ColorDepth::Ansi16).fg,
+            Some(Color::Yellow)
+        );
+        assert_eq!(
+            theme.style(Group::CursorLineNr, ColorDepth::Ansi16).fg,
+            Some(Color::Yellow)
+        );
+
+        let status_line = theme.style(Group::StatusLine, ColorDepth::Ansi16);
+        assert!(status_line.reverse && status_line.bold && status_line.fg.is_none());
+        assert_eq!(
+            theme.style(Group::Search, ColorDepth::Ansi16),
+            Style::default()
+        );
+    }
+
+    #[test]
+    fn test_theme_errors() {
+        assert!(Theme::parse("StatusLine bold").is_err());
+        let error = Theme::parse("name x\nComment fg=red").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: invalid color fg=red");
+        assert!(Theme::parse("name x\nNoSuchGroup bold").is_err());
+    }
+
+    #[test]
+    fn test_builtin_themes() {
+        let themes = Themes::builtin();
+        assert!(themes.find("default").is_some());
+        assert!(themes.find("light").is_some());
+    }
+}
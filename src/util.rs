@@ -1,20 +1,84 @@
+use std::env;
+use std::path::PathBuf;
+
 use unicode_width::UnicodeWidthChar;
 
 pub fn get_char_width(c: char) -> u16 {
     UnicodeWidthChar::width(c).unwrap_or(0) as u16
 }
 
-pub fn get_line_height(line: &str, width: u16) -> usize {
-    let mut w = 0usize;
-    let mut h = 1usize;
-    for c in line.chars() {
-        w += get_char_width(c) as usize;
-        if w >= width as usize {
-            w = 0;
-            h += 1;
+/// Returns the columns `c` takes when it starts at column `col` of its line:
+/// a tab reaches the next multiple of `tab_stop`.
+pub fn get_char_width_at(c: char, col: usize, tab_stop: usize) -> u16 {
+    if c == '\t' {
+        (tab_stop - col % tab_stop) as u16
+    } else {
+        get_char_width(c)
+    }
+}
+
+/// Returns the columns `line` takes when it is not wrapped.
+pub fn get_line_width<I: IntoIterator<Item = char>>(line: I, tab_stop: usize) -> usize {
+    line.into_iter().fold(0, |col, c| {
+        col + get_char_width_at(c, col, tab_stop) as usize
+    })
+}
+
+/// Returns the screen line and the column, counted from the start of `line`,
+/// that follow its characters when it wraps at `width` columns.
+pub fn get_wrapped_position<I: IntoIterator<Item = char>>(
+    line: I,
+    width: u16,
+    tab_stop: usize,
+) -> (usize, u16) {
+    let (mut rows, mut col, mut line_col) = (0, 0, 0);
+    for c in line {
+        let char_width = get_char_width_at(c, line_col, tab_stop);
+        line_col += char_width as usize;
+        // A tab is made of single cells, which wrap one by one.
+        let (cells, cell_width) = if c == '\t' { (char_width, 1) } else { (1, char_width) };
+        for _ in 0..cells {
+            col += cell_width;
+            if col >= width {
+                col = 0;
+                rows += 1;
+            }
         }
     }
-    h
+    (rows, col)
+}
+
+pub fn get_line_height<I: IntoIterator<Item = char>>(line: I, width: u16, tab_stop: usize) -> usize {
+    get_wrapped_position(line, width, tab_stop).0 + 1
+}
+
+/// The kinds of characters vi words are made of: a word is a run of letters,
+/// digits and underscores, or a run of the other non-blank characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+pub fn get_char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Blank
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// The directory of the user's evi files, `$XDG_CONFIG_HOME/evi` or
+/// `~/.config/evi`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("evi"))
 }
 
 pub fn split_line(input: &str) -> Vec<&str> {
@@ -32,6 +96,28 @@ mod tests {
         assert_eq!(get_char_width('🍣'), 2);
     }
 
+    #[test]
+    fn test_tab_width() {
+        assert_eq!(get_char_width_at('\t', 0, 8), 8);
+        assert_eq!(get_char_width_at('\t', 3, 4), 1);
+        assert_eq!(get_char_width_at('a', 3, 4), 1);
+        assert_eq!(get_line_width("a\tb".chars(), 4), 5);
+        // The tab wraps cell by cell: "ab" and 6 cells wrap twice at 4 columns.
+        assert_eq!(get_wrapped_position("ab\tc".chars(), 4, 8), (2, 1));
+        assert_eq!(get_wrapped_position("ab\tc".chars(), 4, 4), (1, 1));
+        assert_eq!(get_line_height("ab\tc".chars(), 4, 8), 3);
+        assert_eq!(get_line_height("abc".chars(), 4, 8), 1);
+    }
+
+    #[test]
+    fn test_get_char_class() {
+        assert_eq!(get_char_class('a'), CharClass::Word);
+        assert_eq!(get_char_class('_'), CharClass::Word);
+        assert_eq!(get_char_class('あ'), CharClass::Word);
+        assert_eq!(get_char_class('.'), CharClass::Punctuation);
+        assert_eq!(get_char_class('\t'), CharClass::Blank);
+    }
+
     #[test]
     fn test_split_line() {
         assert_eq!(split_line("\n"), vec!["", ""]);
@@ -0,0 +1,593 @@
+use crate::editor::EditorCursorData;
+use crate::generic_error::GenericResult;
+
+// A window needs one line of text and its status line.
+const MIN_HEIGHT: u16 = 2;
+const MIN_WIDTH: u16 = 1;
+// Like vim's 'numberwidth': the narrowest line number column, with its space.
+const NUMBER_WIDTH: u16 = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub row: u16,
+    pub col: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    fn contains(&self, row: u16, col: u16) -> bool {
+        (self.row..self.row + self.height).contains(&row)
+            && (self.col..self.col + self.width).contains(&col)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+// A view onto a buffer. The cursor and viewport of the current window live in
+// the fields of `Editor`; `buffer_number` and `cursor_data` are only up to
+// date for the other windows.
+pub struct Window {
+    pub id: usize,
+    pub buffer_number: usize,
+    pub cursor_data: EditorCursorData,
+    pub options: WindowOptions,
+}
+
+// The options that are local to a window. A new window gets a copy of the
+// options of the window it was split from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowOptions {
+    pub number: bool,
+    pub relative_number: bool,
+    pub wrap: bool,
+    pub list: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> WindowOptions {
+        WindowOptions {
+            number: false,
+            relative_number: false,
+            wrap: true,
+            list: false,
+        }
+    }
+}
+
+impl WindowOptions {
+    /// Returns the width of the line number column, including the space
+    /// after the numbers, for `line_count` lines shown `height` at a time.
+    pub fn number_width(&self, line_count: usize, height: u16) -> u16 {
+        // Relative numbers are never larger than the window is high.
+        let largest = match (self.number, self.relative_number) {
+            (false, false) => return 0,
+            (true, _) => line_count,
+            (false, true) => height as usize,
+        };
+        (largest.to_string().len() as u16 + 1).max(NUMBER_WIDTH)
+    }
+
+    /// Returns the line number column for `row` when the cursor is on
+    /// `cursor_row`. Both 'number' and 'relativenumber' show the number of
+    /// the cursor line on the left, like in vim.
+    pub fn line_number(&self, row: usize, cursor_row: usize, width: u16) -> String {
+        let width = width.saturating_sub(1) as usize;
+        let distance = row.abs_diff(cursor_row);
+        match (self.number, self.relative_number) {
+            (true, true) if distance == 0 => format!("{:<width$} ", row + 1),
+            (_, true) => format!("{:>width$} ", distance),
+            _ => format!("{:>width$} ", row + 1),
+        }
+    }
+}
+
+// Windows are laid out like in vim: a split divides its area among its
+// children, side by side (`vertical`) or stacked. Each child keeps its size
+// along the split direction.
+enum Node {
+    Window(usize),
+    Split {
+        vertical: bool,
+        children: Vec<(Node, u16)>,
+    },
+}
+
+impl Node {
+    fn contains(&self, id: usize) -> bool {
+        match self {
+            Node::Window(window_id) => *window_id == id,
+            Node::Split { children, .. } => children.iter().any(|(child, _)| child.contains(id)),
+        }
+    }
+
+    fn first_window(&self) -> usize {
+        match self {
+            Node::Window(id) => *id,
+            Node::Split { children, .. } => children[0].0.first_window(),
+        }
+    }
+
+    fn window_ids(&self, ids: &mut Vec<usize>) {
+        match self {
+            Node::Window(id) => ids.push(*id),
+            Node::Split { children, .. } => {
+                for (child, _) in children {
+                    child.window_ids(ids);
+                }
+            }
+        }
+    }
+
+    fn layout(&self, rect: Rect, rects: &mut Vec<(usize, Rect)>) {
+        match self {
+            Node::Window(id) => rects.push((*id, rect)),
+            Node::Split { vertical, children } => {
+                let mut offset = 0;
+                for (child, size) in children {
+                    let child_rect = if *vertical {
+                        Rect {
+                            col: rect.col + offset,
+                            width: *size,
+                            ..rect
+                        }
+                    } else {
+                        Rect {
+                            row: rect.row + offset,
+                            height: *size,
+                            ..rect
+                        }
+                    };
+                    child.layout(child_rect, rects);
+                    // A column of '|' separates windows side by side.
+                    offset += size + *vertical as u16;
+                }
+            }
+        }
+    }
+
+    // Makes the sizes of the children add up to the area of the split,
+    // keeping their proportions.
+    fn fit(&mut self, width: u16, height: u16) {
+        let Node::Split { vertical, children } = self else {
+            return;
+        };
+        let (available, min) = if *vertical {
+            (width.saturating_sub(children.len() as u16 - 1), MIN_WIDTH)
+        } else {
+            (height, MIN_HEIGHT)
+        };
+        let total: u32 = children.iter().map(|(_, size)| *size as u32).sum();
+        if total != available as u32 {
+            for (_, size) in children.iter_mut() {
+                *size = (*size as u32 * available as u32 / total.max(1)) as u16;
+            }
+        }
+        // Rounding leftovers go to the last child; windows below the minimum
+        // size borrow from the largest one.
+        let sum: u16 = children.iter().map(|(_, size)| *size).sum();
+        children.last_mut().unwrap().1 += available.saturating_sub(sum);
+        for index in 0..children.len() {
+            while children[index].1 < min {
+                let largest = (0..children.len()).max_by_key(|&i| children[i].1).unwrap();
+                if children[largest].1 <= min {
+                    break;
+                }
+                children[largest].1 -= 1;
+                children[index].1 += 1;
+            }
+        }
+        let vertical = *vertical;
+        for (child, size) in children.iter_mut() {
+            if vertical {
+                child.fit(*size, height);
+            } else {
+                child.fit(width, *size);
+            }
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let Node::Split { children, .. } = self {
+            let total: u16 = children.iter().map(|(_, size)| *size).sum();
+            let count = children.len() as u16;
+            for (index, (child, size)) in children.iter_mut().enumerate() {
+                *size = total / count + ((index as u16) < total % count) as u16;
+                child.equalize();
+            }
+        }
+    }
+
+    // Replaces the window `id` with a split of `new_id` and `id`.
+    fn split(&mut self, id: usize, new_id: usize, vertical: bool, extent: u16) -> bool {
+        let (new_size, old_size) = if vertical {
+            let new_size = extent.saturating_sub(1) / 2;
+            (new_size, extent.saturating_sub(1) - new_size)
+        } else {
+            (extent / 2, extent - extent / 2)
+        };
+        match self {
+            Node::Window(window_id) if *window_id == id => {
+                *self = Node::Split {
+                    vertical,
+                    children: vec![
+                        (Node::Window(new_id), new_size),
+                        (Node::Window(id), old_size),
+                    ],
+                };
+                true
+            }
+            Node::Window(_) => false,
+            Node::Split {
+                vertical: split_vertical,
+                children,
+            } => {
+                if *split_vertical == vertical {
+                    if let Some(index) = children.iter().position(
+                        |(child, _)| matches!(child, Node::Window(window_id) if *window_id == id),
+                    ) {
+                        children[index].1 = old_size;
+                        children.insert(index, (Node::Window(new_id), new_size));
+                        return true;
+                    }
+                }
+                children
+                    .iter_mut()
+                    .any(|(child, _)| child.split(id, new_id, vertical, extent))
+            }
+        }
+    }
+
+    // Removes the window `id` and returns the window that got its space.
+    fn remove(&mut self, id: usize) -> Option<usize> {
+        let Node::Split { vertical, children } = self else {
+            return None;
+        };
+        let Some(index) = children
+            .iter()
+            .position(|(child, _)| matches!(child, Node::Window(window_id) if *window_id == id))
+        else {
+            return children.iter_mut().find_map(|(child, _)| child.remove(id));
+        };
+        let (_, size) = children.remove(index);
+        let neighbour = index.saturating_sub(1);
+        children[neighbour].1 += size + *vertical as u16;
+        let next = children[neighbour].0.first_window();
+        if children.len() == 1 {
+            let (child, _) = children.pop().unwrap();
+            *self = child;
+        }
+        Some(next)
+    }
+
+    // Changes the height of the window `id` by `delta` lines, taking them
+    // from or giving them to the window below (or above, for the last one).
+    fn resize(&mut self, id: usize, delta: i32) -> bool {
+        let Node::Split { vertical, children } = self else {
+            return false;
+        };
+        let Some(index) = children.iter().position(|(child, _)| child.contains(id)) else {
+            return false;
+        };
+        if children[index].0.resize(id, delta) {
+            return true;
+        }
+        if *vertical || children.len() < 2 {
+            return false;
+        }
+        let other = if index + 1 < children.len() {
+            index + 1
+        } else {
+            index - 1
+        };
+        let delta = delta
+            .min(children[other].1 as i32 - MIN_HEIGHT as i32)
+            .max(MIN_HEIGHT as i32 - children[index].1 as i32);
+        children[index].1 = (children[index].1 as i32 + delta) as u16;
+        children[other].1 = (children[other].1 as i32 - delta) as u16;
+        true
+    }
+}
+
+pub struct WindowLayout {
+    root: Node,
+    windows: Vec<Window>,
+    current: usize,
+    next_id: usize,
+    // The area below which the command line is shown.
+    width: u16,
+    height: u16,
+    // Like vim's 'laststatus': 2 gives a lone window a status line too.
+    last_status: usize,
+}
+
+impl WindowLayout {
+    pub fn new(buffer_number: usize) -> WindowLayout {
+        WindowLayout {
+            root: Node::Window(1),
+            windows: vec![Window {
+                id: 1,
+                buffer_number,
+                cursor_data: EditorCursorData::default(),
+                options: WindowOptions::default(),
+            }],
+            current: 1,
+            next_id: 2,
+            width: 0,
+            height: 0,
+            last_status: 1,
+        }
+    }
+
+    pub fn last_status(&self) -> usize {
+        self.last_status
+    }
+
+    pub fn set_last_status(&mut self, last_status: usize) {
+        self.last_status = last_status;
+    }
+
+    pub fn set_area(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.root.fit(width, height);
+    }
+
+    pub fn count(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn current_id(&self) -> usize {
+        self.current
+    }
+
+    pub fn set_current(&mut self, id: usize) {
+        self.current = id;
+    }
+
+    pub fn window(&self, id: usize) -> &Window {
+        self.windows.iter().find(|window| window.id == id).unwrap()
+    }
+
+    pub fn window_mut(&mut self, id: usize) -> &mut Window {
+        self.windows
+            .iter_mut()
+            .find(|window| window.id == id)
+            .unwrap()
+    }
+
+    /// Returns the window ids from top left to bottom right.
+    pub fn window_ids(&self) -> Vec<usize> {
+        let mut ids = Vec::new();
+        self.root.window_ids(&mut ids);
+        ids
+    }
+
+    /// Returns the area of every window, including its status line.
+    pub fn rects(&self) -> Vec<(usize, Rect)> {
+        let mut rects = Vec::new();
+        let area = Rect {
+            row: 0,
+            col: 0,
+            width: self.width,
+            height: self.height,
+        };
+        self.root.layout(area, &mut rects);
+        rects
+    }
+
+    pub fn rect(&self, id: usize) -> Rect {
+        self.rects()
+            .into_iter()
+            .find(|(window_id, _)| *window_id == id)
+            .map(|(_, rect)| rect)
+            .unwrap_or_default()
+    }
+
+    /// Windows have a status line once the screen is split, or always with
+    /// 'laststatus' 2.
+    pub fn has_status_lines(&self) -> bool {
+        self.count() > 1 || self.last_status >= 2
+    }
+
+    /// The number of lines a window has for text.
+    pub fn text_height(&self, id: usize) -> u16 {
+        self.rect(id)
+            .height
+            .saturating_sub(self.has_status_lines() as u16)
+    }
+
+    /// Splits the current window. The new window shows the same buffer and
+    /// becomes the current one.
+    pub fn split(&mut self, vertical: bool, cursor_data: EditorCursorData) -> GenericResult<usize> {
+        let rect = self.rect(self.current);
+        let (extent, min) = if vertical {
+            (rect.width, 2 * MIN_WIDTH + 1)
+        } else {
+            (rect.height, 2 * MIN_HEIGHT)
+        };
+        if extent < min {
+            return Err("E36: Not enough room".into());
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let Window {
+            buffer_number,
+            options,
+            ..
+        } = *self.window(self.current);
+        self.windows.push(Window {
+            id,
+            buffer_number,
+            cursor_data,
+            options,
+        });
+        self.root.split(self.current, id, vertical, extent);
+        self.current = id;
+        self.root.fit(self.width, self.height);
+        Ok(id)
+    }
+
+    /// Closes window `id` and returns the window that takes its place.
+    pub fn close(&mut self, id: usize) -> GenericResult<usize> {
+        if self.count() == 1 {
+            return Err("E444: Cannot close last window".into());
+        }
+        let next = self
+            .root
+            .remove(id)
+            .unwrap_or_else(|| self.root.first_window());
+        self.windows.retain(|window| window.id != id);
+        self.root.fit(self.width, self.height);
+        if self.current == id {
+            self.current = next;
+        }
+        Ok(next)
+    }
+
+    /// Closes all windows but the current one.
+    pub fn only(&mut self) {
+        self.windows.retain(|window| window.id == self.current);
+        self.root = Node::Window(self.current);
+    }
+
+    /// Closes the other windows that show buffer `number`.
+    pub fn close_showing(&mut self, number: usize) {
+        let ids: Vec<usize> = self
+            .windows
+            .iter()
+            .filter(|window| window.id != self.current && window.buffer_number == number)
+            .map(|window| window.id)
+            .collect();
+        for id in ids {
+            let _ = self.close(id);
+        }
+    }
+
+    pub fn resize(&mut self, delta: i32) {
+        self.root.resize(self.current, delta);
+        self.root.fit(self.width, self.height);
+    }
+
+    pub fn equalize(&mut self) {
+        self.root.equalize();
+        self.root.fit(self.width, self.height);
+    }
+
+    /// Finds the window next to the current one, in line with the cursor at
+    /// `row`, `col` in the window.
+    pub fn neighbour(&self, direction: Direction, row: u16, col: u16) -> Option<usize> {
+        let rect = self.rect(self.current);
+        let (row, col) = match direction {
+            Direction::Left => (rect.row + row, rect.col.checked_sub(2)?),
+            Direction::Right => (rect.row + row, rect.col + rect.width + 1),
+            Direction::Up => (rect.row.checked_sub(1)?, rect.col + col),
+            Direction::Down => (rect.row + rect.height, rect.col + col),
+        };
+        self.rects()
+            .into_iter()
+            .find(|(_, rect)| rect.contains(row, col))
+            .map(|(id, _)| id)
+    }
+
+    /// The window after the current one, wrapping around.
+    pub fn next(&self) -> usize {
+        let ids = self.window_ids();
+        let index = ids.iter().position(|&id| id == self.current).unwrap_or(0);
+        ids[(index + 1) % ids.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(row: u16, col: u16, width: u16, height: u16) -> Rect {
+        Rect {
+            row,
+            col,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_split_and_close_windows() {
+        let mut layout = WindowLayout::new(1);
+        layout.set_area(80, 23);
+        assert_eq!(layout.text_height(1), 23);
+
+        // :split puts the new window above, :vsplit on the left.
+        let top = layout.split(false, EditorCursorData::default()).unwrap();
+        assert_eq!(
+            layout.rects(),
+            vec![(top, rect(0, 0, 80, 11)), (1, rect(11, 0, 80, 12))]
+        );
+        assert_eq!(layout.text_height(top), 10);
+        let left = layout.split(true, EditorCursorData::default()).unwrap();
+        assert_eq!(layout.rect(left), rect(0, 0, 39, 11));
+        assert_eq!(layout.rect(top), rect(0, 40, 40, 11));
+        assert_eq!(layout.window_ids(), vec![left, top, 1]);
+
+        assert_eq!(layout.neighbour(Direction::Right, 0, 0), Some(top));
+        assert_eq!(layout.neighbour(Direction::Down, 0, 5), Some(1));
+        assert_eq!(layout.neighbour(Direction::Left, 0, 0), None);
+        assert_eq!(layout.next(), top);
+
+        layout.resize(3);
+        assert_eq!(layout.rect(1), rect(14, 0, 80, 9));
+        layout.equalize();
+        assert_eq!(layout.rect(1), rect(12, 0, 80, 11));
+
+        assert_eq!(layout.close(left).unwrap(), top);
+        assert_eq!(layout.current_id(), top);
+        assert_eq!(layout.rect(top), rect(0, 0, 80, 12));
+        layout.only();
+        assert_eq!(layout.rects(), vec![(top, rect(0, 0, 80, 23))]);
+        assert!(layout.close(top).is_err());
+    }
+
+    #[test]
+    fn test_split_needs_room() {
+        let mut layout = WindowLayout::new(1);
+        layout.set_area(80, 4);
+        layout.split(false, EditorCursorData::default()).unwrap();
+        assert!(layout.split(false, EditorCursorData::default()).is_err());
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let number = WindowOptions {
+            number: true,
+            relative_number: false,
+            ..Default::default()
+        };
+        assert_eq!(WindowOptions::default().number_width(10, 20), 0);
+        assert_eq!(number.number_width(10, 20), 4);
+        assert_eq!(number.number_width(12345, 20), 6);
+        assert_eq!(number.line_number(9, 0, 4), " 10 ");
+
+        let relative = WindowOptions {
+            number: false,
+            relative_number: true,
+            ..Default::default()
+        };
+        assert_eq!(relative.number_width(12345, 20), 4);
+        assert_eq!(relative.line_number(9, 7, 4), "  2 ");
+        assert_eq!(relative.line_number(7, 7, 4), "  0 ");
+
+        let hybrid = WindowOptions {
+            number: true,
+            relative_number: true,
+            ..Default::default()
+        };
+        assert_eq!(hybrid.line_number(6, 7, 4), "  1 ");
+        assert_eq!(hybrid.line_number(7, 7, 4), "8   ");
+    }
+}
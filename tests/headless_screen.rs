@@ -0,0 +1,504 @@
+//! Screen tests: keys are fed to an editor through the in-memory backend and
+//! the resulting screen is checked cell by cell.
+use evi::backend::MemoryBackend;
+use evi::buffer::Buffer;
+use evi::editor::Editor;
+use evi::main_loop::run;
+use evi::screen::Style;
+use evi::theme::{ColorDepth, Group};
+
+fn run_keys(lines: &[&str], width: u16, height: u16, keys: &str) -> (Editor, MemoryBackend) {
+    let mut editor = Editor::new();
+    editor.buffer = Buffer::from_lines(lines);
+    let mut backend = MemoryBackend::new(width, height);
+    backend.push_keys(keys);
+    run(&mut editor, &mut backend).unwrap();
+    (editor, backend)
+}
+
+#[test]
+fn initial_screen_shows_the_buffer() {
+    let (_, backend) = run_keys(&["abc", "def"], 10, 4, "");
+    assert_eq!(backend.lines(), ["abc", "def", "", ""]);
+    assert_eq!(backend.screen().row_text(0), "abc       ");
+    assert_eq!(backend.cursor(), (0, 0));
+}
+
+#[test]
+fn keys_edit_the_screen() {
+    let (_, backend) = run_keys(&["abc", "def"], 10, 4, "jlx");
+    assert_eq!(backend.lines(), ["abc", "df", "", ""]);
+    assert_eq!(backend.cursor(), (1, 1));
+
+    let (_, backend) = run_keys(&["abc"], 20, 3, "ixy");
+    assert_eq!(backend.lines(), ["xyabc", "", "-- INSERT --"]);
+    assert_eq!(backend.cursor(), (2, 0));
+}
+
+#[test]
+fn long_lines_wrap() {
+    let (_, backend) = run_keys(&["0123456789abc", "x"], 10, 5, "j");
+    assert_eq!(backend.lines(), ["0123456789", "abc", "x", "", ""]);
+    assert_eq!(backend.cursor(), (0, 2));
+}
+
+#[test]
+fn moving_past_the_bottom_scrolls() {
+    // A line is left blank below the cursor line.
+    let lines = ["a", "b", "c", "d", "e", "f"];
+    let (_, backend) = run_keys(&lines, 10, 5, "jjj");
+    assert_eq!(backend.lines(), ["b", "c", "d", "e", ""]);
+    assert_eq!(backend.cursor(), (0, 2));
+
+    let (_, backend) = run_keys(&lines, 10, 5, "jjjkkk");
+    assert_eq!(backend.lines(), ["a", "b", "c", "d", ""]);
+    assert_eq!(backend.cursor(), (0, 0));
+}
+
+#[test]
+fn ex_command_line_is_shown_while_typing() {
+    let (_, backend) = run_keys(&["abc"], 10, 3, ":s/b");
+    assert_eq!(backend.lines(), ["abc", "", ":s/b"]);
+
+    let (_, backend) = run_keys(&["abc"], 10, 3, ":s/b/x/\r");
+    assert_eq!(backend.lines()[0], "axc");
+}
+
+#[test]
+fn split_windows_have_status_lines() {
+    let (editor, backend) = run_keys(&["abc"], 10, 6, ":sp\r");
+    assert_eq!(
+        backend.lines(),
+        ["abc", "[No Name]", "abc", "", "[No Name]", ""]
+    );
+    let style = |group| editor.theme.style(group, editor.color_depth);
+    assert_eq!(backend.screen().cell(1, 0).style, style(Group::StatusLine));
+    assert_eq!(backend.screen().cell(4, 0).style, style(Group::StatusLineNC));
+    assert_ne!(style(Group::StatusLine), style(Group::StatusLineNC));
+    assert_eq!(backend.cursor(), (0, 0));
+}
+
+#[test]
+fn status_lines_are_clipped_to_narrow_windows() {
+    let (_, backend) = run_keys(&["abc"], 16, 4, ":set ru\r:vsp\r");
+    assert_eq!(backend.lines()[2], "[No 1,1|[No 1,1");
+    let (_, backend) = run_keys(&["abc"], 40, 4, ":vsp\r:vsp\r:vsp\r:vsp\r");
+    assert_eq!(backend.lines()[2], "[|[N|[No |[No Name]|[No Name]");
+}
+
+#[test]
+fn quitting_ends_the_run() {
+    let (editor, backend) = run_keys(&["abc"], 10, 3, ":q\rx");
+    assert!(editor.should_exit);
+    assert_eq!(backend.lines()[0], "abc");
+}
+
+#[test]
+fn line_numbers_narrow_the_text() {
+    let (_, backend) = run_keys(&["abcdefgh", "x", "y"], 10, 5, ":set nu\rj");
+    assert_eq!(
+        backend.lines(),
+        ["  1 abcdef", "    gh", "  2 x", "  3 y", ""]
+    );
+    assert_eq!(backend.cursor(), (4, 2));
+
+    let (_, backend) = run_keys(&["abcdefgh", "x", "y"], 10, 5, ":set nu rnu\rj");
+    assert_eq!(
+        backend.lines(),
+        ["  1 abcdef", "    gh", "2   x", "  1 y", ""]
+    );
+}
+
+#[test]
+fn syntax_is_highlighted() {
+    let mut editor = Editor::new();
+    editor.buffer = Buffer::from_lines(&["fn f() {}", "/* a", "b */ 1"]);
+    let mut backend = MemoryBackend::new(20, 4);
+    backend.push_keys(":set syntax=rust\r");
+    run(&mut editor, &mut backend).unwrap();
+
+    let style = |row, col| backend.screen().cell(row, col).style;
+    let keyword = style(0, 0);
+    assert_ne!(keyword, Style::default());
+    assert_eq!(style(0, 1), keyword);
+    assert_eq!(style(0, 2), Style::default());
+    let comment = style(1, 0);
+    assert_ne!(comment, keyword);
+    assert_eq!(style(2, 3), comment);
+    assert_ne!(style(2, 5), comment);
+}
+
+#[test]
+fn colorscheme_changes_the_colors() {
+    let mut editor = Editor::new();
+    editor.buffer = Buffer::from_lines(&["# a"]);
+    let mut backend = MemoryBackend::new(20, 3);
+    backend.set_color_depth(ColorDepth::Ansi256);
+    backend.push_keys(":set syntax=sh\r");
+    run(&mut editor, &mut backend).unwrap();
+    let dark = backend.screen().cell(0, 0).style;
+    assert_eq!(dark, editor.theme.style(Group::Comment, ColorDepth::Ansi256));
+
+    backend.push_keys(":colorscheme light\r");
+    run(&mut editor, &mut backend).unwrap();
+    let light = backend.screen().cell(0, 0).style;
+    assert_ne!(light, dark);
+    assert_eq!(light, editor.theme.style(Group::Comment, ColorDepth::Ansi256));
+
+    backend.push_keys(":colo nosuch\r");
+    run(&mut editor, &mut backend).unwrap();
+    assert!(backend.lines()[2].starts_with("E185: Cannot find"));
+    let error = backend.screen().cell(2, 0).style;
+    assert_eq!(error, editor.theme.style(Group::ErrorMsg, ColorDepth::Ansi256));
+}
+
+#[test]
+fn nowrap_scrolls_sideways() {
+    let lines = ["0123456789abcdefghij", "short", "x"];
+    let (editor, backend) = run_keys(&lines, 10, 4, ":set nowrap\r");
+    assert_eq!(backend.lines(), ["012345678>", "short", "x", ""]);
+
+    // Without 'sidescroll' the cursor is put in the middle.
+    let (_, backend) = run_keys(&lines, 10, 4, ":set nowrap\r$");
+    assert_eq!(backend.lines(), ["<fghij", "<", "<", ""]);
+    assert_eq!(backend.cursor(), (5, 0));
+    assert_eq!(
+        backend.screen().cell(0, 0).style,
+        editor.theme.style(Group::NonText, editor.color_depth)
+    );
+
+    let (_, backend) = run_keys(&lines, 10, 4, ":set nowrap ss=1 siso=2\r9l");
+    assert_eq!(backend.lines()[0], "<3456789a>");
+    assert_eq!(backend.cursor(), (7, 0));
+
+    let (_, backend) = run_keys(&lines, 10, 4, ":set nowrap\r$0");
+    assert_eq!(backend.lines()[0], "012345678>");
+    assert_eq!(backend.cursor(), (0, 0));
+
+    let (_, backend) = run_keys(&lines, 10, 4, ":set nowrap\r4l3zl");
+    assert_eq!(backend.lines()[0], "<456789ab>");
+    assert_eq!(backend.cursor(), (1, 0));
+}
+
+#[test]
+fn tabs_are_expanded() {
+    let lines = ["\tab", "x\ty", "a\u{a0}b  "];
+    let (_, backend) = run_keys(&lines, 20, 4, "l");
+    assert_eq!(backend.lines(), ["        ab", "x       y", "a\u{a0}b", ""]);
+    assert_eq!(backend.cursor(), (8, 0));
+
+    let (_, backend) = run_keys(&lines, 20, 4, ":set ts=4\rjl");
+    assert_eq!(backend.lines()[1], "x   y");
+    assert_eq!(backend.cursor(), (1, 1));
+    let (_, backend) = run_keys(&lines, 20, 4, ":set ts=4\rjll");
+    assert_eq!(backend.cursor(), (4, 1));
+
+    // A tab wraps cell by cell.
+    let (_, backend) = run_keys(&["abc\td"], 6, 4, "$");
+    assert_eq!(backend.lines(), ["abc", "  d", "", ""]);
+    assert_eq!(backend.cursor(), (2, 1));
+}
+
+#[test]
+fn list_shows_whitespace() {
+    let lines = ["\tab", "x\ty", "a\u{a0}b  "];
+    let (editor, backend) = run_keys(&lines, 20, 4, ":set list\r");
+    assert_eq!(backend.lines(), ["        ab$", "x       y$", "a\u{a0}b  $", ""]);
+    assert_eq!(
+        backend.screen().cell(0, 10).style,
+        editor.theme.style(Group::NonText, editor.color_depth)
+    );
+
+    let keys = ":set list lcs=tab:>-,trail:~,nbsp:+\r";
+    let (editor, backend) = run_keys(&lines, 20, 4, keys);
+    assert_eq!(backend.lines(), [">-------ab", "x>------y", "a+b~~", ""]);
+    assert_eq!(
+        backend.screen().cell(0, 0).style,
+        editor.theme.style(Group::SpecialKey, editor.color_depth)
+    );
+
+    let (_, backend) = run_keys(&lines, 20, 4, ":set list lcs=tab:<->\r");
+    assert_eq!(backend.lines()[0], "<------>ab");
+}
+
+#[test]
+fn long_output_is_paged() {
+    let lines = ["a", "b", "c", "d", "e", "f"];
+    let (_, backend) = run_keys(&lines, 40, 5, ":%p\r");
+    assert_eq!(backend.lines(), ["a", "b", "c", "d", "-- More --"]);
+    let more = backend.screen().cell(4, 0).style;
+    assert_eq!(more, Editor::new().theme.style(Group::MoreMsg, ColorDepth::TrueColor));
+
+    let (_, backend) = run_keys(&lines, 40, 5, ":%p\r ");
+    assert_eq!(
+        backend.lines(),
+        ["c", "d", "e", "f", "Press ENTER or type command to continue"]
+    );
+
+    // The key after the prompt is a command; the cursor is on the last line.
+    let (editor, backend) = run_keys(&lines, 40, 5, ":1,3nu\rx");
+    assert_eq!(backend.lines(), ["a", "b", "", "d", ""]);
+    assert_eq!(editor.buffer.line(2).to_string(), "");
+
+    let (_, backend) = run_keys(&["a\tb"], 40, 3, ":l\r");
+    assert_eq!(backend.lines(), ["a\tb", "", "a^Ib$"].map(|s| s.replace('\t', "       ")));
+}
+
+#[test]
+fn line_numbers_are_shown() {
+    let lines = ["foo", "bar", "baz"];
+    let (_, backend) = run_keys(&lines, 20, 4, ":=\r");
+    assert_eq!(backend.lines()[3], "3");
+    let (_, backend) = run_keys(&lines, 20, 4, "j:.=\r");
+    assert_eq!(backend.lines()[3], "2");
+    let (_, backend) = run_keys(&lines, 20, 4, ":/ba/=\r");
+    assert_eq!(backend.lines()[3], "2");
+    let (_, backend) = run_keys(&lines, 20, 4, ":/qux/=\r");
+    assert_eq!(backend.lines()[3], "E486: Pattern not fo");
+}
+
+#[test]
+fn ruler_and_showcmd_are_on_the_command_line() {
+    let lines = ["abc", "\tx"];
+    let (_, backend) = run_keys(&lines, 40, 4, ":set ruler showcmd\rjl");
+    assert_eq!(backend.lines()[3], format!("{:22}2,2-9         All", ""));
+    let (_, backend) = run_keys(&lines, 40, 4, ":set ruler showcmd\r2d");
+    assert_eq!(backend.lines()[3], format!("{:11}2d{:9}1,1           All", "", ""));
+
+    // the mode is kept apart from the messages
+    let (_, backend) = run_keys(&lines, 40, 4, ":set ruler\ri");
+    assert_eq!(backend.lines()[3], format!("-- INSERT --{:10}1,1           All", ""));
+    let (_, backend) = run_keys(&lines, 40, 4, ":set ruler noshowmode\ri");
+    assert_eq!(backend.lines()[3], format!("{:22}1,1           All", ""));
+}
+
+#[test]
+fn status_lines_show_the_format() {
+    let (_, backend) = run_keys(&["abc"], 30, 4, ":set ls=2 ruler\rx");
+    assert_eq!(backend.lines(), ["bc", "", "[No Name] [+]  1,1        All", ""]);
+    let style = backend.screen().cell(2, 0).style;
+    assert_eq!(style, Editor::new().theme.style(Group::StatusLine, ColorDepth::TrueColor));
+
+    let (_, backend) = run_keys(&["abc", "def"], 30, 4, ":set ls=2 stl=%f%m\\ %l/%L%=%c\rjl");
+    assert_eq!(backend.lines()[2], "[No Name] 2/2                2");
+}
+
+#[test]
+fn counts_of_operators_multiply() {
+    let lines = ["a", "b", "c", "d", "e", "f", "g", "h"];
+    let (_, backend) = run_keys(&lines, 10, 5, "2dd");
+    assert_eq!(backend.lines(), ["c", "d", "e", "f", ""]);
+    let (_, backend) = run_keys(&lines, 10, 5, "2d2j");
+    assert_eq!(backend.lines(), ["f", "g", "h", "", ""]);
+    // a register, and keys that are no command, change nothing
+    let (_, backend) = run_keys(&lines, 10, 5, "\"adqjdd");
+    assert_eq!(backend.lines(), ["a", "c", "d", "e", ""]);
+}
+
+#[test]
+fn motions_of_two_keys() {
+    let lines = ["a", "b", "c", "d", "e", "f"];
+    let (_, backend) = run_keys(&lines, 10, 8, "4ggdd");
+    assert_eq!(backend.lines()[..5], ["a", "b", "c", "e", "f"]);
+    let (_, backend) = run_keys(&lines, 10, 8, "jjjdgg");
+    assert_eq!(backend.lines()[..3], ["e", "f", ""]);
+    let (_, backend) = run_keys(&lines, 10, 8, "5ggd3gg");
+    assert_eq!(backend.lines()[..4], ["a", "b", "f", ""]);
+}
+
+#[test]
+fn mappings_type_their_keys() {
+    let lines = ["a", "b", "c", "d"];
+    let (_, backend) = run_keys(&lines, 10, 5, ":map Q jdd\rQ");
+    assert_eq!(backend.lines(), ["a", "c", "d", "", ""]);
+    // the keys of a noremap mapping are not mapped again
+    let (_, backend) = run_keys(&lines, 10, 5, ":map j dd\r:noremap Q j\rQx");
+    assert_eq!(backend.lines(), ["a", "", "c", "d", ""]);
+    let (_, backend) = run_keys(&lines, 10, 5, ":map j jx\rj");
+    assert_eq!(backend.lines(), ["a", "", "c", "d", ""]);
+    let (_, backend) = run_keys(&["abc"], 10, 3, ":map Q ix\\|y<Bar><Esc>\rQ");
+    assert_eq!(backend.lines(), ["x|y|abc", "", ""]);
+    let (_, backend) = run_keys(&lines, 30, 5, ":map a b\r:map b a\ra");
+    assert_eq!(backend.lines()[4], "E223: Recursive mapping");
+
+    // insert mode, and the keys of a mapping waiting for the rest of it
+    let (_, backend) = run_keys(&["abc"], 20, 3, ":imap jk <Esc>\rixjk");
+    assert_eq!(backend.lines(), ["xabc", "", ""]);
+    let (_, backend) = run_keys(&["abc"], 60, 3, ":set sc\r:imap jk <Esc>\rixj");
+    assert_eq!(backend.lines()[2], format!("-- INSERT --{:37}j", ""));
+    let (_, backend) = run_keys(&["abc"], 20, 3, ":imap jk <Esc>\rixjjk");
+    assert_eq!(backend.lines(), ["xjabc", "", ""]);
+}
+
+#[test]
+fn map_lists_the_mappings() {
+    let (_, backend) = run_keys(&["abc"], 30, 3, ":nnoremap Q dd\r:map\r");
+    assert_eq!(backend.lines()[2], "n  Q            * dd");
+    let (_, backend) = run_keys(&["abc"], 30, 3, ":map Q dd\r:unmap Q\r:map\r");
+    assert_eq!(backend.lines()[2], "No mapping found");
+    let (_, backend) = run_keys(&["abc"], 30, 3, ":unmap Q\r");
+    assert_eq!(backend.lines()[2], "E31: No such mapping");
+}
+
+#[test]
+fn deletes_fill_the_registers() {
+    let lines = ["one", "two", "three", "four"];
+    let (editor, backend) = run_keys(&lines, 10, 5, "\"addx\"Add:d _\r");
+    assert_eq!(backend.lines(), ["four", "", "", "", ""]);
+    let a = editor.registers.get('a').unwrap();
+    assert_eq!((a.text.as_str(), a.linewise), ("one\nwo\n", true));
+    assert_eq!(editor.registers.get('"'), Some(a));
+    assert_eq!(editor.registers.get('-').unwrap().text, "t");
+
+    let lines = ["one two", "x", "three", "four"];
+    let (editor, backend) = run_keys(&lines, 10, 5, "dwjdd:1,2d\r");
+    assert_eq!(backend.lines(), ["four", "", "", "", ""]);
+    assert_eq!(editor.registers.get('1').unwrap().text, "two\nthree\n");
+    assert_eq!(editor.registers.get('2').unwrap().text, "x\n");
+    assert_eq!(editor.registers.get('-').unwrap().text, "one ");
+}
+
+#[test]
+fn x_deletes_count_characters_at_once() {
+    let (editor, backend) = run_keys(&["abcdef"], 10, 3, "3xp");
+    assert_eq!(backend.lines()[0], "dabcef");
+    assert_eq!(editor.registers.get('-').unwrap().text, "abc");
+    let (editor, backend) = run_keys(&["abcdef", "g"], 10, 4, "\"a3x$\"ap");
+    assert_eq!(backend.lines()[..2], ["defabc", "g"]);
+    assert_eq!(editor.registers.get('a').unwrap().text, "abc");
+    // not past the end of the line, and undone at once
+    let (_, backend) = run_keys(&["abcdef", "g"], 10, 4, "ll9xp");
+    assert_eq!(backend.lines()[..2], ["abcdef", "g"]);
+    let (_, backend) = run_keys(&["abcdef"], 10, 3, "3xu");
+    assert_eq!(backend.lines()[0], "abcdef");
+    let (_, backend) = run_keys(&["abcdefgh"], 10, 3, "2x.");
+    assert_eq!(backend.lines()[0], "efgh");
+    let (_, backend) = run_keys(&["abcdefgh"], 10, 3, "2x3.");
+    assert_eq!(backend.lines()[0], "fgh");
+}
+
+#[test]
+fn yank_and_put() {
+    let lines = ["abc de", "f"];
+    let (_, backend) = run_keys(&lines, 10, 5, "yyjp");
+    assert_eq!(backend.lines(), ["abc de", "f", "abc de", "", ""]);
+    assert_eq!(backend.cursor(), (0, 2));
+    let (_, backend) = run_keys(&lines, 10, 5, "jyyk2P");
+    assert_eq!(backend.lines(), ["f", "f", "abc de", "f", ""]);
+    let (_, backend) = run_keys(&lines, 20, 5, "yw$p");
+    assert_eq!(backend.lines(), ["abc deabc", "f", "", "", ""]);
+    assert_eq!(backend.cursor(), (9, 0));
+    let (_, backend) = run_keys(&lines, 10, 5, "\"a2Y\"bylj\"ap\"bPuu");
+    assert_eq!(backend.lines(), ["abc de", "f", "", "", ""]);
+    let (_, backend) = run_keys(&lines, 30, 5, "\"zp");
+    assert_eq!(backend.lines()[4], "E353: Nothing in register z");
+}
+
+#[test]
+fn change_inserts_in_place_of_the_text() {
+    let lines = ["abc de", "f"];
+    let (editor, backend) = run_keys(&lines, 20, 5, "cwX");
+    assert_eq!(backend.lines(), ["X de", "f", "", "", "-- INSERT --"]);
+    assert_eq!(editor.registers.get('"').unwrap().text, "abc");
+    let (_, backend) = run_keys(&lines, 20, 5, "ll2cwX\x1b");
+    assert_eq!(backend.lines(), ["abX", "f", "", "", ""]);
+    let (_, backend) = run_keys(&lines, 20, 5, "\"aSX\x1bj\"ap");
+    assert_eq!(backend.lines(), ["X", "f", "abc de", "", ""]);
+    // the change and the text typed are undone at once
+    let (_, backend) = run_keys(&lines, 20, 5, "lCX\ry\x1bu");
+    assert_eq!(backend.lines(), ["abc de", "f", "", "", ""]);
+}
+
+#[test]
+fn words_are_letters_or_punctuation() {
+    let lines = ["foo.bar(baz)", "x"];
+    let (_, backend) = run_keys(&lines, 20, 4, "e");
+    assert_eq!(backend.cursor(), (2, 0));
+    let (_, backend) = run_keys(&lines, 20, 4, "ee");
+    assert_eq!(backend.cursor(), (3, 0));
+    let (_, backend) = run_keys(&lines, 20, 4, "3w");
+    assert_eq!(backend.cursor(), (7, 0));
+    let (_, backend) = run_keys(&lines, 20, 4, "$b");
+    assert_eq!(backend.cursor(), (8, 0));
+    let (_, backend) = run_keys(&lines, 20, 4, "$2b");
+    assert_eq!(backend.cursor(), (7, 0));
+
+    let (_, backend) = run_keys(&lines, 20, 4, "cwX\x1b");
+    assert_eq!(backend.lines()[..2], ["X.bar(baz)", "x"]);
+    let (_, backend) = run_keys(&lines, 20, 4, "ceX\x1b");
+    assert_eq!(backend.lines()[..2], ["X.bar(baz)", "x"]);
+    let (_, backend) = run_keys(&lines, 20, 4, "3cwX\x1b");
+    assert_eq!(backend.lines()[..2], ["X(baz)", "x"]);
+    let (_, backend) = run_keys(&["foo.bar"], 20, 3, "llcwX\x1b");
+    assert_eq!(backend.lines()[0], "foX.bar");
+    let (_, backend) = run_keys(&["foo.bar"], 20, 3, "dw");
+    assert_eq!(backend.lines()[0], ".bar");
+}
+
+#[test]
+fn dot_repeats_the_last_change() {
+    let lines = ["one two three four five", "six"];
+    let (_, backend) = run_keys(&lines, 30, 4, "dw.");
+    assert_eq!(backend.lines(), ["three four five", "six", "", ""]);
+    // a count replaces the count of the change
+    let (_, backend) = run_keys(&lines, 30, 4, "2dw2.");
+    assert_eq!(backend.lines(), ["five", "six", "", ""]);
+    // an insert is repeated with its text, a change with the text typed
+    let (_, backend) = run_keys(&lines, 30, 4, "iab\x1bj.");
+    assert_eq!(backend.lines(), ["abone two three four five", "siabx", "", ""]);
+    let (_, backend) = run_keys(&lines, 30, 4, "cwX\x1bj0.");
+    assert_eq!(backend.lines(), ["X two three four five", "X", "", ""]);
+    // moving and yanking are not changes
+    let (_, backend) = run_keys(&lines, 30, 4, "xjyl.");
+    assert_eq!(backend.lines(), ["ne two three four five", "ix", "", ""]);
+}
+
+#[test]
+fn replace_join_and_switch_case() {
+    let lines = ["abcdef", "  gh", "", "ij", ")k"];
+    let (_, backend) = run_keys(&lines, 20, 7, "2rxll.");
+    assert_eq!(backend.lines()[0], "xxcxxf");
+    assert_eq!(backend.cursor(), (4, 0));
+    let (_, backend) = run_keys(&lines, 20, 7, "2rxll3.");
+    assert_eq!(backend.lines()[0], "xxcxxx");
+    assert_eq!(backend.cursor(), (5, 0));
+    // too few characters left, and a line break
+    let (_, backend) = run_keys(&lines, 20, 7, "l9rx3r\r");
+    assert_eq!(backend.lines()[..3], ["a", "ef", "  gh"]);
+    let (_, backend) = run_keys(&lines, 20, 7, "rxu");
+    assert_eq!(backend.lines()[0], "abcdef");
+
+    let (_, backend) = run_keys(&lines, 20, 7, "J");
+    assert_eq!(backend.lines()[..2], ["abcdef gh", ""]);
+    assert_eq!(backend.cursor(), (6, 0));
+    let (_, backend) = run_keys(&lines, 20, 7, "4Jj.");
+    assert_eq!(backend.lines()[..3], ["abcdef gh ij", ")k", ""]);
+    let (_, backend) = run_keys(&lines, 20, 7, "3jJ");
+    assert_eq!(backend.lines()[3], "ij)k");
+    let (_, backend) = run_keys(&lines, 20, 7, "3Ju");
+    assert_eq!(backend.lines()[..3], ["abcdef", "  gh", ""]);
+
+    let (_, backend) = run_keys(&["aBc dE"], 20, 3, "~2.");
+    assert_eq!(backend.lines()[0], "AbC dE");
+    assert_eq!(backend.cursor(), (3, 0));
+    let (_, backend) = run_keys(&["aBc dE"], 20, 3, "$9~u");
+    assert_eq!(backend.lines()[0], "aBc dE");
+    let (_, backend) = run_keys(&["aBc dE"], 20, 3, "$h9~");
+    assert_eq!(backend.lines()[0], "aBc De");
+    assert_eq!(backend.cursor(), (5, 0));
+}
+
+#[test]
+fn macros_are_recorded_into_registers() {
+    let lines = ["ab", "cd", "ef", "gh"];
+    let (_, backend) = run_keys(&lines, 30, 6, "qaxj");
+    assert_eq!(backend.lines(), ["b", "cd", "ef", "gh", "", "recording @a"]);
+    let (_, backend) = run_keys(&lines, 30, 6, "qaiX");
+    assert_eq!(backend.lines(), ["Xab", "cd", "ef", "gh", "", "-- INSERT --recording @a"]);
+    let (editor, backend) = run_keys(&lines, 30, 6, "qaxjq2@a@@");
+    assert_eq!(backend.lines(), ["b", "d", "f", "h", "", ""]);
+    assert_eq!(editor.registers.get('a').unwrap().text, "xj");
+    // a macro put, edited and yanked back
+    let (_, backend) = run_keys(&lines, 30, 6, "qaxqj\"apaj\x1b0l\"ay2ldd@a");
+    assert_eq!(backend.lines(), ["b", "f", "gh", "", "", ""]);
+}
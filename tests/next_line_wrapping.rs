@@ -1,5 +1,5 @@
 //! Unit tests for NextLine behavior with wrapped lines.
-use evi::editor::{Editor, TerminalSize};
+use evi::editor::Editor;
 use evi::buffer::Buffer;
 use evi::command::commands::move_cursor::NextLine;
 use evi::command::base::Command;
@@ -8,7 +8,7 @@ fn setup_editor(lines: &[&str], width: u16, height: u16) -> Editor {
     let mut editor = Editor::new();
     editor.resize_terminal(width, height);
     let mut buf = Buffer::new();
-    for &l in lines { buf.lines.push(l.to_string()); }
+    for &l in lines { buf.push_line(l); }
     editor.buffer = buf;
     editor
 }